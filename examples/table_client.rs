@@ -0,0 +1,101 @@
+//! En enkel klient for bordene, som sjekker eller synker til hendene er
+//! ferdige. Lag et bord og start to klienter:
+//!
+//! ```sh
+//! curl -X POST localhost:8080/api/v1/tables
+//! cargo run --example table_client -- <id> Alice
+//! cargo run --example table_client -- <id> Bob
+//! ```
+//!
+//! Plass 1 starter hendene. Klienten går etter tre hender, eller
+//! `TABLE_HANDS` hvis den er satt.
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let table = args.next().ok_or("usage: table_client <table id> [name]")?;
+    let name = args.next().unwrap_or_else(|| "bot".into());
+    let host = std::env::var("TABLE_HOST").unwrap_or_else(|_| "localhost:8080".into());
+    let hands: usize = match std::env::var("TABLE_HANDS") {
+        Ok(hands) => hands.parse()?,
+        Err(_) => 3,
+    };
+
+    let url = format!("ws://{host}/api/v1/tables/{table}/ws");
+    let (mut socket, _) = connect_async(url).await?;
+    let join = json!({ "type": "join", "name": name });
+    socket.send(Message::text(join.to_string())).await?;
+
+    let mut seat = 0;
+    let mut played = 0;
+    while let Some(message) = socket.next().await {
+        let Message::Text(text) = message? else {
+            continue;
+        };
+        let event: Value = serde_json::from_str(&text)?;
+
+        let reply = match event["type"].as_str() {
+            Some("joined") => {
+                seat = event["seat"].as_u64().unwrap_or_default();
+                println!("{name} sitter på plass {seat}");
+                None
+            }
+            Some("hole_cards") => {
+                println!("{name} fikk {}", event["cards"]);
+                None
+            }
+            Some("hand_finished") => {
+                played += 1;
+                println!("bordet: {}", event["board"]);
+                for winning in event["winnings"].as_array().into_iter().flatten() {
+                    println!("plass {} vant {}", winning["seat"], winning["amount"]);
+                }
+                if played == hands {
+                    break;
+                }
+                None
+            }
+            Some("error") => {
+                eprintln!("{}: {}", event["code"], event["detail"]);
+                None
+            }
+            Some("state") => next_move(&event, seat, played < hands),
+            _ => None,
+        };
+
+        if let Some(reply) = reply {
+            socket.send(Message::text(reply.to_string())).await?;
+        }
+    }
+
+    let leave = json!({ "type": "leave" });
+    socket.send(Message::text(leave.to_string())).await?;
+
+    Ok(())
+}
+
+/// Sjekker eller synker når det er vår tur, og starter neste hånd fra plass 1
+fn next_move(state: &Value, seat: u64, more: bool) -> Option<Value> {
+    let hand = &state["hand"];
+    if hand.is_null() {
+        let players = state["seats"].as_array().map_or(0, Vec::len);
+        return (seat == 1 && players >= 2 && more).then(|| json!({ "type": "start" }));
+    }
+    if hand["to_act"].as_u64() != Some(seat) {
+        return None;
+    }
+
+    let bet = state["seats"]
+        .as_array()?
+        .iter()
+        .find(|other| other["seat"].as_u64() == Some(seat))?["bet"]
+        .clone();
+    let action = match bet == hand["current_bet"] {
+        true => "check",
+        false => "call",
+    };
+    Some(json!({ "type": "action", "action": action }))
+}
@@ -2,22 +2,23 @@
 import { writable, get } from 'svelte/store';
 
 export const historyItems = writable([]);
-export const historyNextOffset = writable(0);
+export const historyNextCursor = writable(null);
 export const historyError = writable(null);
 
 const API_BASE = 'http://localhost:8080/api/v1';
 
-export async function fetchHistory(offset = 0) {
+export async function fetchHistory(cursor = null) {
   historyError.set(null);
 
   try {
-    const response = await fetch(`${API_BASE}/history?offset=${offset}`);
+    const query = cursor ? `?cursor=${encodeURIComponent(cursor)}` : '';
+    const response = await fetch(`${API_BASE}/history${query}`);
 
     if (!response.ok) throw new Error('Failed to fetch history');
 
     const data = await response.json();
 
-    if (offset === 0) {
+    if (cursor === null) {
       // Ved ny lasting erstatt items
       historyItems.set(data.items);
     } else {
@@ -25,8 +26,27 @@ export async function fetchHistory(offset = 0) {
       historyItems.update(items => [...items, ...data.items]);
     }
 
-    historyNextOffset.set(data.next_offset ?? null);
+    historyNextCursor.set(data.next_cursor ?? null);
   } catch (err) {
     historyError.set(err.message);
   }
 }
+
+// Nye visninger kommer fra strømmen og legges øverst. Returnerer en funksjon
+// som lukker strømmen.
+export function subscribeHistory() {
+  const source = new EventSource(`${API_BASE}/history/stream`);
+
+  source.addEventListener('history', ({ data }) => {
+    const item = JSON.parse(data);
+    historyItems.update(items => [
+      item,
+      ...items.filter(other => other.deck !== item.deck || other.offset !== item.offset)
+    ]);
+  });
+
+  // Vi gikk glipp av elementer, så listen hentes på nytt
+  source.addEventListener('lagged', () => fetchHistory());
+
+  return () => source.close();
+}
@@ -0,0 +1,96 @@
+// src/lib/stores/table.js
+import { writable } from 'svelte/store';
+
+export const currentTable = writable(null);
+export const seat = writable(null);
+export const tableState = writable(null);
+export const holeCards = writable(null);
+export const lastResult = writable(null);
+export const error = writable(null);
+
+const API_BASE = 'http://localhost:8080/api/v1';
+const WS_BASE = API_BASE.replace(/^http/, 'ws');
+
+let socket = null;
+
+export async function createTable(options = {}) {
+  error.set(null);
+
+  try {
+    const response = await fetch(`${API_BASE}/tables`, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify(options)
+    });
+
+    if (!response.ok) throw new Error('Failed to create table');
+
+    const { id } = await response.json();
+    currentTable.set(id);
+    return id;
+  } catch (err) {
+    error.set(err.message);
+    throw err;
+  }
+}
+
+export function joinTable(tableId, name) {
+  leaveTable();
+  error.set(null);
+  currentTable.set(tableId);
+
+  socket = new WebSocket(`${WS_BASE}/tables/${tableId}/ws`);
+  socket.onopen = () => send({ type: 'join', name });
+  socket.onclose = () => {
+    socket = null;
+    seat.set(null);
+  };
+  socket.onmessage = ({ data }) => {
+    const event = JSON.parse(data);
+
+    switch (event.type) {
+      case 'joined':
+        seat.set(event.seat);
+        break;
+      case 'state':
+        tableState.set(event);
+        break;
+      case 'hole_cards':
+        holeCards.set(event.cards);
+        lastResult.set(null);
+        break;
+      case 'hand_finished':
+        lastResult.set(event);
+        holeCards.set(null);
+        break;
+      case 'error':
+        error.set(event.detail);
+        break;
+    }
+  };
+}
+
+export function startHand() {
+  send({ type: 'start' });
+}
+
+export function act(action, to) {
+  error.set(null);
+  send(action === 'raise' ? { type: 'action', action, to } : { type: 'action', action });
+}
+
+export function leaveTable() {
+  if (!socket) return;
+  send({ type: 'leave' });
+  socket.close();
+  socket = null;
+  seat.set(null);
+  tableState.set(null);
+  holeCards.set(null);
+}
+
+function send(message) {
+  if (socket?.readyState === WebSocket.OPEN) {
+    socket.send(JSON.stringify(message));
+  }
+}
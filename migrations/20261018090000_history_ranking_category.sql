@@ -0,0 +1,5 @@
+ALTER TABLE history ADD COLUMN ranking_category TEXT;
+
+CREATE INDEX history_time ON history(time);
+CREATE INDEX history_deck_time ON history(deck, time);
+CREATE INDEX history_ranking_category_time ON history(ranking_category, time);
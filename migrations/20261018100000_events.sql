@@ -0,0 +1,20 @@
+-- Append-only logg over alt som skjer med kortstokkene. `history` er en
+-- projeksjon av `view`-hendelsene med siste visning per hånd.
+CREATE TABLE events(
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  kind TEXT NOT NULL,
+  time INTEGER NOT NULL,
+  deck NUMERIC,
+  offset INTEGER,
+  user_agent TEXT,
+  client_ip TEXT,
+  payload TEXT
+);
+
+CREATE INDEX events_time ON events(time);
+CREATE INDEX events_deck ON events(deck, offset);
+
+ALTER TABLE history ADD COLUMN views INTEGER NOT NULL DEFAULT 1;
+
+INSERT INTO events(kind, time, deck, offset)
+SELECT 'view', time, deck, offset FROM history;
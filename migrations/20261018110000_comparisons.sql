@@ -0,0 +1,10 @@
+-- Lagrede sammenligninger, slik at resultatet kan etterprøves senere.
+-- `hands`, `winners` og `decided_by` er JSON slik API-et returnerte dem.
+CREATE TABLE comparisons(
+  id NUMERIC PRIMARY KEY NOT NULL,
+  time INTEGER NOT NULL,
+  evaluator_version INTEGER NOT NULL,
+  hands TEXT NOT NULL,
+  winners TEXT NOT NULL,
+  decided_by TEXT
+);
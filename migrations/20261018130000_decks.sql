@@ -0,0 +1,9 @@
+-- Kortstokker laget med `POST /api/v1/decks`. Eldre kortstokker finnes bare
+-- som en UUID og stokkes med den.
+CREATE TABLE decks(
+  id NUMERIC PRIMARY KEY NOT NULL,
+  time INTEGER NOT NULL,
+  seed TEXT,
+  label TEXT,
+  notes TEXT
+);
@@ -0,0 +1,2 @@
+-- Kortene en kortstokk starter med, som en JSON-liste. Resten stokkes.
+ALTER TABLE decks ADD COLUMN cards TEXT;
@@ -0,0 +1,16 @@
+-- Femkorts draw. Spillet spilles av igjen fra kortstokken, så bare antall
+-- spillere og posisjonene hver plass kastet lagres.
+CREATE TABLE draw_games(
+  deck NUMERIC PRIMARY KEY NOT NULL,
+  time INTEGER NOT NULL,
+  players INTEGER NOT NULL
+);
+
+CREATE TABLE draw_discards(
+  deck NUMERIC NOT NULL REFERENCES draw_games(deck),
+  seat INTEGER NOT NULL,
+  time INTEGER NOT NULL,
+  -- JSON-liste med posisjoner i hånden
+  positions TEXT NOT NULL,
+  PRIMARY KEY (deck, seat)
+);
@@ -0,0 +1,23 @@
+-- Spillere og API-klienter. Nøklene lagres bare som SHA-256.
+CREATE TABLE users(
+  id NUMERIC PRIMARY KEY NOT NULL,
+  time INTEGER NOT NULL,
+  name TEXT NOT NULL
+);
+
+CREATE TABLE api_keys(
+  id NUMERIC PRIMARY KEY NOT NULL,
+  user_id NUMERIC NOT NULL REFERENCES users(id),
+  time INTEGER NOT NULL,
+  label TEXT,
+  key_hash TEXT NOT NULL UNIQUE,
+  revoked INTEGER
+);
+
+CREATE INDEX api_keys_user ON api_keys(user_id);
+
+-- Kortstokker og historikk uten eier hører til anonyme klienter
+ALTER TABLE decks ADD COLUMN owner NUMERIC REFERENCES users(id);
+ALTER TABLE history ADD COLUMN owner NUMERIC;
+
+CREATE INDEX history_owner_time ON history(owner, time);
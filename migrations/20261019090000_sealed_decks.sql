@@ -0,0 +1,2 @@
+-- Kortstokken til en hånd ved et bord er skjult til hånden er ferdig
+ALTER TABLE decks ADD COLUMN sealed BOOLEAN NOT NULL DEFAULT FALSE;
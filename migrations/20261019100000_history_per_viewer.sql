@@ -0,0 +1,23 @@
+-- Historikken hører til den som så hånden, så samme hånd kan ha én rad per
+-- bruker og én for anonyme klienter. SQLite kan ikke endre primærnøkkelen,
+-- så tabellen bygges på nytt.
+CREATE TABLE history_per_viewer(
+  deck NUMERIC NOT NULL,
+  offset INTEGER NOT NULL,
+  time DATETIME NOT NULL,
+  ranking_category TEXT,
+  views INTEGER NOT NULL DEFAULT 1,
+  owner NUMERIC
+);
+
+INSERT INTO history_per_viewer(deck, offset, time, ranking_category, views, owner)
+SELECT deck, offset, time, ranking_category, views, owner FROM history;
+
+DROP TABLE history;
+ALTER TABLE history_per_viewer RENAME TO history;
+
+CREATE UNIQUE INDEX history_view ON history(deck, offset, ifnull(owner, 0));
+CREATE INDEX history_time ON history(time);
+CREATE INDEX history_deck_time ON history(deck, time);
+CREATE INDEX history_ranking_category_time ON history(ranking_category, time);
+CREATE INDEX history_owner_time ON history(owner, time);
@@ -0,0 +1,2 @@
+-- Sammenligninger uten eier er gjort av anonyme klienter
+ALTER TABLE comparisons ADD COLUMN owner NUMERIC REFERENCES users(id);
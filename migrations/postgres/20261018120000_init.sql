@@ -0,0 +1,36 @@
+-- Samme skjema som SQLite-migrasjonene i `migrations`, samlet i én fil
+CREATE TABLE history(
+  deck UUID NOT NULL,
+  "offset" BIGINT NOT NULL,
+  time BIGINT NOT NULL,
+  ranking_category TEXT,
+  views BIGINT NOT NULL DEFAULT 1,
+  PRIMARY KEY (deck, "offset")
+);
+
+CREATE INDEX history_time ON history(time);
+CREATE INDEX history_deck_time ON history(deck, time);
+CREATE INDEX history_ranking_category_time ON history(ranking_category, time);
+
+CREATE TABLE events(
+  id BIGSERIAL PRIMARY KEY,
+  kind TEXT NOT NULL,
+  time BIGINT NOT NULL,
+  deck UUID,
+  "offset" BIGINT,
+  user_agent TEXT,
+  client_ip TEXT,
+  payload JSONB
+);
+
+CREATE INDEX events_time ON events(time);
+CREATE INDEX events_deck ON events(deck, "offset");
+
+CREATE TABLE comparisons(
+  id UUID PRIMARY KEY,
+  time BIGINT NOT NULL,
+  evaluator_version INTEGER NOT NULL,
+  hands JSONB NOT NULL,
+  winners JSONB NOT NULL,
+  decided_by JSONB
+);
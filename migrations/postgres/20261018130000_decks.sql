@@ -0,0 +1,7 @@
+CREATE TABLE decks(
+  id UUID PRIMARY KEY,
+  time BIGINT NOT NULL,
+  seed TEXT,
+  label TEXT,
+  notes TEXT
+);
@@ -0,0 +1,2 @@
+-- Kortene en kortstokk starter med. Resten stokkes.
+ALTER TABLE decks ADD COLUMN cards JSONB;
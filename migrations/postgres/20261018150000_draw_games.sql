@@ -0,0 +1,13 @@
+CREATE TABLE draw_games(
+  deck UUID PRIMARY KEY,
+  time BIGINT NOT NULL,
+  players INTEGER NOT NULL
+);
+
+CREATE TABLE draw_discards(
+  deck UUID NOT NULL REFERENCES draw_games(deck),
+  seat INTEGER NOT NULL,
+  time BIGINT NOT NULL,
+  positions JSONB NOT NULL,
+  PRIMARY KEY (deck, seat)
+);
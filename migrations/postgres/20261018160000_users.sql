@@ -0,0 +1,21 @@
+CREATE TABLE users(
+  id UUID PRIMARY KEY,
+  time BIGINT NOT NULL,
+  name TEXT NOT NULL
+);
+
+CREATE TABLE api_keys(
+  id UUID PRIMARY KEY,
+  user_id UUID NOT NULL REFERENCES users(id),
+  time BIGINT NOT NULL,
+  label TEXT,
+  key_hash TEXT NOT NULL UNIQUE,
+  revoked BIGINT
+);
+
+CREATE INDEX api_keys_user ON api_keys(user_id);
+
+ALTER TABLE decks ADD COLUMN owner UUID REFERENCES users(id);
+ALTER TABLE history ADD COLUMN owner UUID;
+
+CREATE INDEX history_owner_time ON history(owner, time);
@@ -0,0 +1,6 @@
+-- Historikken hører til den som så hånden, så samme hånd kan ha én rad per
+-- bruker og én for anonyme klienter
+ALTER TABLE history DROP CONSTRAINT history_pkey;
+
+CREATE UNIQUE INDEX history_view
+  ON history(deck, "offset", COALESCE(owner, '00000000-0000-0000-0000-000000000000'));
@@ -0,0 +1 @@
+ALTER TABLE comparisons ADD COLUMN owner UUID REFERENCES users(id);
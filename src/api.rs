@@ -1,34 +1,184 @@
+mod rate_limit;
 #[cfg(test)]
 mod test;
 mod v1;
 
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
 use axum::{
     Router,
-    extract::FromRef,
-    routing::{get, post},
+    extract::{DefaultBodyLimit, FromRef, Request},
+    middleware,
+    routing::{delete, get, post},
 };
-use sqlx::SqlitePool;
 
-use crate::DeckProvider;
+use tokio::sync::broadcast;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{Level, Span};
+
+use crate::{DeckProvider, HistoryEntry, Metrics, Repository, tables::Tables};
+
+pub use rate_limit::RateLimit;
+use rate_limit::RateLimiter;
+
+/// Så mange nye elementer i historikken kan en klient av strømmen henge
+/// etter før den får `lagged`
+const HISTORY_FEED_CAPACITY: usize = 256;
+
+/// Innstillinger for API-et som ikke hører til lagringen
+#[derive(Clone, Default)]
+pub struct RouterOptions {
+    /// Bearer-token for `/api/v1/admin`. Uten token er admin-endepunktene
+    /// stengt.
+    pub admin_token: Option<String>,
+    /// Deles med bakgrunnsoppgaver som også skal telles
+    pub metrics: Arc<Metrics>,
+    /// Begrensning per API-nøkkel, eller per IP-adresse uten nøkkel
+    pub rate_limit: Option<RateLimit>,
+    pub compare_limits: CompareLimits,
+    /// Proxyer som får oppgi klientens IP-adresse i `X-Forwarded-For` og
+    /// `X-Real-IP`. Ellers brukes adressen til motparten.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Grenser for `POST /api/v1/hands/compare`. Hendene og id-ene gjelder også
+/// hver linje i en batch.
+#[derive(Clone, Copy, Debug)]
+pub struct CompareLimits {
+    pub max_body_bytes: usize,
+    pub max_hands: usize,
+    /// I tegn
+    pub max_external_id_length: usize,
+}
+
+impl Default for CompareLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 64 * 1024,
+            // Flere hender enn dette kan ikke deles ut fra én kortstokk
+            max_hands: 10,
+            max_external_id_length: 100,
+        }
+    }
+}
 
 #[derive(Clone, FromRef)]
 struct AppState {
     deck_provider: Arc<DeckProvider>,
-    pool: SqlitePool,
+    repository: Arc<dyn Repository>,
+    metrics: Arc<Metrics>,
+    admin_token: v1::AdminToken,
+    tables: Arc<Tables>,
+    history_feed: broadcast::Sender<HistoryEntry>,
+    compare_limits: CompareLimits,
+    trusted_proxies: v1::TrustedProxies,
 }
 
-pub fn router(deck_provider: DeckProvider, pool: SqlitePool) -> Router {
+/// Begrensningen slår opp API-nøkkelen selv, siden den ligger utenfor rutene
+#[derive(Clone, FromRef)]
+struct RateLimitState {
+    limiter: Arc<RateLimiter>,
+    repository: Arc<dyn Repository>,
+    admin_token: v1::AdminToken,
+    trusted_proxies: v1::TrustedProxies,
+}
+
+pub fn router(
+    deck_provider: DeckProvider,
+    repository: Arc<dyn Repository>,
+    options: RouterOptions,
+) -> Router {
+    let deck_provider = Arc::new(deck_provider);
     let app_state = AppState {
-        deck_provider: Arc::new(deck_provider),
-        pool,
+        tables: Arc::new(Tables::new(
+            deck_provider.clone(),
+            repository.clone(),
+            Default::default(),
+        )),
+        deck_provider,
+        repository,
+        history_feed: broadcast::channel(HISTORY_FEED_CAPACITY).0,
+        metrics: options.metrics,
+        admin_token: v1::AdminToken::new(options.admin_token),
+        compare_limits: options.compare_limits,
+        trusted_proxies: v1::TrustedProxies::new(options.trusted_proxies),
     };
 
-    Router::new()
+    let mut router = Router::new()
         .route("/api/v1/decks", post(v1::create_deck))
         .route("/api/v1/decks/{deck_id}", get(v1::list_hands))
+        .route("/api/v1/decks/{deck_id}/info", get(v1::deck_info))
+        .route("/api/v1/decks/{deck_id}/cards", get(v1::deck_cards))
+        .route("/api/v1/decks/{deck_id}/deal", post(v1::deal_table))
+        .route(
+            "/api/v1/decks/{deck_id}/draw",
+            get(v1::draw_state).post(v1::start_draw),
+        )
+        .route("/api/v1/decks/{deck_id}/draw/{seat}", post(v1::discard))
+        .route("/api/v1/decks/{deck_id}/history", get(v1::deck_history))
+        .route("/api/v1/tables", post(v1::create_table))
+        .route("/api/v1/tables/{table_id}/ws", get(v1::table_socket))
         .route("/api/v1/history", get(v1::history))
-        .route("/api/v1/hands/compare", post(v1::compare_hands))
+        .route("/api/v1/history/stream", get(v1::history_stream))
+        .route(
+            "/api/v1/hands/compare",
+            post(v1::compare_hands)
+                .layer(DefaultBodyLimit::max(options.compare_limits.max_body_bytes)),
+        )
+        .route("/api/v1/comparisons/{id}", get(v1::get_comparison))
+        .route("/api/v1/hands/evaluate:batch", post(v1::evaluate_batch))
+        .route(
+            "/api/v1/admin/decks/{deck_id}/history",
+            delete(v1::purge_deck_history),
+        )
+        .route("/api/v1/admin/metrics", get(v1::metrics))
+        .route("/api/v1/admin/users", post(v1::create_user))
+        .route(
+            "/api/v1/admin/users/{user_id}/keys",
+            get(v1::list_api_keys).post(v1::create_api_key),
+        )
+        .route("/api/v1/admin/keys/{key_id}", delete(v1::revoke_api_key))
+        .route("/api/v1/me", get(v1::me));
+
+    if let Some(rate_limit) = options.rate_limit {
+        let state = RateLimitState {
+            limiter: Arc::new(RateLimiter::new(rate_limit)),
+            repository: app_state.repository.clone(),
+            admin_token: app_state.admin_token.clone(),
+            trusted_proxies: app_state.trusted_proxies.clone(),
+        };
+        router = router.layer(middleware::from_fn_with_state(state, v1::rate_limit));
+    }
+
+    // Siste lag er ytterst, så id-en settes før spennet lages
+    router
+        .layer(middleware::from_fn(v1::localize_errors))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
         .with_state(app_state)
 }
+
+/// Spennet til en forespørsel. Alt som logges mens den behandles får med
+/// `request_id`, som også sendes tilbake i `x-request-id`.
+fn request_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id,
+    )
+}
@@ -0,0 +1,179 @@
+//! Begrensning av antall forespørsler per klient med token bucket. Hver
+//! klient har en bøtte med plass til `burst` forespørsler, som fylles med
+//! `requests_per_second`.
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Så mange klienter holdes det styr på. Klienten som har vært borte lengst
+/// glemmes først.
+const MAX_CLIENTS: usize = 10_000;
+
+/// Hvor mange forespørsler en klient kan gjøre
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    /// Antall forespørsler som kan gjøres på en gang
+    pub burst: u32,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Plassen i `Buckets::recent`
+    used: u64,
+}
+
+#[derive(Default)]
+struct Buckets {
+    clients: HashMap<String, Bucket>,
+    /// Klientene etter når de sist gjorde en forespørsel, eldst først
+    recent: BTreeMap<u64, String>,
+    next_use: u64,
+}
+
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    max_clients: usize,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self::with_max_clients(limit, MAX_CLIENTS)
+    }
+
+    fn with_max_clients(limit: RateLimit, max_clients: usize) -> Self {
+        Self {
+            limit,
+            max_clients,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Bruker en forespørsel fra bøtta til klienten. Gir hvor lenge klienten
+    /// må vente når bøtta er tom.
+    pub fn check(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let RateLimit {
+            requests_per_second,
+            burst,
+        } = self.limit;
+        let burst = f64::from(burst);
+
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        let Buckets {
+            clients,
+            recent,
+            next_use,
+        } = &mut *buckets;
+        let used = *next_use;
+        *next_use += 1;
+
+        if !clients.contains_key(client)
+            && clients.len() >= self.max_clients
+            && let Some((_, oldest)) = recent.pop_first()
+        {
+            clients.remove(&oldest);
+        }
+        let bucket = clients.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            used,
+        });
+        recent.remove(&bucket.used);
+        recent.insert(used, client.to_string());
+        bucket.used = used;
+
+        let elapsed = now.saturating_duration_since(bucket.updated);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * requests_per_second).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / requests_per_second,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimit {
+            requests_per_second: 2.0,
+            burst: 3,
+        })
+    }
+
+    #[test]
+    fn test_allows_burst_then_rejects() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check("a", now), Ok(()));
+        }
+        assert_eq!(limiter.check("a", now), Err(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_refills_over_time() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for _ in 0..3 {
+            limiter.check("a", now).unwrap();
+        }
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(limiter.check("a", later), Ok(()));
+        assert_eq!(limiter.check("a", later), Ok(()));
+        assert!(limiter.check("a", later).is_err());
+
+        // Bøtta fylles aldri over `burst`
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert_eq!(limiter.check("a", much_later), Ok(()));
+        }
+        assert!(limiter.check("a", much_later).is_err());
+    }
+
+    #[test]
+    fn test_forgets_least_recently_seen_client() {
+        let limiter = RateLimiter::with_max_clients(limiter().limit, 2);
+        let now = Instant::now();
+        for client in ["a", "b"] {
+            for _ in 0..3 {
+                limiter.check(client, now).unwrap();
+            }
+        }
+        assert!(limiter.check("a", now).is_err());
+
+        // `b` er den som har vært borte lengst, og glemmes når `c` kommer
+        assert_eq!(limiter.check("c", now), Ok(()));
+        assert!(limiter.check("a", now).is_err());
+        assert_eq!(limiter.check("b", now), Ok(()));
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.clients.len(), 2);
+        assert_eq!(buckets.recent.len(), 2);
+    }
+
+    #[test]
+    fn test_clients_have_separate_buckets() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for _ in 0..3 {
+            limiter.check("a", now).unwrap();
+        }
+
+        assert!(limiter.check("a", now).is_err());
+        assert_eq!(limiter.check("b", now), Ok(()));
+    }
+}
@@ -1,19 +1,141 @@
 use super::*;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
 use axum::{
     Router,
     body::Body,
+    extract::ConnectInfo,
     http::{Request, StatusCode},
 };
 use serde_json::{Value, json};
 use tower::util::ServiceExt; // for oneshot
 use uuid::Uuid;
 
+#[cfg(feature = "postgres")]
+use crate::PostgresRepository;
+#[cfg(feature = "sqlite")]
+use crate::SqliteRepository;
+use crate::{EventKind, MemoryRepository, RouterOptions};
+
+/// Bruker Postgres når `TEST_POSTGRES_URL` er satt, og ellers SQLite i
+/// minnet. Hver test får sitt eget skjema i Postgres, så databasen bør være
+/// en som kan kastes. Uten SQLite kjøres testene mot `MemoryRepository`.
 async fn setup_router() -> Router {
+    router(Default::default(), test_repository().await, test_options())
+}
+
+async fn test_repository() -> Arc<dyn Repository> {
+    #[cfg(feature = "postgres")]
+    if let Ok(url) = std::env::var("TEST_POSTGRES_URL") {
+        return Arc::new(postgres_repository(&url).await);
+    }
+
+    local_repository().await
+}
+
+#[cfg(feature = "sqlite")]
+async fn local_repository() -> Arc<dyn Repository> {
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let repository = SqliteRepository::new(pool);
+    repository.migrate().await.unwrap();
+    Arc::new(repository)
+}
+
+#[cfg(not(feature = "sqlite"))]
+async fn local_repository() -> Arc<dyn Repository> {
+    Arc::new(MemoryRepository::new())
+}
+
+fn test_options() -> RouterOptions {
+    RouterOptions {
+        admin_token: Some(ADMIN_TOKEN.into()),
+        trusted_proxies: vec![PROXY.ip()],
+        ..Default::default()
+    }
+}
+
+const ADMIN_TOKEN: &str = "hemmelig";
+/// Motparten til forespørslene i testene
+const PROXY: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 40000);
+
+#[cfg(feature = "postgres")]
+async fn postgres_repository(url: &str) -> PostgresRepository {
+    use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+
+    let schema = format!("test_{}", Uuid::new_v4().simple());
+    let admin = PgPool::connect(url).await.unwrap();
+    sqlx::query(&format!("CREATE SCHEMA {schema}"))
+        .execute(&admin)
+        .await
+        .unwrap();
+    admin.close().await;
+
+    // Poolen holder på skjemaet, som slettes når testen er ferdig med den
+    let test_schema = Arc::new(TestSchema {
+        url: url.to_string(),
+        schema: schema.clone(),
+    });
+    let options: PgConnectOptions = url.parse().unwrap();
+    let pool = PgPoolOptions::new()
+        .after_connect(move |_, _| {
+            let _ = &test_schema;
+            Box::pin(async { Ok(()) })
+        })
+        .connect_with(options.options([("search_path", &schema)]))
+        .await
+        .unwrap();
+
+    let repository = PostgresRepository::new(pool);
+    repository.migrate().await.unwrap();
+    repository
+}
+
+/// Et skjema i Postgres som bare finnes så lenge en test bruker det
+#[cfg(feature = "postgres")]
+struct TestSchema {
+    url: String,
+    schema: String,
+}
+
+#[cfg(feature = "postgres")]
+impl Drop for TestSchema {
+    fn drop(&mut self) {
+        let url = self.url.clone();
+        let drop_schema = format!("DROP SCHEMA IF EXISTS {} CASCADE", self.schema);
+
+        // Drop kan ikke vente på testens runtime, så skjemaet slettes fra en
+        // egen tråd med sin egen
+        let result = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    let pool = sqlx::PgPool::connect(&url).await?;
+                    sqlx::query(&drop_schema).execute(&pool).await?;
+                    pool.close().await;
+                    Ok::<_, sqlx::Error>(())
+                })
+        })
+        .join();
+
+        if let Ok(Err(error)) = result {
+            eprintln!("could not drop {}: {error}", self.schema);
+        }
+    }
+}
+
+/// Alltid SQLite, for tester som ser direkte i databasen
+#[cfg(feature = "sqlite")]
+async fn setup_router_with_pool() -> (Router, sqlx::SqlitePool) {
     let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
-    sqlx::migrate!().run(&pool).await.unwrap();
+    let repository = SqliteRepository::new(pool.clone());
+    repository.migrate().await.unwrap();
 
-    let deck_provider = Default::default();
-    router(deck_provider, pool)
+    (
+        router(Default::default(), Arc::new(repository), test_options()),
+        pool,
+    )
 }
 
 async fn make_request(
@@ -21,18 +143,35 @@ async fn make_request(
     method: &str,
     uri: &str,
     json_body: Option<&Value>,
+) -> (StatusCode, Value) {
+    make_request_with_headers(app, method, uri, json_body, &[]).await
+}
+
+async fn make_request_with_headers(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    json_body: Option<&Value>,
+    headers: &[(&str, &str)],
 ) -> (StatusCode, Value) {
     let body = match json_body {
         Some(val) => Body::from(val.to_string()),
         None => Body::empty(),
     };
 
-    let mut builder = Request::builder().method(method).uri(uri);
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .extension(ConnectInfo(PROXY));
 
     if json_body.is_some() {
         builder = builder.header("Content-Type", "application/json");
     }
 
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+
     let request = builder.body(body).unwrap();
     let response = app.clone().oneshot(request).await.unwrap();
 
@@ -40,7 +179,12 @@ async fn make_request(
     let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
         .unwrap();
-    let json: Value = serde_json::from_slice(&bytes).expect("Invalid JSON response");
+    // 204 No Content har ingen body
+    let json: Value = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).expect("Invalid JSON response")
+    };
 
     (status, json)
 }
@@ -55,6 +199,112 @@ async fn test_create_deck_returns_uuid() {
     assert!(Uuid::parse_str(id_str).is_ok());
 }
 
+#[tokio::test]
+async fn test_create_deck_stores_label_notes_and_seed() {
+    let app = setup_router().await;
+    let body = json!({"seed": "kurs-1", "label": "Kurs", "notes": "Første øvelse"});
+    let (status, json) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["seed"], "kurs-1");
+
+    let uri = format!("/api/v1/decks/{}/info", json["id"].as_str().unwrap());
+    let (status, info) = make_request(&app, "GET", &uri, None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(info, json);
+    assert_eq!(info["label"], "Kurs");
+    assert_eq!(info["notes"], "Første øvelse");
+}
+
+#[tokio::test]
+async fn test_decks_with_same_seed_deal_same_hands() {
+    let app = setup_router().await;
+    let body = json!({"seed": "samme"});
+
+    let mut hands = Vec::new();
+    for _ in 0..2 {
+        let (_, deck) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+        let uri = format!("/api/v1/decks/{}?offset=10", deck["id"].as_str().unwrap());
+        let (_, json) = make_request(&app, "GET", &uri, None).await;
+        hands.push(json["hand"]["cards"].clone());
+    }
+
+    assert_eq!(hands[0], hands[1]);
+}
+
+#[tokio::test]
+async fn test_create_deck_rejects_invalid_fields() {
+    let app = setup_router().await;
+
+    let body = json!({"seed": ""});
+    let (status, json) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["code"], "invalid_body");
+    assert_eq!(json["errors"][0]["field"], "seed");
+
+    let body = json!({"label": "x".repeat(201)});
+    let (status, json) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["errors"][0]["field"], "label");
+}
+
+#[tokio::test]
+async fn test_deck_from_preset_deals_it_first_and_is_not_random() {
+    let app = setup_router().await;
+    let body = json!({"preset": "royal_flush"});
+    let (_, deck) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+
+    assert_eq!(deck["random"], false);
+
+    let uri = format!("/api/v1/decks/{}?offset=0", deck["id"].as_str().unwrap());
+    let (_, json) = make_request(&app, "GET", &uri, None).await;
+
+    assert_eq!(json["hand"]["description"], "Royal Flush in Spades");
+    assert_eq!(json["random"], false);
+}
+
+#[tokio::test]
+async fn test_deck_from_partial_order_shuffles_the_rest() {
+    let app = setup_router().await;
+    let cards = ["2k", "7r", "9h", "js", "ak"];
+    let body = json!({"cards": cards, "seed": "resten"});
+    let (_, deck) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    let id = deck["id"].as_str().unwrap();
+
+    let (_, first) = make_request(&app, "GET", &format!("/api/v1/decks/{id}"), None).await;
+    assert_eq!(first["hand"]["cards"], json!(cards));
+
+    let (_, json) = make_request(&app, "POST", "/api/v1/decks", None).await;
+    assert_eq!(json["random"], true);
+}
+
+#[tokio::test]
+async fn test_create_deck_rejects_invalid_order() {
+    let app = setup_router().await;
+
+    let body = json!({"cards": ["2k", "3k", "2k"]});
+    let (status, json) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["errors"][0]["field"], "cards[2]");
+
+    let body = json!({"cards": ["2k"], "preset": "sorted"});
+    let (status, json) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["errors"][0]["field"], "preset");
+}
+
+#[tokio::test]
+async fn test_unknown_deck_info_returns_not_found() {
+    let app = setup_router().await;
+    let uri = format!("/api/v1/decks/{}/info", Uuid::new_v4());
+
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["code"], "deck_not_found");
+}
+
 #[tokio::test]
 async fn test_list_hands_returns_five_cards_and_next_offset() {
     let app = setup_router().await;
@@ -76,211 +326,2057 @@ async fn test_list_hands_returns_five_cards_and_next_offset() {
 }
 
 #[tokio::test]
-async fn test_list_hands_invalid_offset_returns_error() {
+async fn test_list_hands_explains_hand() {
     let app = setup_router().await;
-    let deck_id = Uuid::new_v4();
-    let uri = format!("/api/v1/decks/{deck_id}?offset=1000");
-    let (status, json) = make_request(&app, "GET", &uri, None).await;
+    let uri = "/api/v1/decks/3b783e86-9390-495a-8cd0-e5a9a93032c0?offset=0";
+    let (_, json) = make_request(&app, "GET", uri, None).await;
 
-    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
-    let desc = json.get("description").unwrap().as_str().unwrap();
+    let hand = json.get("hand").unwrap();
 
-    assert!(desc.contains("Invalid offset"));
+    assert_eq!(hand["description"], "Royal Flush in Clubs");
+    assert_eq!(hand["primary_cards"].as_array().unwrap().len(), 5);
+    assert!(hand["kickers"].as_array().unwrap().is_empty());
 }
 
 #[tokio::test]
-async fn test_fetch_all_hands_pagination() {
+async fn test_list_hands_returns_best_five_of_seven() {
+    let app = setup_router().await;
+    let uri = "/api/v1/decks/3b783e86-9390-495a-8cd0-e5a9a93032c0?offset=0&count=7";
+    let (status, json) = make_request(&app, "GET", uri, None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["cards"].as_array().unwrap().len(), 7);
+    assert_eq!(json["hand"]["ranking_category"], "StraightFlush");
+    assert_eq!(json["next_offset"], 7);
+}
+
+#[tokio::test]
+async fn test_list_hands_only_gives_whole_deck_to_admin_and_owner() {
     let app = setup_router().await;
     let deck_id = Uuid::new_v4();
-    let mut offset = 0usize;
-    let mut all_cards = Vec::new();
+    let uri = format!("/api/v1/decks/{deck_id}?offset=0&count=52");
 
-    loop {
-        let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
-        let (_, json) = make_request(&app, "GET", &uri, None).await;
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(json["code"], "count_not_allowed");
+    assert_eq!(json["errors"][0]["field"], "count");
+    let (status, _) = make_request(
+        &app,
+        "GET",
+        &format!("/api/v1/decks/{deck_id}?count=8"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
 
-        let cards = json
-            .get("hand")
-            .unwrap()
-            .get("cards")
-            .unwrap()
-            .as_array()
-            .unwrap()
-            .clone();
-        all_cards.extend(cards);
+    let admin = format!("Bearer {ADMIN_TOKEN}");
+    let (status, json) =
+        make_request_with_headers(&app, "GET", &uri, None, &[("Authorization", &admin)]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["cards"].as_array().unwrap().len(), 52);
 
-        if let Some(next_offset) = json.get("next_offset").and_then(|v| v.as_u64()) {
-            offset = next_offset as usize;
-        } else {
-            break;
-        }
-    }
+    // Andre brukere kan heller ikke hente hele kortstokker uten eier
+    let kari = create_user_with_key(&app, "Kari").await;
+    let headers = [("Authorization", kari.as_str())];
+    let (status, _) = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
 
-    assert_eq!(all_cards.len(), 50);
+    let (_, deck) = make_request_with_headers(&app, "POST", "/api/v1/decks", None, &headers).await;
+    let uri = format!("/api/v1/decks/{}?count=52", deck["id"].as_str().unwrap());
+    let (status, _) = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+    assert_eq!(status, StatusCode::OK);
 }
 
 #[tokio::test]
-async fn test_compare_hands_single_winner() {
+async fn test_history_only_records_five_card_views() {
     let app = setup_router().await;
-    let payload = json!({
-        "hands": [
-            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
-            { "external_id": "b", "hand": ["2k", "3k", "4k", "5k", "6k"] }
-        ]
-    });
-
-    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+    let deck_id = Uuid::new_v4();
+    let uri = format!("/api/v1/decks/{deck_id}?offset=0");
+    let (_, hand) = make_request(&app, "GET", &uri, None).await;
+    let _ = make_request(&app, "GET", &format!("{uri}&count=7"), None).await;
+    let _ = make_request(
+        &app,
+        "GET",
+        &format!("/api/v1/decks/{deck_id}?offset=10&count=6"),
+        None,
+    )
+    .await;
 
-    assert_eq!(status, StatusCode::OK);
+    let (_, json) = make_request(&app, "GET", "/api/v1/history", None).await;
+    let items = json["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["offset"], 0);
+    assert_eq!(items[0]["views"], 1);
     assert_eq!(
-        json["winners"],
-        json!([{ "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] }])
+        items[0]["ranking_category"],
+        hand["hand"]["ranking_category"]
     );
 }
 
 #[tokio::test]
-async fn test_compare_hands_tie_between_two_winners() {
+async fn test_list_hands_returns_raw_cards_with_stride() {
     let app = setup_router().await;
-
-    let payload = serde_json::json!({
-        "hands": [
-            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] }, // Royal Flush
-            { "external_id": "b", "hand": ["as", "ks", "qs", "js", "ts"] }, // Royal Flush same rank, different suits
-            { "external_id": "c", "hand": ["2k", "3k", "4k", "5k", "6k"] }
-        ]
-    });
-
-    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+    let deck_id = Uuid::new_v4();
+    let uri = format!("/api/v1/decks/{deck_id}?offset=49&count=3&stride=1");
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
 
     assert_eq!(status, StatusCode::OK);
+    assert!(json.get("hand").is_none());
+    assert_eq!(json["cards"].as_array().unwrap().len(), 3);
+    assert_eq!(json["next_offset"], Value::Null);
 
-    // Expect two winners (tie)
-    let winners = json["winners"].as_array().unwrap();
-    assert_eq!(winners.len(), 2);
+    let uri = format!("/api/v1/decks/{deck_id}?offset=48&count=3&stride=1");
+    let (_, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(json["next_offset"], 49);
 
-    let external_ids: Vec<_> = winners
-        .iter()
-        .map(|w| w["external_id"].as_str().unwrap())
-        .collect();
-    assert!(external_ids.contains(&"a"));
-    assert!(external_ids.contains(&"b"));
+    let uri = format!("/api/v1/decks/{deck_id}?offset=50&count=3");
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["code"], "invalid_offset");
+
+    let uri = format!("/api/v1/decks/{deck_id}?count=0");
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["errors"][0]["field"], "count");
 }
 
 #[tokio::test]
-async fn test_compare_hands_empty_hands_list_returns_empty_winners() {
+async fn test_deck_cards_requires_admin_token() {
     let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+    let uri = format!("/api/v1/decks/{deck_id}/cards");
 
-    let payload = serde_json::json!({ "hands": [] });
-
-    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+    let (status, _) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
 
+    let auth = format!("Bearer {ADMIN_TOKEN}");
+    let headers = [("Authorization", auth.as_str())];
+    let (status, json) = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
     assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["random"], true);
 
-    assert!(json["winners"].as_array().unwrap().is_empty());
+    let cards = json["cards"].as_array().unwrap();
+    assert_eq!(cards.len(), 52);
+
+    let (_, hand) = make_request(
+        &app,
+        "GET",
+        &format!("/api/v1/decks/{deck_id}?count=4"),
+        None,
+    )
+    .await;
+    assert_eq!(hand["cards"].as_array().unwrap()[..], cards[..4]);
 }
 
 #[tokio::test]
-async fn test_compare_hands_invalid_hand_format_returns_error() {
+async fn test_deal_holdem_table_from_sorted_deck() {
     let app = setup_router().await;
+    let body = json!({"preset": "sorted"});
+    let (_, deck) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    let uri = format!(
+        "/api/v1/decks/{}/deal?players=2&variant=holdem",
+        deck["id"].as_str().unwrap()
+    );
 
-    // Hand with fewer than 5 cards
-    let payload = serde_json::json!({
-        "hands": [
-            { "external_id": "a", "hand": ["ah", "kh", "qh"] }
-        ]
-    });
-
-    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+    let (status, json) = make_request(&app, "POST", &uri, None).await;
 
-    let desc = json.get("description").unwrap().as_str().unwrap();
-    assert!(desc.contains("invalid length 3, expected an array of length 5"));
-    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["seats"][0]["hole_cards"], json!(["2k", "2h"]));
+    assert_eq!(json["seats"][1]["hole_cards"], json!(["2r", "2s"]));
+    assert_eq!(json["burned"], json!(["3k", "4k", "4h"]));
+    assert_eq!(json["board"]["flop"], json!(["3r", "3h", "3s"]));
+    assert_eq!(json["board"]["turn"], "4r");
+    assert_eq!(json["board"]["river"], "4s");
+    assert_eq!(json["seats"][0]["hand"]["ranking_category"], "FullHouse");
+    assert_eq!(json["winners"], json!([1, 2]));
+    assert_eq!(json["random"], false);
 }
 
 #[tokio::test]
-async fn test_history_returns_empty_when_no_entries_exist() {
+async fn test_deal_stud_from_sorted_deck() {
     let app = setup_router().await;
+    let body = json!({"preset": "sorted"});
+    let (_, deck) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    let uri = format!(
+        "/api/v1/decks/{}/deal?players=2&variant=stud",
+        deck["id"].as_str().unwrap()
+    );
 
-    let (status, json) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+    let (status, json) = make_request(&app, "POST", &uri, None).await;
 
     assert_eq!(status, StatusCode::OK);
-    assert_eq!(json["items"].as_array().unwrap().len(), 0);
-    assert!(json["next_offset"].is_null());
+    assert!(json.get("board").is_none());
+    assert_eq!(json["seats"][0]["hole_cards"], json!(["2k", "2h", "6k"]));
+    assert_eq!(
+        json["seats"][0]["up_cards"],
+        json!(["3k", "3s", "4h", "5r"])
+    );
+    // 3k er lavere enn 3r fordi kløver er laveste farge
+    assert_eq!(
+        json["streets"][0],
+        json!({"street": 3, "first_to_act": 1, "bring_in": true})
+    );
+    assert_eq!(json["streets"][1]["first_to_act"], 1);
+    assert_eq!(json["burned"], json!(["3h", "4r", "5k", "5s"]));
 }
 
 #[tokio::test]
-async fn test_history_returns_entries_after_list_hands_calls() {
+async fn test_draw_game_is_played_in_turn_and_replayed() {
     let app = setup_router().await;
-    let deck_id = Uuid::new_v4();
+    let body = json!({"preset": "sorted"});
+    let (_, deck) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    let uri = format!("/api/v1/decks/{}/draw", deck["id"].as_str().unwrap());
 
-    // Gjør noen kall som skriver til history via list_hands
-    for offset in [0, 5, 10] {
-        let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
-        let (_status, _json) = make_request(&app, "GET", &uri, None).await;
-    }
+    let (status, json) = make_request(&app, "POST", &format!("{uri}?players=2"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json["seats"][0]["cards"],
+        json!(["2k", "2h", "3k", "3h", "4k"])
+    );
+    assert_eq!(json["next_seat"], 1);
 
-    // Nå skal history returnere 3 entries
-    let (status, json) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+    let (status, _) = make_request(&app, "POST", &format!("{uri}?players=2"), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
 
-    assert_eq!(status, StatusCode::OK);
+    let discard = json!({"discard": [0]});
+    let (status, json) = make_request(&app, "POST", &format!("{uri}/2"), Some(&discard)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(json["code"], "not_your_turn");
 
-    let items = json["items"].as_array().unwrap();
-    assert_eq!(items.len(), 3);
+    let invalid = json!({"discard": [0, 0]});
+    let (status, json) = make_request(&app, "POST", &format!("{uri}/1"), Some(&invalid)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["errors"][0]["field"], "discard[1]");
 
-    let seen_offsets: Vec<_> = items
-        .iter()
-        .map(|item| item["offset"].as_u64().unwrap())
-        .collect();
-    assert!(seen_offsets.contains(&0));
-    assert!(seen_offsets.contains(&5));
-    assert!(seen_offsets.contains(&10));
+    let (_, json) = make_request(&app, "POST", &format!("{uri}/1"), Some(&discard)).await;
+    assert_eq!(
+        json["seats"][0]["cards"],
+        json!(["4h", "2h", "3k", "3h", "4k"])
+    );
+    assert_eq!(json["seats"][0]["discarded"], json!(["2k"]));
+    assert_eq!(json["next_seat"], 2);
+
+    let keep = json!({"discard": []});
+    let (_, finished) = make_request(&app, "POST", &format!("{uri}/2"), Some(&keep)).await;
+    assert_eq!(finished["finished"], true);
+    assert_eq!(finished["winners"], json!([1]));
+    assert_eq!(finished["seats"][1]["place"], 2);
+
+    let (_, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(json, finished);
 }
 
 #[tokio::test]
-async fn test_history_pagination_next_offset_set_correctly() {
+async fn test_draw_not_started_returns_not_found() {
     let app = setup_router().await;
+    let uri = format!("/api/v1/decks/{}/draw", Uuid::new_v4());
 
-    // 18 kall --> skal føre til next_offset
-    for deck_id in [Uuid::new_v4(), Uuid::new_v4()] {
-        for offset in (0..45).step_by(5) {
-            let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
-            let (_status, _json) = make_request(&app, "GET", &uri, None).await;
-        }
-    }
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
 
-    let (status, json) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["code"], "draw_not_found");
+}
 
-    assert_eq!(status, StatusCode::OK);
+type TableSocket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
 
-    let items = json["items"].as_array().unwrap();
-    assert_eq!(items.len(), 10); // Page size
-    assert_eq!(json["next_offset"].as_u64().unwrap(), 10);
+/// Bordene trenger en ekte forbindelse, så de testes mot en server
+async fn serve(app: Router) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    address
 }
 
-#[tokio::test]
-async fn test_history_overwrites_existing_entry_with_new_time() {
-    let app = setup_router().await;
-    let deck_id = Uuid::new_v4();
+async fn join_table(address: std::net::SocketAddr, table: &str, name: &str) -> TableSocket {
+    let url = format!("ws://{address}/api/v1/tables/{table}/ws");
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    send_message(&mut socket, json!({ "type": "join", "name": name })).await;
+    socket
+}
 
-    // Første kall til offset 0
+async fn send_message(socket: &mut TableSocket, message: Value) {
+    use futures_util::SinkExt;
+
+    let message = tokio_tungstenite::tungstenite::Message::text(message.to_string());
+    socket.send(message).await.unwrap();
+}
+
+/// Neste hendelse av typen `kind`. Andre hendelser hoppes over.
+async fn next_event(socket: &mut TableSocket, kind: &str) -> Value {
+    use futures_util::StreamExt;
+
+    let wait = async {
+        loop {
+            let message = socket.next().await.unwrap().unwrap();
+            let Ok(text) = message.to_text() else {
+                continue;
+            };
+            let event: Value = serde_json::from_str(text).unwrap();
+            if event["type"] == kind {
+                return event;
+            }
+        }
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(5), wait)
+        .await
+        .unwrap_or_else(|_| panic!("no {kind} event"))
+}
+
+#[tokio::test]
+async fn test_table_plays_a_full_hand_over_websockets() {
+    let app = setup_router().await;
+    let (status, table) = make_request(&app, "POST", "/api/v1/tables", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(table["big_blind"], 2);
+    let table_id = table["id"].as_str().unwrap();
+    let address = serve(app.clone()).await;
+
+    let mut alice = join_table(address, table_id, "Alice").await;
+    assert_eq!(next_event(&mut alice, "joined").await["seat"], 1);
+    let mut bob = join_table(address, table_id, "Bob").await;
+    assert_eq!(next_event(&mut bob, "joined").await["seat"], 2);
+
+    send_message(&mut alice, json!({ "type": "start" })).await;
+    let alice_cards = next_event(&mut alice, "hole_cards").await["cards"].clone();
+    let bob_cards = next_event(&mut bob, "hole_cards").await["cards"].clone();
+    assert_eq!(alice_cards.as_array().unwrap().len(), 2);
+    assert_ne!(alice_cards, bob_cards);
+
+    // Med to spillere har knappen small blind og handler først
+    send_message(&mut bob, json!({ "type": "action", "action": "check" })).await;
+    let error = next_event(&mut bob, "error").await;
+    assert_eq!(error["code"], "not_your_turn");
+
+    let finished = loop {
+        let event = next_event(&mut alice, "state").await;
+        let Some(to_act) = event["hand"]["to_act"].as_u64() else {
+            break next_event(&mut bob, "hand_finished").await;
+        };
+        // Kortstokken vises ikke mens hånden spilles
+        assert!(event["deck"].is_null());
+        let seat = &event["seats"][to_act as usize - 1];
+        let action = match seat["bet"] == event["hand"]["current_bet"] {
+            true => "check",
+            false => "call",
+        };
+        let socket = if to_act == 1 { &mut alice } else { &mut bob };
+        send_message(socket, json!({ "type": "action", "action": action })).await;
+    };
+
+    assert_eq!(finished["board"].as_array().unwrap().len(), 5);
+    assert_eq!(finished["shown"][0]["hole_cards"], alice_cards);
+    let won: u64 = finished["winnings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|winning| winning["amount"].as_u64().unwrap())
+        .sum();
+    assert_eq!(won, 4);
+
+    // Hånden ble spilt med en lagret kortstokk
+    let uri = format!("/api/v1/decks/{}/info", finished["deck"].as_str().unwrap());
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["label"], format!("table {table_id}"));
+}
+
+#[tokio::test]
+async fn test_table_deck_is_hidden_until_hand_is_finished() {
+    let repository = Arc::new(MemoryRepository::new());
+    let app = router(Default::default(), repository.clone(), test_options());
+    let (_, table) = make_request(&app, "POST", "/api/v1/tables", None).await;
+    let table_id = table["id"].as_str().unwrap();
+    let address = serve(app.clone()).await;
+
+    let mut alice = join_table(address, table_id, "Alice").await;
+    next_event(&mut alice, "joined").await;
+    let mut bob = join_table(address, table_id, "Bob").await;
+    next_event(&mut bob, "joined").await;
+    send_message(&mut alice, json!({ "type": "start" })).await;
+    next_event(&mut alice, "hole_cards").await;
+    let state = next_event(&mut alice, "state").await;
+    assert!(state["deck"].is_null());
+
+    let deck = repository
+        .events()
+        .into_iter()
+        .find(|event| event.kind == EventKind::Deal)
+        .and_then(|event| event.deck)
+        .unwrap();
+    for (method, uri) in [
+        ("GET", format!("/api/v1/decks/{deck}?count=52")),
+        ("GET", format!("/api/v1/decks/{deck}/info")),
+        ("POST", format!("/api/v1/decks/{deck}/deal?players=2")),
+        ("POST", format!("/api/v1/decks/{deck}/draw?players=2")),
+    ] {
+        let (status, json) = make_request(&app, method, &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+        assert_eq!(json["code"], "deck_not_found");
+    }
+
+    // Knappen handler først med to spillere
+    send_message(&mut alice, json!({ "type": "action", "action": "fold" })).await;
+    let finished = next_event(&mut bob, "hand_finished").await;
+    assert_eq!(finished["deck"], deck.to_string());
+    let state = next_event(&mut bob, "state").await;
+    assert_eq!(state["deck"], deck.to_string());
+
+    let uri = format!("/api/v1/decks/{deck}?count=7");
+    let (status, _) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_table_socket_for_unknown_table_returns_not_found() {
+    let address = serve(setup_router().await).await;
+    let url = format!("ws://{address}/api/v1/tables/{}/ws", Uuid::new_v4());
+
+    let error = tokio_tungstenite::connect_async(url).await.unwrap_err();
+
+    let tokio_tungstenite::tungstenite::Error::Http(response) = error else {
+        panic!("expected an HTTP error, got {error}");
+    };
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_table_rejects_stack_below_big_blind() {
+    let app = setup_router().await;
+    let body = json!({ "big_blind": 10, "stack": 5 });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/tables", Some(&body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["errors"][0]["field"], "stack");
+}
+
+#[tokio::test]
+async fn test_deal_rejects_invalid_player_count() {
+    let app = setup_router().await;
+    let uri = format!("/api/v1/decks/{}/deal?players=11", Uuid::new_v4());
+
+    let (status, json) = make_request(&app, "POST", &uri, None).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["errors"][0]["field"], "players");
+
+    let uri = format!("/api/v1/decks/{}/deal?players=6", Uuid::new_v4());
+    let (status, json) = make_request(&app, "POST", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["seats"].as_array().unwrap().len(), 6);
+    assert_eq!(json["variant"], "holdem");
+}
+
+#[tokio::test]
+async fn test_list_hands_invalid_offset_returns_error() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+    let uri = format!("/api/v1/decks/{deck_id}?offset=1000");
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["code"], "invalid_offset");
+    assert_eq!(json["status"], 422);
+    assert_eq!(json["errors"][0]["field"], "offset");
+
+    let detail = json.get("detail").unwrap().as_str().unwrap();
+    assert!(detail.contains("Invalid offset"));
+}
+
+#[tokio::test]
+async fn test_errors_are_problem_json() {
+    let app = setup_router().await;
+    let request = Request::builder()
+        .uri("/api/v1/decks/not-a-uuid")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Value = serde_json::from_slice(&bytes).unwrap();
+
+    assert_eq!(json["code"], "invalid_path");
+    assert_eq!(json["type"], "urn:pokerhaand:problem:invalid_path");
+    assert!(json["title"].is_string());
+}
+
+#[tokio::test]
+async fn test_responses_carry_request_id() {
+    let app = setup_router().await;
+
+    let request = Request::builder()
+        .uri("/api/v1/history")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let id = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(Uuid::parse_str(id).is_ok());
+
+    // En id fra klienten eller en proxy beholdes, også for feil
+    let request = Request::builder()
+        .uri(format!("/api/v1/decks/{}/info", Uuid::new_v4()))
+        .header("x-request-id", "fra-proxy")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["x-request-id"], "fra-proxy");
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_database_failure_returns_problem() {
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let repository = SqliteRepository::new(pool.clone());
+    let app = router(Default::default(), Arc::new(repository), test_options());
+    pool.close().await;
+
+    let uri = format!("/api/v1/decks/{}?offset=0", Uuid::new_v4());
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(json["code"], "database_error");
+}
+
+#[tokio::test]
+async fn test_list_hands_non_numeric_offset_returns_problem() {
+    let app = setup_router().await;
+    let uri = format!("/api/v1/decks/{}?offset=abc", Uuid::new_v4());
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["code"], "invalid_query");
+    assert_eq!(json["errors"][0]["field"], "offset");
+}
+
+#[tokio::test]
+async fn test_list_hands_is_localized_from_accept_language() {
+    let app = setup_router().await;
+    let headers = [("Accept-Language", "nb-NO,nb;q=0.9,en;q=0.8")];
+
+    let uri = "/api/v1/decks/3b783e86-9390-495a-8cd0-e5a9a93032c0?offset=0";
+    let (_, json) = make_request_with_headers(&app, "GET", uri, None, &headers).await;
+
+    assert_eq!(json["hand"]["description"], "Royal flush i kløver");
+    assert_eq!(json["hand"]["ranking_category"], "StraightFlush");
+
+    let uri = format!("/api/v1/decks/{}?offset=1000", Uuid::new_v4());
+    let (status, json) = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["code"], "invalid_offset");
+    assert_eq!(json["title"], "Ugyldig offset");
+
+    let detail = json.get("detail").unwrap().as_str().unwrap();
+    assert!(detail.contains("Ugyldig offset"));
+}
+
+#[tokio::test]
+async fn test_validation_errors_are_localized() {
+    let app = setup_router().await;
+    let headers = [("Accept-Language", "nb")];
+
+    let uri = format!("/api/v1/decks/{}?offset=0&count=0", Uuid::new_v4());
+    let (status, json) = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["code"], "invalid_query");
+    assert_eq!(json["detail"], "count må være mellom 1 og 52");
+    assert_eq!(json["errors"][0]["field"], "count");
+    assert_eq!(json["errors"][0]["detail"], "count må være mellom 1 og 52");
+
+    let body = json!({ "seed": "" });
+    let (status, json) =
+        make_request_with_headers(&app, "POST", "/api/v1/decks", Some(&body), &headers).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["code"], "invalid_body");
+    assert_eq!(json["detail"], "seed kan ikke være tom");
+
+    let (_, json) = make_request(&app, "POST", "/api/v1/decks", Some(&body)).await;
+    assert_eq!(json["detail"], "seed must not be empty");
+}
+
+#[tokio::test]
+async fn test_fetch_all_hands_pagination() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+    let mut offset = 0usize;
+    let mut all_cards = Vec::new();
+
+    loop {
+        let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
+        let (_, json) = make_request(&app, "GET", &uri, None).await;
+
+        let cards = json
+            .get("hand")
+            .unwrap()
+            .get("cards")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .clone();
+        all_cards.extend(cards);
+
+        if let Some(next_offset) = json.get("next_offset").and_then(|v| v.as_u64()) {
+            offset = next_offset as usize;
+        } else {
+            break;
+        }
+    }
+
+    assert_eq!(all_cards.len(), 50);
+}
+
+#[tokio::test]
+async fn test_compare_hands_single_winner() {
+    let app = setup_router().await;
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3k", "4k", "5k", "6k"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json["winners"],
+        json!([{ "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] }])
+    );
+}
+
+#[tokio::test]
+async fn test_compare_hands_reports_deciding_kicker() {
+    let app = setup_router().await;
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["9h", "9r", "3s", "jk", "qs"] },
+            { "external_id": "b", "hand": ["9k", "9s", "3r", "th", "qr"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["winners"][0]["external_id"], "a");
+    assert_eq!(
+        json["decided_by"],
+        json!({ "kind": "kicker", "position": 2 })
+    );
+}
+
+#[tokio::test]
+async fn test_compare_hands_tie_between_two_winners() {
+    let app = setup_router().await;
+
+    let payload = serde_json::json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] }, // Royal Flush
+            { "external_id": "b", "hand": ["as", "ks", "qs", "js", "ts"] }, // Royal Flush same rank, different suits
+            { "external_id": "c", "hand": ["2k", "3k", "4k", "5k", "6k"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    // Expect two winners (tie)
+    let winners = json["winners"].as_array().unwrap();
+    assert_eq!(winners.len(), 2);
+
+    let external_ids: Vec<_> = winners
+        .iter()
+        .map(|w| w["external_id"].as_str().unwrap())
+        .collect();
+    assert!(external_ids.contains(&"a"));
+    assert!(external_ids.contains(&"b"));
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_compare_hands_is_stored_and_can_be_fetched() {
+    let (app, pool) = setup_router_with_pool().await;
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "as", "kh", "ks", "2k"] },
+            { "external_id": "b", "hand": ["ar", "ak", "kr", "kk", "3k"] }
+        ]
+    });
+
+    let (_, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+    let id = json["id"].as_str().unwrap().to_string();
+
+    let (status, stored) =
+        make_request(&app, "GET", &format!("/api/v1/comparisons/{id}"), None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stored["id"], id);
+    assert_eq!(stored["evaluator_version"], 1);
+    assert_eq!(stored["hands"], payload["hands"]);
+    assert_eq!(stored["winners"], json["winners"]);
+    assert_eq!(stored["decided_by"], json["decided_by"]);
+    assert_eq!(stored["reevaluation"]["winners"], json["winners"]);
+    assert_eq!(stored["reevaluation"]["differences"], json!([]));
+
+    // En lagret vinner som ikke stemmer med gjeldende evaluator flagges
+    assert_eq!(json["winners"][0]["external_id"], "b");
+    sqlx::query("UPDATE comparisons SET winners = ?, evaluator_version = 0")
+        .bind(json!([payload["hands"][0]]).to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (_, stored) = make_request(&app, "GET", &format!("/api/v1/comparisons/{id}"), None).await;
+
+    assert_eq!(stored["evaluator_version"], 0);
+    assert_eq!(stored["reevaluation"]["evaluator_version"], 1);
+    assert_eq!(stored["reevaluation"]["differences"], json!(["winners"]));
+    assert_eq!(stored["reevaluation"]["winners"], json["winners"]);
+}
+
+#[tokio::test]
+async fn test_get_unknown_comparison_returns_not_found() {
+    let app = setup_router().await;
+    let uri = format!("/api/v1/comparisons/{}", Uuid::new_v4());
+
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["code"], "comparison_not_found");
+}
+
+#[tokio::test]
+async fn test_compare_hands_omits_standings_by_default() {
+    let app = setup_router().await;
+    let payload = json!({
+        "hands": [{ "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] }]
+    });
+
+    let (_, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert!(json.get("standings").is_none());
+}
+
+#[tokio::test]
+async fn test_compare_hands_returns_standings_when_requested() {
+    let app = setup_router().await;
+    let payload = json!({
+        "hands": [
+            { "external_id": "c", "hand": ["2k", "4r", "7k", "jr", "kk"] },
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "d", "hand": ["9k", "9r", "3k", "jk", "qr"] },
+            { "external_id": "b", "hand": ["as", "ks", "qs", "js", "ts"] }
+        ]
+    });
+
+    let (status, json) = make_request(
+        &app,
+        "POST",
+        "/api/v1/hands/compare?standings=true",
+        Some(&payload),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["winners"].as_array().unwrap().len(), 2);
+
+    let standings = json["standings"].as_array().unwrap();
+    let places: Vec<_> = standings
+        .iter()
+        .map(|s| {
+            (
+                s["external_id"].as_str().unwrap(),
+                s["place"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+
+    assert_eq!(places, vec![("a", 1), ("b", 1), ("d", 3), ("c", 4)]);
+    assert_eq!(standings[2]["ranking_category"], "OnePair");
+    assert_eq!(
+        standings[2]["evaluation"]["description"],
+        "One Pair, Nines, Queen kicker"
+    );
+    assert_eq!(standings[2]["hand"], json!(["9k", "9r", "3k", "jk", "qr"]));
+}
+
+#[tokio::test]
+async fn test_compare_hands_rejects_card_shared_between_hands() {
+    let app = setup_router().await;
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3k", "ah", "5k", "6k"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["code"], "duplicate_cards");
+    assert_eq!(
+        json["errors"],
+        json!([{
+            "field": "hands[1].hand[2]",
+            "code": "duplicate_card",
+            "detail": "Card ah in hand \"b\" is already used in hand \"a\"",
+            "external_id": "b",
+            "card": "ah"
+        }])
+    );
+}
+
+#[tokio::test]
+async fn test_compare_hands_rejects_duplicate_card_within_hand() {
+    let app = setup_router().await;
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "ah", "ah", "ah", "ah"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["errors"].as_array().unwrap().len(), 4);
+    assert_eq!(json["errors"][0]["field"], "hands[0].hand[1]");
+}
+
+#[tokio::test]
+async fn test_compare_hands_empty_hands_list_returns_empty_winners() {
+    let app = setup_router().await;
+
+    let payload = serde_json::json!({ "hands": [] });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    assert!(json["winners"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_compare_hands_invalid_hand_format_returns_error() {
+    let app = setup_router().await;
+
+    // Hand with fewer than 5 cards
+    let payload = serde_json::json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    let detail = json.get("detail").unwrap().as_str().unwrap();
+    assert!(detail.contains("invalid length 3, expected an array of length 5"));
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["code"], "invalid_body");
+    assert_eq!(json["errors"][0]["field"], "hands[0].hand");
+}
+
+#[tokio::test]
+async fn test_compare_hands_malformed_json_returns_error() {
+    let app = setup_router().await;
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/hands/compare")
+        .header("Content-Type", "application/json")
+        .body(Body::from("{\"hands\": ["))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(json["code"], "malformed_json");
+}
+
+#[tokio::test]
+async fn test_compare_hands_without_content_type_returns_error() {
+    let app = setup_router().await;
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/hands/compare")
+        .body(Body::from(r#"{"hands": []}"#))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+/// Antall lagrede sammenligninger og hendelser, for å se at en avvist
+/// sammenligning aldri ble evaluert
+#[cfg(feature = "sqlite")]
+async fn stored_rows(pool: &sqlx::SqlitePool) -> (i64, i64) {
+    let comparisons = sqlx::query_scalar("SELECT COUNT(*) FROM comparisons")
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    let events = sqlx::query_scalar("SELECT COUNT(*) FROM events")
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+    (comparisons, events)
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_compare_hands_rejects_too_many_hands_before_evaluating() {
+    let (app, pool) = setup_router_with_pool().await;
+    // Samme kort i alle hendene, som ville gitt `duplicate_cards`
+    let hands: Vec<_> = (0..11)
+        .map(|i| json!({ "external_id": format!("h{i}"), "hand": ["ah", "kh", "qh", "jh", "th"] }))
+        .collect();
+    let payload = json!({ "hands": hands });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["code"], "too_many_hands");
+    assert_eq!(json["errors"][0]["field"], "hands");
+    assert_eq!(stored_rows(&pool).await, (0, 0));
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_compare_hands_stores_nothing_when_event_fails() {
+    let (app, pool) = setup_router_with_pool().await;
+    sqlx::query(
+        "CREATE TRIGGER events_fail BEFORE INSERT ON events BEGIN SELECT RAISE(ABORT, 'nei'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3r", "4s", "5h", "7k"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(json["code"], "database_error");
+    assert_eq!(stored_rows(&pool).await, (0, 0));
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_compare_hands_rejects_long_external_id_before_evaluating() {
+    let (app, pool) = setup_router_with_pool().await;
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b".repeat(101), "hand": ["ah", "kh", "qh", "jh", "th"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json["code"], "external_id_too_long");
+    assert_eq!(json["errors"][0]["field"], "hands[1].external_id");
+    assert_eq!(stored_rows(&pool).await, (0, 0));
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_compare_hands_rejects_oversized_body_before_evaluating() {
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let repository = SqliteRepository::new(pool.clone());
+    repository.migrate().await.unwrap();
+    let options = RouterOptions {
+        compare_limits: crate::CompareLimits {
+            max_body_bytes: 100,
+            ..Default::default()
+        },
+        ..test_options()
+    };
+    let app = router(Default::default(), Arc::new(repository), options);
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2h", "3h", "4h", "5h", "7s"] }
+        ]
+    });
+
+    let (status, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(json["code"], "body_too_large");
+    assert_eq!(stored_rows(&pool).await, (0, 0));
+}
+
+async fn evaluate_batch(app: &Router, body: String) -> (StatusCode, Vec<Value>) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/hands/evaluate:batch")
+        .header("Content-Type", "application/x-ndjson")
+        .body(Body::from(body))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let lines = bytes
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).expect("Invalid NDJSON line"))
+        .collect();
+
+    (status, lines)
+}
+
+#[tokio::test]
+async fn test_evaluate_batch_returns_result_per_group() {
+    let app = setup_router().await;
+    let body = [
+        json!({ "id": "t1", "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3k", "4k", "5k", "6k"] }
+        ]})
+        .to_string(),
+        String::new(),
+        "{ not json".to_string(),
+        json!({ "id": "t3", "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["ah", "3k", "4k", "5k", "6k"] }
+        ]})
+        .to_string(),
+    ]
+    .join("\n");
+
+    let (status, results) = evaluate_batch(&app, body).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(results.len(), 3);
+
+    assert_eq!(results[0]["id"], "t1");
+    assert_eq!(results[0]["line"], 1);
+    assert_eq!(results[0]["winners"][0]["external_id"], "a");
+
+    assert_eq!(results[1]["line"], 3);
+    assert_eq!(results[1]["error"]["code"], "malformed_json");
+
+    assert_eq!(results[2]["id"], "t3");
+    assert_eq!(results[2]["error"]["code"], "duplicate_cards");
+}
+
+#[tokio::test]
+async fn test_evaluate_batch_records_event_per_compared_group() {
+    let repository = Arc::new(MemoryRepository::new());
+    let app = router(Default::default(), repository.clone(), test_options());
+    let body = [
+        json!({ "id": "t1", "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3k", "4k", "5k", "6k"] }
+        ]})
+        .to_string(),
+        "{ not json".to_string(),
+        json!({ "id": "t3", "hands": [
+            { "external_id": "c", "hand": ["2k", "3r", "4s", "5h", "7k"] },
+            { "external_id": "d", "hand": ["ak", "ar", "4h", "5s", "7h"] },
+            { "external_id": "e", "hand": ["9k", "9r", "4k", "5r", "7s"] }
+        ]})
+        .to_string(),
+    ]
+    .join("\n");
+
+    let (status, _) = evaluate_batch(&app, body).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let payloads: Vec<_> = repository
+        .events()
+        .into_iter()
+        .filter(|event| event.kind == EventKind::Compare)
+        .map(|event| event.payload.unwrap())
+        .collect();
+    assert_eq!(
+        payloads,
+        [
+            json!({"line": 1, "hands": 2, "winners": ["a"]}),
+            json!({"line": 3, "hands": 3, "winners": ["d"]}),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_evaluate_batch_rejects_overlong_line_and_continues() {
+    let app = setup_router().await;
+    let overlong = format!("{{\"id\": \"{}\"}}", "x".repeat(2 * 1024 * 1024));
+    let valid = json!({ "id": "ok", "hands": [] }).to_string();
+
+    let (_, results) = evaluate_batch(&app, format!("{overlong}\n{valid}\n")).await;
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["error"]["code"], "invalid_body");
+    assert_eq!(results[1]["id"], "ok");
+    assert!(results[1]["winners"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_evaluate_batch_applies_compare_limits_per_line() {
+    let app = setup_router().await;
+    let long_id = "x".repeat(101);
+    let body = format!(
+        "{}\n{}\n",
+        json!({"id": "lang", "hands": [{ "external_id": long_id, "hand": ["ah", "kh", "qh", "jh", "th"] }]}),
+        json!({"id": "ok", "hands": [{ "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] }]}),
+    );
+
+    let (status, results) = evaluate_batch(&app, body).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(results[0]["error"]["code"], "external_id_too_long");
+    assert_eq!(results[1]["winners"][0]["external_id"], "a");
+}
+
+#[tokio::test]
+async fn test_history_returns_empty_when_no_entries_exist() {
+    let app = setup_router().await;
+
+    let (status, json) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["items"].as_array().unwrap().len(), 0);
+    assert!(json["next_offset"].is_null());
+}
+
+#[tokio::test]
+async fn test_history_returns_entries_after_list_hands_calls() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+
+    // Gjør noen kall som skriver til history via list_hands
+    for offset in [0, 5, 10] {
+        let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
+        let (_status, _json) = make_request(&app, "GET", &uri, None).await;
+    }
+
+    // Nå skal history returnere 3 entries
+    let (status, json) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let items = json["items"].as_array().unwrap();
+    assert_eq!(items.len(), 3);
+
+    let seen_offsets: Vec<_> = items
+        .iter()
+        .map(|item| item["offset"].as_u64().unwrap())
+        .collect();
+    assert!(seen_offsets.contains(&0));
+    assert!(seen_offsets.contains(&5));
+    assert!(seen_offsets.contains(&10));
+}
+
+#[tokio::test]
+async fn test_history_pagination_next_offset_set_correctly() {
+    let app = setup_router().await;
+
+    // 18 kall --> skal føre til next_offset
+    for deck_id in [Uuid::new_v4(), Uuid::new_v4()] {
+        for offset in (0..45).step_by(5) {
+            let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
+            let (_status, _json) = make_request(&app, "GET", &uri, None).await;
+        }
+    }
+
+    let (status, json) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let items = json["items"].as_array().unwrap();
+    assert_eq!(items.len(), 10); // Page size
+    assert_eq!(json["next_offset"].as_u64().unwrap(), 10);
+}
+
+#[tokio::test]
+async fn test_history_overwrites_existing_entry_with_new_time() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+
+    // Første kall til offset 0
+    let uri = format!("/api/v1/decks/{deck_id}?offset=0");
+    let _ = make_request(&app, "GET", &uri, None).await;
+
+    // Hent første timestamp
+    let (_, json1) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+    let first_time = json1["items"][0]["time"].as_u64().unwrap();
+
+    // Vent litt, gjør nytt kall til samme offset
+    tokio::time::sleep(std::time::Duration::from_millis(15)).await;
+    let _ = make_request(&app, "GET", &uri, None).await;
+
+    // Hent igjen, skal ha samme deck+offset, men nyere tid
+    let (_, json2) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+    let second_time = json2["items"][0]["time"].as_u64().unwrap();
+
+    assert_eq!(json2["items"].as_array().unwrap().len(), 1);
+    assert_eq!(json2["items"][0]["views"], 2);
+    assert!(
+        second_time > first_time,
+        "Expected time to be updated on second visit"
+    );
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_events_record_every_deal_view_and_compare() {
+    let (app, pool) = setup_router_with_pool().await;
+
+    let (_, json) = make_request(&app, "POST", "/api/v1/decks", None).await;
+    let deck_id = json["id"].as_str().unwrap().to_string();
+
+    let uri = format!("/api/v1/decks/{deck_id}?offset=0");
+    let headers = [
+        ("User-Agent", "test-agent"),
+        // Den første adressen er satt av klienten selv
+        ("X-Forwarded-For", "10.0.0.9, 10.0.0.1"),
+    ];
+    let _ = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+    let _ = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+
+    let body = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3r", "4s", "5h", "7k"] }
+        ]
+    });
+    let _ = make_request(&app, "POST", "/api/v1/hands/compare", Some(&body)).await;
+
+    #[derive(sqlx::FromRow)]
+    struct Event {
+        kind: String,
+        offset: Option<i64>,
+        user_agent: Option<String>,
+        client_ip: Option<String>,
+        payload: Option<String>,
+    }
+
+    let events: Vec<Event> = sqlx::query_as(
+        "SELECT kind, offset, user_agent, client_ip, payload FROM events ORDER BY id",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+
+    let kinds: Vec<_> = events.iter().map(|e| e.kind.as_str()).collect();
+    assert_eq!(kinds, ["deal", "view", "view", "compare"]);
+
+    let view = &events[1];
+    assert_eq!(view.offset, Some(0));
+    assert_eq!(view.user_agent.as_deref(), Some("test-agent"));
+    assert_eq!(view.client_ip.as_deref(), Some("10.0.0.1"));
+
+    let compare: Value = serde_json::from_str(events[3].payload.as_deref().unwrap()).unwrap();
+    assert_eq!(compare["hands"], 2);
+    assert_eq!(compare["winners"], json!(["a"]));
+
+    // Projeksjonen har fortsatt én rad per hånd
+    let (_, json) = make_request(&app, "GET", "/api/v1/history", None).await;
+    assert_eq!(json["items"].as_array().unwrap().len(), 1);
+    assert_eq!(json["items"][0]["views"], 2);
+}
+
+#[tokio::test]
+async fn test_history_filters_by_deck_offset_and_category() {
+    let app = setup_router().await;
+    let royal_deck = "3b783e86-9390-495a-8cd0-e5a9a93032c0";
+    let other_deck = Uuid::new_v4();
+
+    for uri in [
+        format!("/api/v1/decks/{royal_deck}?offset=0"),
+        format!("/api/v1/decks/{royal_deck}?offset=5"),
+        format!("/api/v1/decks/{other_deck}?offset=0"),
+        format!("/api/v1/decks/{other_deck}?offset=10"),
+    ] {
+        make_request(&app, "GET", &uri, None).await;
+    }
+
+    let uri = format!("/api/v1/history?deck={other_deck}");
+    let (_, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(json["items"].as_array().unwrap().len(), 2);
+
+    let (_, json) = make_request(&app, "GET", "/api/v1/history?hand_offset=0", None).await;
+    assert_eq!(json["items"].as_array().unwrap().len(), 2);
+
+    let uri = "/api/v1/history?category=StraightFlush";
+    let (_, json) = make_request(&app, "GET", uri, None).await;
+    let items = json["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["deck"], royal_deck);
+    assert_eq!(items[0]["ranking_category"], "StraightFlush");
+}
+
+#[tokio::test]
+async fn test_history_filters_by_time_range() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+    let before = chrono::Utc::now().timestamp_millis();
+
+    let uri = format!("/api/v1/decks/{deck_id}?offset=0");
+    make_request(&app, "GET", &uri, None).await;
+
+    let uri = format!("/api/v1/history?from={before}");
+    let (_, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(json["items"].as_array().unwrap().len(), 1);
+
+    let uri = format!("/api/v1/history?to={before}");
+    let (_, json) = make_request(&app, "GET", &uri, None).await;
+    assert!(json["items"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_deck_history_only_returns_that_deck() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+
+    for (deck, offset) in [(deck_id, 0), (deck_id, 5), (Uuid::new_v4(), 0)] {
+        let uri = format!("/api/v1/decks/{deck}?offset={offset}");
+        make_request(&app, "GET", &uri, None).await;
+    }
+
+    let uri = format!("/api/v1/decks/{deck_id}/history");
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let items = json["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|item| item["deck"] == deck_id.to_string()));
+}
+
+#[tokio::test]
+async fn test_history_rejects_unknown_category() {
+    let app = setup_router().await;
+
+    let (status, json) = make_request(&app, "GET", "/api/v1/history?category=Royal", None).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["code"], "invalid_query");
+    assert_eq!(json["errors"][0]["field"], "category");
+}
+
+fn history_ids(json: &Value) -> Vec<(String, u64)> {
+    json["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| {
+            (
+                item["deck"].as_str().unwrap().to_string(),
+                item["offset"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+/// Leser SSE-strømmen til neste hendelse som ikke er en kommentar
+async fn next_sse_event(body: &mut axum::body::BodyDataStream) -> (String, Value) {
+    use futures_util::StreamExt;
+
+    let mut buffer = String::new();
+    loop {
+        if let Some(end) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..end + 2).collect();
+            let field = |name: &str| {
+                event
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .map(str::to_string)
+            };
+            if let (Some(kind), Some(data)) = (field("event: "), field("data: ")) {
+                return (kind, serde_json::from_str(&data).unwrap());
+            }
+            continue;
+        }
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+            .await
+            .expect("no event in time")
+            .unwrap()
+            .unwrap();
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_history_stream_pushes_new_views() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+    let request = Request::builder()
+        .uri(format!("/api/v1/history/stream?deck={deck_id}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut body = response.into_body().into_data_stream();
+
+    // Filtreres bort
+    let uri = format!("/api/v1/decks/{}?offset=0", Uuid::new_v4());
+    make_request(&app, "GET", &uri, None).await;
+    for _ in 0..2 {
+        let uri = format!("/api/v1/decks/{deck_id}?offset=5");
+        make_request(&app, "GET", &uri, None).await;
+    }
+
+    for views in 1..=2 {
+        let (kind, item) = next_sse_event(&mut body).await;
+        assert_eq!(kind, "history");
+        assert_eq!(item["deck"], deck_id.to_string());
+        assert_eq!(item["offset"], 5);
+        assert_eq!(item["views"], views);
+    }
+}
+
+#[tokio::test]
+async fn test_history_stream_reports_lagging_clients() {
+    use axum::response::{IntoResponse, sse::Sse};
+    use tokio::sync::broadcast;
+
+    let (sender, receiver) = broadcast::channel(1);
+    let events = v1::history_events(receiver, Default::default());
+    for offset in 0..3 {
+        sender
+            .send(crate::HistoryEntry {
+                deck: Uuid::new_v4(),
+                offset,
+                time: 0,
+                views: 1,
+                ranking_category: None,
+                owner: None,
+            })
+            .unwrap();
+    }
+    drop(sender);
+    let mut body = Sse::new(events)
+        .into_response()
+        .into_body()
+        .into_data_stream();
+
+    let (kind, lagged) = next_sse_event(&mut body).await;
+    assert_eq!(kind, "lagged");
+    assert_eq!(lagged["skipped"], 2);
+    let (kind, item) = next_sse_event(&mut body).await;
+    assert_eq!(kind, "history");
+    assert_eq!(item["offset"], 2);
+}
+
+#[tokio::test]
+async fn test_history_cursor_pagination_is_stable_under_updates() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+
+    for offset in (0..=45).step_by(5).chain([47]) {
+        let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
+        make_request(&app, "GET", &uri, None).await;
+    }
+
+    let (_, first) = make_request(&app, "GET", "/api/v1/history", None).await;
+    let first_page = history_ids(&first);
+    assert_eq!(first_page.len(), 10);
+    assert!(first["prev_cursor"].is_null());
+
+    // Et nytt besøk flytter en rad fra side 2 til toppen av historikken
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
     let uri = format!("/api/v1/decks/{deck_id}?offset=0");
-    let _ = make_request(&app, "GET", &uri, None).await;
+    make_request(&app, "GET", &uri, None).await;
 
-    // Hent første timestamp
-    let (_, json1) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
-    let first_time = json1["items"][0]["time"].as_u64().unwrap();
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let uri = format!("/api/v1/history?cursor={cursor}");
+    let (status, second) = make_request(&app, "GET", &uri, None).await;
+    let second_page = history_ids(&second);
 
-    // Vent litt, gjør nytt kall til samme offset
-    tokio::time::sleep(std::time::Duration::from_millis(15)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second_page.len(), 0);
+    assert!(second["next_cursor"].is_null());
+    assert!(second_page.iter().all(|item| !first_page.contains(item)));
+}
+
+#[tokio::test]
+async fn test_history_cursor_pages_forwards_and_backwards() {
+    let app = setup_router().await;
+
+    for deck_id in [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()] {
+        for offset in (0..35).step_by(5) {
+            let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
+            make_request(&app, "GET", &uri, None).await;
+        }
+    }
+
+    let (_, first) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let (_, second) = make_request(
+        &app,
+        "GET",
+        &format!("/api/v1/history?cursor={cursor}"),
+        None,
+    )
+    .await;
+    let cursor = second["next_cursor"].as_str().unwrap();
+    let (_, third) = make_request(
+        &app,
+        "GET",
+        &format!("/api/v1/history?cursor={cursor}"),
+        None,
+    )
+    .await;
+
+    assert_eq!(history_ids(&second).len(), 10);
+    assert_eq!(history_ids(&third).len(), 1);
+    assert!(third["next_cursor"].is_null());
+    assert!(second["next_offset"].is_null());
+
+    let cursor = third["prev_cursor"].as_str().unwrap();
+    let (_, back) = make_request(
+        &app,
+        "GET",
+        &format!("/api/v1/history?cursor={cursor}"),
+        None,
+    )
+    .await;
+    assert_eq!(history_ids(&back), history_ids(&second));
+
+    let cursor = back["prev_cursor"].as_str().unwrap();
+    let (_, back) = make_request(
+        &app,
+        "GET",
+        &format!("/api/v1/history?cursor={cursor}"),
+        None,
+    )
+    .await;
+    assert_eq!(history_ids(&back), history_ids(&first));
+    assert!(back["prev_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn test_history_rejects_invalid_cursor() {
+    let app = setup_router().await;
+
+    let (status, json) = make_request(&app, "GET", "/api/v1/history?cursor=abc", None).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["code"], "invalid_cursor");
+}
+
+#[tokio::test]
+async fn test_router_runs_on_memory_repository() {
+    let repository = Arc::new(MemoryRepository::new());
+    let app = router(Default::default(), repository.clone(), test_options());
+
+    let (_, json) = make_request(&app, "POST", "/api/v1/decks", None).await;
+    let deck_id = json["id"].as_str().unwrap().to_string();
+    let uri = format!("/api/v1/decks/{deck_id}?offset=5");
     let _ = make_request(&app, "GET", &uri, None).await;
 
-    // Hent igjen, skal ha samme deck+offset, men nyere tid
-    let (_, json2) = make_request(&app, "GET", "/api/v1/history?offset=0", None).await;
-    let second_time = json2["items"][0]["time"].as_u64().unwrap();
+    let (status, json) = make_request(&app, "GET", "/api/v1/history", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["items"][0]["deck"], deck_id);
+    assert_eq!(json["items"][0]["offset"], 5);
 
-    assert_eq!(json2["items"].as_array().unwrap().len(), 1);
+    let payload = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3k", "4k", "5k", "6k"] }
+        ]
+    });
+    let (_, json) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&payload)).await;
+    let uri = format!("/api/v1/comparisons/{}", json["id"].as_str().unwrap());
+    let (status, json) = make_request(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["winners"][0]["external_id"], "a");
+
+    let kinds: Vec<_> = repository.events().iter().map(|e| e.kind).collect();
+    assert_eq!(
+        kinds,
+        [EventKind::Deal, EventKind::View, EventKind::Compare]
+    );
+}
+
+#[tokio::test]
+async fn test_admin_purges_history_for_one_deck() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+    let other_deck = Uuid::new_v4();
+
+    for deck in [deck_id, other_deck] {
+        for offset in [0, 5] {
+            let uri = format!("/api/v1/decks/{deck}?offset={offset}");
+            let _ = make_request(&app, "GET", &uri, None).await;
+        }
+    }
+
+    let uri = format!("/api/v1/admin/decks/{deck_id}/history");
+    let auth = format!("Bearer {ADMIN_TOKEN}");
+    let (status, json) =
+        make_request_with_headers(&app, "DELETE", &uri, None, &[("Authorization", &auth)]).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["deleted"], 2);
+
+    let (_, json) = make_request(&app, "GET", "/api/v1/history", None).await;
+    let items = json["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
     assert!(
-        second_time > first_time,
-        "Expected time to be updated on second visit"
+        items
+            .iter()
+            .all(|item| item["deck"] == other_deck.to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_admin_endpoints_require_token() {
+    let app = setup_router().await;
+    let uri = format!("/api/v1/admin/decks/{}/history", Uuid::new_v4());
+
+    let (status, json) = make_request(&app, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(json["code"], "unauthorized");
+
+    let headers = [("Authorization", "Bearer feil")];
+    let (status, _) = make_request_with_headers(&app, "DELETE", &uri, None, &headers).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Uten konfigurert token er endepunktene stengt
+    let app = router(
+        Default::default(),
+        Arc::new(MemoryRepository::new()),
+        Default::default(),
+    );
+    let headers = [("Authorization", "Bearer ")];
+    let (status, _) = make_request_with_headers(&app, "DELETE", &uri, None, &headers).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_admin_metrics_count_purged_history() {
+    let app = setup_router().await;
+    let deck_id = Uuid::new_v4();
+    let _ = make_request(&app, "GET", &format!("/api/v1/decks/{deck_id}"), None).await;
+
+    let auth = format!("Bearer {ADMIN_TOKEN}");
+    let headers = [("Authorization", auth.as_str())];
+    let uri = format!("/api/v1/admin/decks/{deck_id}/history");
+    let _ = make_request_with_headers(&app, "DELETE", &uri, None, &headers).await;
+
+    let request = Request::builder()
+        .uri("/api/v1/admin/metrics")
+        .header("Authorization", &auth)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(body.contains("pokerhaand_history_purged_total 1\n"));
+}
+
+/// Lager en bruker og en nøkkel til den med admin-endepunktene. Returnerer
+/// `Authorization`-headeren for nøkkelen.
+async fn create_user_with_key(app: &Router, name: &str) -> String {
+    let admin = format!("Bearer {ADMIN_TOKEN}");
+    let headers = [("Authorization", admin.as_str())];
+    let body = json!({"name": name});
+    let (status, user) =
+        make_request_with_headers(app, "POST", "/api/v1/admin/users", Some(&body), &headers).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let uri = format!("/api/v1/admin/users/{}/keys", user["id"].as_str().unwrap());
+    let (status, key) = make_request_with_headers(app, "POST", &uri, None, &headers).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    format!("Bearer {}", key["key"].as_str().unwrap())
+}
+
+#[tokio::test]
+async fn test_api_key_authenticates_its_user() {
+    let app = setup_router().await;
+    let admin = format!("Bearer {ADMIN_TOKEN}");
+    let headers = [("Authorization", admin.as_str())];
+    let body = json!({"name": "Kari"});
+    let (_, user) =
+        make_request_with_headers(&app, "POST", "/api/v1/admin/users", Some(&body), &headers).await;
+
+    let uri = format!("/api/v1/admin/users/{}/keys", user["id"].as_str().unwrap());
+    let body = json!({"label": "bot"});
+    let (status, key) = make_request_with_headers(&app, "POST", &uri, Some(&body), &headers).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(key["label"], "bot");
+    assert!(key["key"].as_str().unwrap().starts_with("pk_"));
+
+    let auth = format!("Bearer {}", key["key"].as_str().unwrap());
+    let (status, me) =
+        make_request_with_headers(&app, "GET", "/api/v1/me", None, &[("Authorization", &auth)])
+            .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me, user);
+
+    // Nøkkelen vises bare når den lages
+    let (status, keys) = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(keys["keys"][0]["id"], key["id"]);
+    assert!(keys["keys"][0].get("key").is_none());
+}
+
+#[tokio::test]
+async fn test_owned_deck_is_only_visible_to_owner() {
+    let app = setup_router().await;
+    let kari = create_user_with_key(&app, "Kari").await;
+    let ola = create_user_with_key(&app, "Ola").await;
+
+    let (_, deck) = make_request_with_headers(
+        &app,
+        "POST",
+        "/api/v1/decks",
+        None,
+        &[("Authorization", &kari)],
+    )
+    .await;
+    let deck_id = deck["id"].as_str().unwrap();
+
+    for uri in [
+        format!("/api/v1/decks/{deck_id}/info"),
+        format!("/api/v1/decks/{deck_id}"),
+    ] {
+        let (status, _) =
+            make_request_with_headers(&app, "GET", &uri, None, &[("Authorization", &kari)]).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, json) =
+            make_request_with_headers(&app, "GET", &uri, None, &[("Authorization", &ola)]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["code"], "deck_not_found");
+
+        let (status, _) = make_request(&app, "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn test_history_is_scoped_to_user() {
+    let app = setup_router().await;
+    let kari = create_user_with_key(&app, "Kari").await;
+    let headers = [("Authorization", kari.as_str())];
+
+    let (_, deck) = make_request_with_headers(&app, "POST", "/api/v1/decks", None, &headers).await;
+    let owned = deck["id"].as_str().unwrap().to_string();
+    let uri = format!("/api/v1/decks/{owned}");
+    let _ = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+    let anonymous = Uuid::new_v4().to_string();
+    let _ = make_request(&app, "GET", &format!("/api/v1/decks/{anonymous}"), None).await;
+
+    let (_, json) = make_request_with_headers(&app, "GET", "/api/v1/history", None, &headers).await;
+    assert_eq!(history_ids(&json), [(owned.clone(), 0)]);
+
+    let (_, json) = make_request(&app, "GET", "/api/v1/history", None).await;
+    assert_eq!(history_ids(&json), [(anonymous, 0)]);
+
+    let uri = format!("/api/v1/decks/{owned}/history");
+    let (_, json) = make_request(&app, "GET", &uri, None).await;
+    assert!(json["items"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_history_records_the_viewer_of_unowned_decks() {
+    let app = setup_router().await;
+    let kari = create_user_with_key(&app, "Kari").await;
+    let headers = [("Authorization", kari.as_str())];
+    let deck_id = Uuid::new_v4().to_string();
+    let uri = format!("/api/v1/decks/{deck_id}");
+
+    let _ = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+    let _ = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+    let _ = make_request(&app, "GET", &uri, None).await;
+
+    let (_, json) = make_request_with_headers(&app, "GET", "/api/v1/history", None, &headers).await;
+    assert_eq!(history_ids(&json), [(deck_id.clone(), 0)]);
+    assert_eq!(json["items"][0]["views"], 2);
+
+    let (_, json) = make_request(&app, "GET", "/api/v1/history", None).await;
+    assert_eq!(history_ids(&json), [(deck_id, 0)]);
+    assert_eq!(json["items"][0]["views"], 1);
+}
+
+#[tokio::test]
+async fn test_revoked_or_unknown_api_key_is_unauthorized() {
+    let app = setup_router().await;
+    let headers = [("Authorization", "Bearer pk_finnes-ikke")];
+    let (status, json) =
+        make_request_with_headers(&app, "POST", "/api/v1/decks", None, &headers).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(json["code"], "unauthorized");
+
+    let admin = format!("Bearer {ADMIN_TOKEN}");
+    let admin_headers = [("Authorization", admin.as_str())];
+    let body = json!({"name": "Kari"});
+    let (_, user) = make_request_with_headers(
+        &app,
+        "POST",
+        "/api/v1/admin/users",
+        Some(&body),
+        &admin_headers,
+    )
+    .await;
+    let uri = format!("/api/v1/admin/users/{}/keys", user["id"].as_str().unwrap());
+    let (_, key) = make_request_with_headers(&app, "POST", &uri, None, &admin_headers).await;
+    let auth = format!("Bearer {}", key["key"].as_str().unwrap());
+
+    let uri = format!("/api/v1/admin/keys/{}", key["id"].as_str().unwrap());
+    let (status, _) = make_request_with_headers(&app, "DELETE", &uri, None, &admin_headers).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) =
+        make_request_with_headers(&app, "GET", "/api/v1/me", None, &[("Authorization", &auth)])
+            .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, json) =
+        make_request_with_headers(&app, "DELETE", &uri, None, &admin_headers).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["code"], "api_key_not_found");
+}
+
+#[tokio::test]
+async fn test_unknown_api_key_is_unauthorized_on_every_route() {
+    let app = setup_router().await;
+    let headers = [("Authorization", "Bearer pk_finnes-ikke")];
+    let body = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3r", "4s", "5h", "7k"] }
+        ]
+    });
+    let (_, comparison) = make_request(&app, "POST", "/api/v1/hands/compare", Some(&body)).await;
+    let table_uri = format!("/api/v1/tables/{}/ws", Uuid::new_v4());
+    let comparison_uri = format!("/api/v1/comparisons/{}", comparison["id"].as_str().unwrap());
+
+    for (method, uri, body) in [
+        ("POST", "/api/v1/hands/compare", Some(&body)),
+        ("POST", "/api/v1/hands/evaluate:batch", Some(&body)),
+        ("GET", comparison_uri.as_str(), None),
+        ("POST", "/api/v1/tables", None),
+        ("GET", table_uri.as_str(), None),
+    ] {
+        let (status, json) = make_request_with_headers(&app, method, uri, body, &headers).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{method} {uri}");
+        assert_eq!(json["code"], "unauthorized");
+    }
+}
+
+#[tokio::test]
+async fn test_comparison_is_only_visible_to_its_user() {
+    let app = setup_router().await;
+    let kari = create_user_with_key(&app, "Kari").await;
+    let ola = create_user_with_key(&app, "Ola").await;
+    let body = json!({
+        "hands": [
+            { "external_id": "a", "hand": ["ah", "kh", "qh", "jh", "th"] },
+            { "external_id": "b", "hand": ["2k", "3r", "4s", "5h", "7k"] }
+        ]
+    });
+    let (status, json) = make_request_with_headers(
+        &app,
+        "POST",
+        "/api/v1/hands/compare",
+        Some(&body),
+        &[("Authorization", &kari)],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = format!("/api/v1/comparisons/{}", json["id"].as_str().unwrap());
+
+    let (status, _) =
+        make_request_with_headers(&app, "GET", &uri, None, &[("Authorization", &kari)]).await;
+    assert_eq!(status, StatusCode::OK);
+    for headers in [vec![("Authorization", ola.as_str())], vec![]] {
+        let (status, json) = make_request_with_headers(&app, "GET", &uri, None, &headers).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["code"], "comparison_not_found");
+    }
+}
+
+#[tokio::test]
+async fn test_user_management_requires_admin() {
+    let app = setup_router().await;
+    let kari = create_user_with_key(&app, "Kari").await;
+    let body = json!({"name": "Ola"});
+
+    let (status, _) = make_request(&app, "POST", "/api/v1/admin/users", Some(&body)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // En API-nøkkel gir ikke tilgang til admin-endepunktene
+    let headers = [("Authorization", kari.as_str())];
+    let (status, _) =
+        make_request_with_headers(&app, "POST", "/api/v1/admin/users", Some(&body), &headers).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = make_request(&app, "GET", "/api/v1/me", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let admin = format!("Bearer {ADMIN_TOKEN}");
+    let uri = format!("/api/v1/admin/users/{}/keys", Uuid::new_v4());
+    let (status, json) =
+        make_request_with_headers(&app, "POST", &uri, None, &[("Authorization", &admin)]).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["code"], "user_not_found");
+}
+
+#[tokio::test]
+async fn test_rate_limit_rejects_client_with_retry_after() {
+    let options = RouterOptions {
+        rate_limit: Some(crate::RateLimit {
+            requests_per_second: 0.1,
+            burst: 2,
+        }),
+        ..test_options()
+    };
+    let app = router(Default::default(), test_repository().await, options);
+    let key = create_user_with_key(&app, "Kari").await;
+    let get = |ip: &str, auth: Option<&str>| {
+        let mut builder = Request::builder()
+            .uri("/api/v1/history")
+            .extension(ConnectInfo(PROXY))
+            .header("X-Forwarded-For", ip);
+        if let Some(auth) = auth {
+            builder = builder.header("Authorization", auth);
+        }
+        app.clone().oneshot(builder.body(Body::empty()).unwrap())
+    };
+
+    for _ in 0..2 {
+        assert_eq!(
+            get("10.0.0.1", None).await.unwrap().status(),
+            StatusCode::OK
+        );
+    }
+    let response = get("10.0.0.1", None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "10");
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(json["code"], "rate_limited");
+
+    // Andre IP-adresser og API-nøkler har egne bøtter
+    assert_eq!(
+        get("10.0.0.2", None).await.unwrap().status(),
+        StatusCode::OK
+    );
+    let response = get("10.0.0.1", Some(&key)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Ukjente nøkler og admin-tokenet får ikke egen bøtte
+    for auth in ["Bearer pk_falsk", &format!("Bearer {ADMIN_TOKEN}")] {
+        let response = get("10.0.0.1", Some(auth)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit_trusts_forwarded_for_only_from_proxy() {
+    let rate_limit = Some(crate::RateLimit {
+        requests_per_second: 0.1,
+        burst: 1,
+    });
+    let get = |app: &Router, peer: SocketAddr, forwarded_for: &str| {
+        let request = Request::builder()
+            .uri("/api/v1/history")
+            .extension(ConnectInfo(peer))
+            .header("X-Forwarded-For", forwarded_for)
+            .body(Body::empty())
+            .unwrap();
+        let app = app.clone();
+        async move { app.oneshot(request).await.unwrap().status() }
+    };
+
+    // Uten proxyer er det motparten som begrenses
+    let options = RouterOptions {
+        rate_limit,
+        trusted_proxies: Vec::new(),
+        ..test_options()
+    };
+    let app = router(Default::default(), test_repository().await, options);
+    assert_eq!(get(&app, PROXY, "10.0.0.1").await, StatusCode::OK);
+    assert_eq!(
+        get(&app, PROXY, "10.0.0.2").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // Bak proxyen er det adressen proxyen la til, ikke det klienten skrev
+    let options = RouterOptions {
+        rate_limit,
+        ..test_options()
+    };
+    let app = router(Default::default(), test_repository().await, options);
+    assert_eq!(get(&app, PROXY, "10.0.0.1").await, StatusCode::OK);
+    assert_eq!(
+        get(&app, PROXY, "10.0.0.2, 10.0.0.1").await,
+        StatusCode::TOO_MANY_REQUESTS
     );
+    assert_eq!(get(&app, PROXY, "10.0.0.2").await, StatusCode::OK);
+    let other = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)), 40000);
+    assert_eq!(get(&app, other, "10.0.0.4").await, StatusCode::OK);
+    assert_eq!(
+        get(&app, other, "10.0.0.5").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[tokio::test]
+async fn test_repository_prunes_history_by_age_and_rows() {
+    let repository = test_repository().await;
+    let app = router(Default::default(), repository.clone(), test_options());
+    let deck_id = Uuid::new_v4();
+
+    for offset in [0, 5, 10, 15] {
+        let uri = format!("/api/v1/decks/{deck_id}?offset={offset}");
+        let _ = make_request(&app, "GET", &uri, None).await;
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+    }
+
+    let (_, json) = make_request(&app, "GET", "/api/v1/history", None).await;
+    let oldest = json["items"][3]["time"].as_u64().unwrap();
+
+    assert_eq!(
+        repository
+            .prune_history(Some(oldest + 1), None)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(repository.prune_history(None, Some(2)).await.unwrap(), 1);
+    assert_eq!(repository.prune_history(None, None).await.unwrap(), 0);
+
+    let (_, json) = make_request(&app, "GET", "/api/v1/history", None).await;
+    let offsets: Vec<_> = json["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["offset"].as_i64().unwrap())
+        .collect();
+    assert_eq!(offsets, [15, 10]);
 }
@@ -1,130 +1,1145 @@
 mod dto;
+mod error;
+mod table;
 
-use std::sync::Arc;
+use std::{
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use axum::{
-    extract::{Path, Query, State},
-    response::IntoResponse,
+    body::{Body, Bytes},
+    extract::{Request, State, WebSocketUpgrade},
+    http::{
+        StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE},
+    },
+    middleware::Next,
+    response::{
+        IntoResponse, Response,
+        sse::{Event as SseEvent, KeepAlive, Sse},
+    },
+};
+use futures_util::{Stream, StreamExt, TryStreamExt, stream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::{
+    bytes::BytesMut,
+    codec::{Decoder, FramedRead, LinesCodec, LinesCodecError},
+    io::StreamReader,
 };
-use sqlx::SqlitePool;
 use uuid::Uuid;
 
-use crate::{DECK_SIZE, DeckProvider, Hand};
+use super::{CompareLimits, rate_limit::RateLimiter};
+use crate::{
+    ApiKey, Card, Comparison, DECK_SIZE, DealError, Deck, DeckInfo, DeckProvider, Draw, DrawError,
+    DrawGame, EVALUATOR_VERSION, Event, EventKind, Hand, HistoryEntry, HistoryFilter,
+    HistoryPosition, Locale, MAX_DISCARDS, MIN_PLAYERS, Metrics, OrderError, Repository, User,
+    deal, places,
+    tables::{TableOptions, Tables},
+    validate_hands,
+};
 
-const LIST_HANDS_PAGE_SIZE: usize = 5;
-const LIST_HANDS_LIMIT: usize = DECK_SIZE - LIST_HANDS_PAGE_SIZE;
+const HAND_SIZE: usize = 5;
+/// To egne kort og fem på bordet
+const HOLDEM_CARDS: usize = 7;
+/// Så mange kort kan hvem som helst hente om gangen. Mer av rekkefølgen er
+/// forbeholdt admin og eieren av kortstokken.
+const LIST_MAX_COUNT: usize = HOLDEM_CARDS;
 const HISTORY_PAGE_SIZE: usize = 10;
+/// Hvor ofte strømmen av historikk sender en kommentar når ingenting skjer,
+/// slik at proxyer ikke lukker forbindelsen
+const HISTORY_STREAM_HEARTBEAT: Duration = Duration::from_secs(15);
+const DECK_SEED_MAX_LENGTH: usize = 256;
+const DECK_LABEL_MAX_LENGTH: usize = 200;
+const DECK_NOTES_MAX_LENGTH: usize = 4000;
+const USER_NAME_MAX_LENGTH: usize = 100;
+const API_KEY_LABEL_MAX_LENGTH: usize = 200;
+/// Øvre grense for én linje i en batch, slik at minnebruken er begrenset
+/// uansett hvor stor forespørselen er
+const BATCH_MAX_LINE_LENGTH: usize = 1024 * 1024;
 
 use dto::{
-    ApiError, CompareHandsRequest, CompareHandsResponse, CreateDeckResponse, HistoryItem,
-    HistoryResponse, Json, ListHandsResponse, Pagination,
+    Admin, ApiKeyResponse, ApiKeysResponse, BatchGroup, BatchResult, CompareHandsQuery,
+    CompareHandsRequest, CompareHandsResponse, ComparisonResponse, CreateApiKeyRequest,
+    CreateDeckRequest, CreateTableRequest, CreateUserRequest, CurrentUser, CursorDirection,
+    DealQuery, DealResponse, DeckCardsResponse, DeckResponse, DiscardRequest, DrawQuery,
+    DrawResponse, EvaluatedHand, HandDto, HistoryCursor, HistoryItem, HistoryQuery,
+    HistoryResponse, HistoryStreamQuery, Json, ListHandsResponse, Pagination, Path,
+    PurgeHistoryResponse, Query, Reevaluation, RequestMeta, Standing, TableResponse, UserResponse,
+    generate_api_key, hash_api_key,
 };
+pub use dto::{AdminToken, TrustedProxies};
+use error::{ApiError, Reason};
+
+pub async fn create_deck(
+    State(deck_provider): State<Arc<DeckProvider>>,
+    State(repository): State<Arc<dyn Repository>>,
+    meta: RequestMeta,
+    user: CurrentUser,
+    request: Option<Json<CreateDeckRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let CreateDeckRequest {
+        seed,
+        label,
+        notes,
+        cards,
+        preset,
+    } = request.map(|Json(request)| request).unwrap_or_default();
+
+    check_length("seed", seed.as_deref(), DECK_SEED_MAX_LENGTH)?;
+    check_length("label", label.as_deref(), DECK_LABEL_MAX_LENGTH)?;
+    check_length("notes", notes.as_deref(), DECK_NOTES_MAX_LENGTH)?;
+    if seed.as_deref() == Some("") {
+        return Err(ApiError::InvalidBody {
+            field: Some("seed".into()),
+            reason: Reason::Empty,
+        });
+    }
+
+    let cards = match (cards, preset) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::InvalidBody {
+                field: Some("preset".into()),
+                reason: Reason::PresetWithCards,
+            });
+        }
+        (cards, preset) => cards.or_else(|| preset.map(|preset| deck_provider.preset(preset))),
+    };
+    if let Some(cards) = &cards {
+        deck_provider
+            .get_with_order(cards, 0)
+            .map_err(|error| ApiError::InvalidBody {
+                field: Some(format!("cards[{}]", error.position())),
+                reason: match error {
+                    OrderError::UnknownCard { card, .. } => Reason::UnknownCard(card),
+                    OrderError::RepeatedCard { card, .. } => Reason::RepeatedCard(card),
+                },
+            })?;
+    }
+
+    let deck = DeckInfo {
+        id: Uuid::new_v4(),
+        time: now(),
+        seed,
+        label,
+        notes,
+        cards,
+        owner: user.id(),
+        sealed: false,
+    };
+    repository.create_deck(&deck).await?;
+
+    let event = Event {
+        deck: Some(deck.id),
+        ..new_event(&meta, EventKind::Deal)
+    };
+    repository.record_event(event).await?;
 
-pub async fn create_deck() -> impl IntoResponse {
-    Json(CreateDeckResponse { id: Uuid::new_v4() })
+    Ok(Json(DeckResponse::from(deck)))
 }
 
+pub async fn deck_info(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(deck_id): Path<Uuid>,
+    user: CurrentUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let deck = visible_deck(&*repository, deck_id, user.id())
+        .await?
+        .ok_or(ApiError::DeckNotFound)?;
+
+    Ok(Json(DeckResponse::from(deck)))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn list_hands(
     State(deck_provider): State<Arc<DeckProvider>>,
-    State(pool): State<SqlitePool>,
+    State(repository): State<Arc<dyn Repository>>,
+    State(history_feed): State<broadcast::Sender<HistoryEntry>>,
     Path(deck_id): Path<Uuid>,
-    Query(Pagination { offset }): Query<Pagination>,
+    Query(Pagination {
+        offset,
+        count,
+        stride,
+    }): Query<Pagination>,
+    locale: Locale,
+    meta: RequestMeta,
+    user: CurrentUser,
+    admin: Option<Admin>,
 ) -> impl IntoResponse {
-    if offset > LIST_HANDS_LIMIT {
-        return Err(ApiError::UserInput {
-            description: format!(
-                "Invalid offset. Expected a number between 0 and {LIST_HANDS_LIMIT}, got {}",
-                offset
-            ),
+    if count == 0 || count > DECK_SIZE {
+        return Err(ApiError::InvalidQuery {
+            field: Some("count".into()),
+            reason: Reason::OutOfRange {
+                min: 1,
+                max: DECK_SIZE,
+            },
+        });
+    }
+    if stride == Some(0) {
+        return Err(ApiError::InvalidQuery {
+            field: Some("stride".into()),
+            reason: Reason::TooSmall { min: 1 },
         });
     }
-    let deck = deck_provider.get_with_seed(deck_id);
-    let next_offset = offset + 5;
+    if offset > DECK_SIZE - count {
+        return Err(ApiError::InvalidOffset {
+            max: DECK_SIZE - count,
+            got: offset,
+        });
+    }
+    let info = visible_deck(&*repository, deck_id, user.id()).await?;
+    let owner = info
+        .as_ref()
+        .is_some_and(|deck| deck.owner.is_some() && deck.owner == user.id());
+    if count > LIST_MAX_COUNT && admin.is_none() && !owner {
+        return Err(ApiError::CountNotAllowed {
+            max: LIST_MAX_COUNT,
+        });
+    }
+    let (deck, random) = order_deck(&deck_provider, deck_id, info)?;
+    let cards = &deck[offset..offset + count];
+    let next_offset = offset + stride.unwrap_or(count);
 
-    add_history(&pool, deck_id, offset).await?;
+    let hand = match count {
+        HAND_SIZE..=HOLDEM_CARDS => Hand::best_of(cards),
+        _ => None,
+    };
 
-    deck[offset..next_offset]
-        .try_into()
-        .map(|cards: &[_; 5]| {
-            Json(ListHandsResponse {
-                hand: Hand::from(cards.clone()),
-                next_offset: (next_offset < DECK_SIZE - 5).then_some(next_offset),
-            })
-        })
-        .map_err(|_| ApiError::InternalServer)
+    let event = Event {
+        deck: Some(deck_id),
+        offset: Some(offset),
+        payload: Some(serde_json::json!({
+            "ranking_category": hand.as_ref().map(Hand::ranking_category),
+            "count": count,
+        })),
+        ..new_event(&meta, EventKind::View)
+    };
+    // Historikken er over hender på fem kort. Flere kort fra samme posisjon
+    // er en annen hånd, og logges bare som hendelse.
+    match &hand {
+        Some(hand) if count == HAND_SIZE => {
+            let entry = repository
+                .record_view(event, hand.ranking_category(), user.id())
+                .await?;
+            // Feiler bare når ingen følger med på strømmen
+            let _ = history_feed.send(entry);
+        }
+        _ => repository.record_event(event).await?,
+    }
+
+    Ok(Json(ListHandsResponse {
+        hand: hand.map(|hand| EvaluatedHand::new(hand, locale)),
+        cards: (count != HAND_SIZE).then(|| cards.to_vec()),
+        next_offset: (next_offset + count <= DECK_SIZE).then_some(next_offset),
+        random,
+    }))
+}
+
+pub async fn deal_table(
+    State(deck_provider): State<Arc<DeckProvider>>,
+    State(repository): State<Arc<dyn Repository>>,
+    Path(deck_id): Path<Uuid>,
+    Query(DealQuery { players, variant }): Query<DealQuery>,
+    locale: Locale,
+    meta: RequestMeta,
+    user: CurrentUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let (deck, random) = load_deck(&deck_provider, &*repository, deck_id, user.id()).await?;
+    let table = deal(&deck, variant, players).map_err(deal_error)?;
+    let response = DealResponse::new(deck_id, table, random, locale);
+
+    let event = Event {
+        deck: Some(deck_id),
+        payload: Some(serde_json::json!({
+            "variant": variant,
+            "players": players,
+            "winners": response.winners,
+        })),
+        ..new_event(&meta, EventKind::Deal)
+    };
+    repository.record_event(event).await?;
+
+    Ok(Json(response))
+}
+
+pub async fn start_draw(
+    State(deck_provider): State<Arc<DeckProvider>>,
+    State(repository): State<Arc<dyn Repository>>,
+    Path(deck_id): Path<Uuid>,
+    Query(DrawQuery { players }): Query<DrawQuery>,
+    locale: Locale,
+    meta: RequestMeta,
+    user: CurrentUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let (deck, random) = load_deck(&deck_provider, &*repository, deck_id, user.id()).await?;
+    let draw = Draw::deal(&deck, players).map_err(deal_error)?;
+
+    let game = DrawGame {
+        deck: deck_id,
+        time: now(),
+        players,
+        discards: vec![],
+    };
+    if !repository.create_draw_game(&game).await? {
+        return Err(ApiError::DrawAlreadyStarted);
+    }
+
+    let event = Event {
+        deck: Some(deck_id),
+        payload: Some(serde_json::json!({ "variant": "draw", "players": players })),
+        ..new_event(&meta, EventKind::Deal)
+    };
+    repository.record_event(event).await?;
+
+    Ok(Json(DrawResponse::new(deck_id, draw, random, locale)))
+}
+
+pub async fn draw_state(
+    State(deck_provider): State<Arc<DeckProvider>>,
+    State(repository): State<Arc<dyn Repository>>,
+    Path(deck_id): Path<Uuid>,
+    locale: Locale,
+    user: CurrentUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let (game, deck, random) = load_draw(&deck_provider, &*repository, deck_id, user.id()).await?;
+    let draw = replay_draw(&game, &deck)?;
+
+    Ok(Json(DrawResponse::new(deck_id, draw, random, locale)))
+}
+
+pub async fn discard(
+    State(deck_provider): State<Arc<DeckProvider>>,
+    State(repository): State<Arc<dyn Repository>>,
+    Path((deck_id, seat)): Path<(Uuid, usize)>,
+    locale: Locale,
+    meta: RequestMeta,
+    user: CurrentUser,
+    Json(DiscardRequest { discard }): Json<DiscardRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (game, deck, random) = load_draw(&deck_provider, &*repository, deck_id, user.id()).await?;
+    let mut draw = replay_draw(&game, &deck)?;
+
+    let Some(index) = seat.checked_sub(1).filter(|index| *index < game.players) else {
+        return Err(ApiError::InvalidPath {
+            field: Some("seat".into()),
+            reason: Reason::OutOfRange {
+                min: 1,
+                max: game.players,
+            },
+        });
+    };
+    draw.discard(&deck, index, &discard)
+        .map_err(|error| match error {
+            DrawError::NotYourTurn { next } => ApiError::NotYourTurn { next },
+            DrawError::TooManyDiscards { got } => ApiError::InvalidBody {
+                field: Some("discard".into()),
+                reason: Reason::TooManyDiscards {
+                    max: MAX_DISCARDS,
+                    got,
+                },
+            },
+            DrawError::InvalidPosition { index, position } => ApiError::InvalidBody {
+                field: Some(format!("discard[{index}]")),
+                reason: Reason::InvalidDiscard { position },
+            },
+        })?;
+
+    // En annen forespørsel rakk å bytte for plassen først
+    if !repository
+        .record_discard(deck_id, index, now(), &discard)
+        .await?
+    {
+        return Err(ApiError::NotYourTurn {
+            next: (index + 1 < game.players).then_some(index + 1),
+        });
+    }
+
+    let event = Event {
+        deck: Some(deck_id),
+        payload: Some(serde_json::json!({ "seat": seat, "discard": discard })),
+        ..new_event(&meta, EventKind::Draw)
+    };
+    repository.record_event(event).await?;
+
+    Ok(Json(DrawResponse::new(deck_id, draw, random, locale)))
+}
+
+pub async fn create_table(
+    State(tables): State<Arc<Tables>>,
+    _: CurrentUser,
+    request: Option<Json<CreateTableRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let CreateTableRequest {
+        small_blind,
+        big_blind,
+        stack,
+    } = request.map(|Json(request)| request).unwrap_or_default();
+    let defaults = TableOptions::default();
+    let options = TableOptions {
+        small_blind: small_blind.unwrap_or(defaults.small_blind),
+        big_blind: big_blind.unwrap_or(defaults.big_blind),
+        stack: stack.unwrap_or(defaults.stack),
+    };
+
+    if options.small_blind == 0 || options.big_blind < options.small_blind {
+        return Err(ApiError::InvalidBody {
+            field: Some("big_blind".into()),
+            reason: Reason::Blinds,
+        });
+    }
+    if options.stack < options.big_blind {
+        return Err(ApiError::InvalidBody {
+            field: Some("stack".into()),
+            reason: Reason::BelowField { other: "big_blind" },
+        });
+    }
+
+    let id = tables.create(options).ok_or(ApiError::TooManyTables)?;
+
+    Ok(Json(TableResponse {
+        id,
+        small_blind: options.small_blind,
+        big_blind: options.big_blind,
+        stack: options.stack,
+    }))
+}
+
+pub async fn table_socket(
+    State(tables): State<Arc<Tables>>,
+    Path(table_id): Path<Uuid>,
+    _: CurrentUser,
+    upgrade: WebSocketUpgrade,
+) -> Result<impl IntoResponse, ApiError> {
+    let table = tables.get(table_id).ok_or(ApiError::TableNotFound)?;
+
+    Ok(upgrade.on_upgrade(move |socket| table::play(socket, table)))
+}
+
+pub async fn deck_cards(
+    _: Admin,
+    State(deck_provider): State<Arc<DeckProvider>>,
+    State(repository): State<Arc<dyn Repository>>,
+    Path(deck_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let info = repository.deck(deck_id).await?;
+    let (cards, random) = order_deck(&deck_provider, deck_id, info)?;
+
+    Ok(Json(DeckCardsResponse {
+        id: deck_id,
+        cards,
+        random,
+    }))
 }
 
 pub async fn compare_hands(
+    State(repository): State<Arc<dyn Repository>>,
+    State(limits): State<CompareLimits>,
+    Query(CompareHandsQuery { standings }): Query<CompareHandsQuery>,
+    locale: Locale,
+    meta: RequestMeta,
+    user: CurrentUser,
     Json(CompareHandsRequest { hands }): Json<CompareHandsRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_hands(&hands, limits)?;
+    let mut response = compare(hands.clone(), standings, locale)?;
+    let id = Uuid::new_v4();
+    let comparison = Comparison {
+        id,
+        time: now(),
+        evaluator_version: EVALUATOR_VERSION,
+        hands: to_stored_json(&hands)?,
+        winners: to_stored_json(&response.winners)?,
+        decided_by: response
+            .decided_by
+            .as_ref()
+            .map(to_stored_json)
+            .transpose()?,
+        owner: user.id(),
+    };
+    let payload = serde_json::json!({
+        "comparison": id,
+        "hands": hands.len(),
+        "winners": response.winners.iter().map(|w| &w.external_id).collect::<Vec<_>>(),
+    });
+    let event = Event {
+        payload: Some(payload),
+        ..new_event(&meta, EventKind::Compare)
+    };
+    repository.store_comparison(&comparison, event).await?;
+    response.id = Some(id);
+
+    Ok(Json(response))
+}
+
+/// Henter en lagret sammenligning og evaluerer hendene på nytt, slik at
+/// endringer i evalueringen som gir et annet resultat blir synlige
+pub async fn get_comparison(
+    State(repository): State<Arc<dyn Repository>>,
+    Path(id): Path<Uuid>,
+    user: CurrentUser,
+) -> Result<impl IntoResponse, ApiError> {
+    // Sammenligningene til andre brukere finnes ikke, som kortstokkene deres
+    let comparison = repository
+        .comparison(id)
+        .await?
+        .filter(|comparison| comparison.owner.is_none() || comparison.owner == user.id())
+        .ok_or(ApiError::ComparisonNotFound)?;
+
+    let hands: Vec<HandDto> = from_stored_json(comparison.hands)?;
+    let winners: Vec<HandDto> = from_stored_json(comparison.winners)?;
+    let decided_by = comparison.decided_by.map(from_stored_json).transpose()?;
+
+    let current = compare(hands.clone(), false, Locale::default())?;
+
+    let mut differences = vec![];
+    if current.winners != winners {
+        differences.push("winners");
+    }
+    if current.decided_by != decided_by {
+        differences.push("decided_by");
+    }
+
+    Ok(Json(ComparisonResponse {
+        id,
+        time: comparison.time,
+        evaluator_version: comparison.evaluator_version,
+        hands,
+        winners,
+        decided_by,
+        reevaluation: Reevaluation {
+            evaluator_version: EVALUATOR_VERSION,
+            winners: current.winners,
+            decided_by: current.decided_by,
+            differences,
+        },
+    }))
+}
+
+/// Sammenligner mange uavhengige grupper hender. Forespørselen er NDJSON med
+/// én `BatchGroup` per linje, og svaret strømmes tilbake som NDJSON med ett
+/// `BatchResult` per linje, i samme rekkefølge. Linjene leses og evalueres
+/// etter hvert som klienten leser svaret. Hver gruppe som sammenlignes gir en
+/// hendelse.
+pub async fn evaluate_batch(
+    State(repository): State<Arc<dyn Repository>>,
+    State(limits): State<CompareLimits>,
+    Query(CompareHandsQuery { standings }): Query<CompareHandsQuery>,
+    locale: Locale,
+    meta: RequestMeta,
+    _: CurrentUser,
+    body: Body,
 ) -> impl IntoResponse {
-    let winner = hands.iter().max_by_key(|h| Hand::from(h.hand.clone()));
+    let reader = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+    let lines = FramedRead::new(reader, BatchLines::default());
+
+    let results = lines
+        .enumerate()
+        .filter(|(_, line)| {
+            std::future::ready(!matches!(line, Ok(Ok(line)) if line.trim().is_empty()))
+        })
+        .then(move |(index, line)| {
+            let repository = repository.clone();
+            let meta = meta.clone();
+            async move {
+                let line_number = index + 1;
+                let line = line.and_then(|line| line);
+                let result = match evaluate_batch_line(line, limits, standings, locale) {
+                    Ok((id, hands, result)) => {
+                        let winners: Vec<_> =
+                            result.winners.iter().map(|w| &w.external_id).collect();
+                        let event = Event {
+                            payload: Some(serde_json::json!({
+                                "line": line_number,
+                                "hands": hands,
+                                "winners": winners,
+                            })),
+                            ..new_event(&meta, EventKind::Compare)
+                        };
+                        match repository.record_event(event).await {
+                            Ok(()) => Ok((id, result)),
+                            Err(error) => Err((id, error.into())),
+                        }
+                    }
+                    Err(error) => Err(error),
+                };
+
+                let result = match result {
+                    Ok((id, result)) => BatchResult::Compared {
+                        line: line_number,
+                        id,
+                        result,
+                    },
+                    Err((id, error)) => BatchResult::Failed {
+                        line: line_number,
+                        id,
+                        error: error.problem(locale),
+                    },
+                };
 
-    let Some(winner) = winner else {
-        return Json(CompareHandsResponse { winners: vec![] });
+                serde_json::to_vec(&result).map(|mut bytes| {
+                    bytes.push(b'\n');
+                    Bytes::from(bytes)
+                })
+            }
+        });
+
+    (
+        [(CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(results),
+    )
+}
+
+/// Deler opp en batch i linjer. I motsetning til `LinesCodec` alene avsluttes
+/// ikke strømmen når en linje er for lang, linjen hoppes over og gir en feil.
+struct BatchLines(LinesCodec);
+
+impl Default for BatchLines {
+    fn default() -> Self {
+        Self(LinesCodec::new_with_max_length(BATCH_MAX_LINE_LENGTH))
+    }
+}
+
+impl Decoder for BatchLines {
+    type Item = Result<String, LinesCodecError>;
+    type Error = LinesCodecError;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.0.decode(buf) {
+            Err(LinesCodecError::MaxLineLengthExceeded) => {
+                Ok(Some(Err(LinesCodecError::MaxLineLengthExceeded)))
+            }
+            result => result.map(|line| line.map(Ok)),
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.0.decode_eof(buf) {
+            Err(LinesCodecError::MaxLineLengthExceeded) => {
+                Ok(Some(Err(LinesCodecError::MaxLineLengthExceeded)))
+            }
+            result => result.map(|line| line.map(Ok)),
+        }
+    }
+}
+
+/// Id-en til gruppen, og antall hender og resultatet når de kunne sammenlignes
+type BatchLineResult =
+    Result<(Option<String>, usize, CompareHandsResponse), (Option<String>, ApiError)>;
+
+fn evaluate_batch_line(
+    line: Result<String, LinesCodecError>,
+    limits: CompareLimits,
+    standings: bool,
+    locale: Locale,
+) -> BatchLineResult {
+    let line = line.map_err(|error| {
+        let reason = match error {
+            LinesCodecError::MaxLineLengthExceeded => Reason::LineTooLong {
+                max: BATCH_MAX_LINE_LENGTH,
+            },
+            LinesCodecError::Io(error) => Reason::Rejected(error.to_string()),
+        };
+
+        (
+            None,
+            ApiError::InvalidBody {
+                field: None,
+                reason,
+            },
+        )
+    })?;
+
+    let deserializer = &mut serde_json::Deserializer::from_str(&line);
+    let BatchGroup { id, hands } =
+        serde_path_to_error::deserialize(deserializer).map_err(|error| (None, error.into()))?;
+
+    let count = hands.len();
+    let result = check_hands(&hands, limits).and_then(|()| compare(hands, standings, locale));
+    match result {
+        Ok(result) => Ok((id, count, result)),
+        Err(error) => Err((id, error)),
+    }
+}
+
+/// Sjekker grensene før hendene evalueres
+fn check_hands(hands: &[HandDto], limits: CompareLimits) -> Result<(), ApiError> {
+    if hands.len() > limits.max_hands {
+        return Err(ApiError::TooManyHands {
+            max: limits.max_hands,
+            got: hands.len(),
+        });
+    }
+    let too_long = hands
+        .iter()
+        .position(|h| h.external_id.chars().count() > limits.max_external_id_length);
+    if let Some(index) = too_long {
+        return Err(ApiError::ExternalIdTooLong {
+            index,
+            max: limits.max_external_id_length,
+        });
+    }
+
+    Ok(())
+}
+
+fn compare(
+    hands: Vec<HandDto>,
+    standings: bool,
+    locale: Locale,
+) -> Result<CompareHandsResponse, ApiError> {
+    validate_hands(hands.iter().map(|h| (h.external_id.as_str(), &h.hand[..])))
+        .map_err(ApiError::DuplicateCards)?;
+
+    let evaluated: Vec<_> = hands
+        .iter()
+        .map(|h| Hand::from_distinct(h.hand.clone()))
+        .collect();
+
+    let Some(best) = evaluated.iter().max() else {
+        return Ok(CompareHandsResponse {
+            id: None,
+            winners: vec![],
+            decided_by: None,
+            standings: standings.then(Vec::new),
+        });
     };
 
     let winners = hands
         .iter()
-        .filter(|h| Hand::from(h.hand.clone()) == Hand::from(winner.hand.clone()))
-        .cloned()
+        .zip(&evaluated)
+        .filter(|(_, hand)| *hand == best)
+        .map(|(h, _)| h.clone())
         .collect();
 
-    Json(CompareHandsResponse { winners })
+    // Det som skiller vinnerne fra den beste av de øvrige hendene
+    let decided_by = evaluated
+        .iter()
+        .filter(|hand| *hand != best)
+        .max()
+        .and_then(|runner_up| best.decided_by(runner_up));
+
+    let standings = standings.then(|| {
+        let mut standings: Vec<_> = places(&evaluated)
+            .into_iter()
+            .zip(hands.iter().zip(evaluated.iter()))
+            .map(|(place, (h, hand))| Standing {
+                place,
+                hand: h.clone(),
+                ranking_category: hand.ranking_category(),
+                evaluation: EvaluatedHand::new(hand.clone(), locale),
+            })
+            .collect();
+
+        standings.sort_by_key(|standing| standing.place);
+        standings
+    });
+
+    Ok(CompareHandsResponse {
+        id: None,
+        winners,
+        decided_by,
+        standings,
+    })
 }
 
 pub async fn history(
-    Query(pagination): Query<Pagination>,
-    State(pool): State<SqlitePool>,
-) -> impl IntoResponse {
-    fetch_history(&pool, pagination.offset, HISTORY_PAGE_SIZE + 1)
-        .await
-        .map(|mut items| {
-            let next_offset =
-                (items.len() > HISTORY_PAGE_SIZE).then_some(pagination.offset + HISTORY_PAGE_SIZE);
-            items.truncate(HISTORY_PAGE_SIZE);
-
-            Json(HistoryResponse { items, next_offset })
+    Query(query): Query<HistoryQuery>,
+    State(repository): State<Arc<dyn Repository>>,
+    user: CurrentUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|cursor| HistoryCursor::decode(cursor).ok_or(ApiError::InvalidCursor))
+        .transpose()?;
+
+    let position = match &cursor {
+        Some(cursor) => cursor.position(),
+        None => HistoryPosition::Skip(query.offset),
+    };
+    let mut items: Vec<HistoryItem> = repository
+        .history(&query.filter(user.id()), position, HISTORY_PAGE_SIZE + 1)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let has_more = items.len() > HISTORY_PAGE_SIZE;
+    items.truncate(HISTORY_PAGE_SIZE);
+
+    let backwards = matches!(
+        cursor,
+        Some(HistoryCursor {
+            direction: CursorDirection::Before,
+            ..
         })
+    );
+    let (has_newer, has_older) = if backwards {
+        items.reverse();
+        (has_more, true)
+    } else {
+        (cursor.is_some() || query.offset > 0, has_more)
+    };
+
+    let prev_cursor = items
+        .first()
+        .filter(|_| has_newer)
+        .map(|item| HistoryCursor::new(CursorDirection::Before, item).encode());
+    let next_cursor = items
+        .last()
+        .filter(|_| has_older)
+        .map(|item| HistoryCursor::new(CursorDirection::After, item).encode());
+    let next_offset = (cursor.is_none() && has_more).then_some(query.offset + HISTORY_PAGE_SIZE);
+
+    Ok(Json(HistoryResponse {
+        items,
+        next_offset,
+        next_cursor,
+        prev_cursor,
+    }))
 }
 
-async fn add_history(pool: &SqlitePool, deck: Uuid, offset: usize) -> Result<(), ApiError> {
-    let now = chrono::Utc::now().timestamp_millis();
-    let offset = offset as i64;
+pub async fn history_stream(
+    State(history_feed): State<broadcast::Sender<HistoryEntry>>,
+    Query(query): Query<HistoryStreamQuery>,
+    user: CurrentUser,
+) -> impl IntoResponse {
+    let events = history_events(history_feed.subscribe(), query.filter(user.id()));
+
+    Sse::new(events).keep_alive(KeepAlive::new().interval(HISTORY_STREAM_HEARTBEAT))
+}
 
-    sqlx::query!(
-        r#"INSERT INTO history(deck, offset, time)
-           VALUES (?, ?, ?)
-           ON CONFLICT (deck, offset) DO UPDATE SET time = excluded.time
-        "#,
-        deck,
-        offset,
-        now,
-    )
-    .execute(pool)
-    .await
-    .map_err(|_| ApiError::InternalServer)?;
+/// Nye elementer i historikken som `history`-hendelser. En klient som henger
+/// for langt etter får `lagged` med antall elementer den gikk glipp av, og
+/// kan hente dem med `/api/v1/history`.
+pub fn history_events(
+    receiver: broadcast::Receiver<HistoryEntry>,
+    filter: HistoryFilter,
+) -> impl Stream<Item = Result<SseEvent, axum::Error>> {
+    stream::unfold(receiver, move |mut receiver| {
+        let filter = filter.clone();
+        async move {
+            let event = loop {
+                match receiver.recv().await {
+                    Ok(entry) if filter.matches(&entry) => {
+                        break SseEvent::default()
+                            .event("history")
+                            .json_data(HistoryItem::from(entry));
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        break SseEvent::default()
+                            .event("lagged")
+                            .json_data(serde_json::json!({ "skipped": skipped }));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            };
+            Some((event, receiver))
+        }
+    })
+}
 
-    Ok(())
+pub async fn deck_history(
+    Path(deck_id): Path<Uuid>,
+    Query(query): Query<HistoryQuery>,
+    repository: State<Arc<dyn Repository>>,
+    user: CurrentUser,
+) -> impl IntoResponse {
+    let query = HistoryQuery {
+        deck: Some(deck_id),
+        ..query
+    };
+
+    history(Query(query), repository, user).await
+}
+
+/// Sletter all historikk for en kortstokk. Hendelsesloggen beholdes.
+pub async fn purge_deck_history(
+    _: Admin,
+    State(repository): State<Arc<dyn Repository>>,
+    State(metrics): State<Arc<Metrics>>,
+    Path(deck_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = repository.purge_deck_history(deck_id).await?;
+    metrics.record_purge(deleted);
+    tracing::info!(%deck_id, deleted, "purged deck history");
+
+    Ok(Json(PurgeHistoryResponse { deleted }))
 }
 
-async fn fetch_history(
-    pool: &SqlitePool,
-    offset: usize,
-    page_size: usize,
-) -> Result<Vec<HistoryItem>, ApiError> {
-    let page_size = page_size as i64;
-    let offset = offset as i64;
-    sqlx::query_as!(
-        HistoryItem,
-        r#"SELECT offset, time as "time!: u64", deck as "deck!: Uuid"
-           FROM history
-           ORDER BY time DESC
-           LIMIT ? OFFSET ?
-        "#,
-        page_size,
-        offset
+pub async fn metrics(_: Admin, State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
     )
-    .fetch_all(pool)
-    .await
-    .map_err(|_| ApiError::InternalServer)
+}
+
+/// Brukeren som eier API-nøkkelen
+pub async fn me(user: CurrentUser) -> Result<impl IntoResponse, ApiError> {
+    let user = user.0.ok_or(ApiError::Unauthorized)?;
+
+    Ok(Json(UserResponse::from(user)))
+}
+
+pub async fn create_user(
+    _: Admin,
+    State(repository): State<Arc<dyn Repository>>,
+    Json(CreateUserRequest { name }): Json<CreateUserRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_length("name", Some(&name), USER_NAME_MAX_LENGTH)?;
+    if name.trim().is_empty() {
+        return Err(ApiError::InvalidBody {
+            field: Some("name".into()),
+            reason: Reason::Empty,
+        });
+    }
+
+    let user = User {
+        id: Uuid::new_v4(),
+        time: now(),
+        name,
+    };
+    repository.create_user(&user).await?;
+    tracing::info!(user_id = %user.id, "created user");
+
+    Ok((StatusCode::CREATED, Json(UserResponse::from(user))))
+}
+
+pub async fn list_api_keys(
+    _: Admin,
+    State(repository): State<Arc<dyn Repository>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .user(user_id)
+        .await?
+        .ok_or(ApiError::UserNotFound)?;
+    let keys = repository.api_keys(user_id).await?;
+
+    Ok(Json(ApiKeysResponse {
+        keys: keys.into_iter().map(Into::into).collect(),
+    }))
+}
+
+/// Lager en ny nøkkel for brukeren. Nøkkelen er bare med i denne responsen.
+pub async fn create_api_key(
+    _: Admin,
+    State(repository): State<Arc<dyn Repository>>,
+    Path(user_id): Path<Uuid>,
+    request: Option<Json<CreateApiKeyRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let CreateApiKeyRequest { label } = request.map(|Json(request)| request).unwrap_or_default();
+    check_length("label", label.as_deref(), API_KEY_LABEL_MAX_LENGTH)?;
+    repository
+        .user(user_id)
+        .await?
+        .ok_or(ApiError::UserNotFound)?;
+
+    let key = generate_api_key();
+    let api_key = ApiKey {
+        id: Uuid::new_v4(),
+        user: user_id,
+        time: now(),
+        label,
+        key_hash: hash_api_key(&key),
+        revoked: None,
+    };
+    repository.create_api_key(&api_key).await?;
+    tracing::info!(%user_id, key_id = %api_key.id, "created api key");
+
+    let response = ApiKeyResponse {
+        key: Some(key),
+        ..api_key.into()
+    };
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn revoke_api_key(
+    _: Admin,
+    State(repository): State<Arc<dyn Repository>>,
+    Path(key_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !repository.revoke_api_key(key_id, now()).await? {
+        return Err(ApiError::ApiKeyNotFound);
+    }
+    tracing::info!(%key_id, "revoked api key");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Avviser forespørselen med `429` når klienten har brukt opp bøtta si.
+/// Klienter med API-nøkkel begrenses per nøkkel, andre per IP-adresse.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    user: Result<CurrentUser, ApiError>,
+    meta: RequestMeta,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Nøkler som ikke hører til en bruker begrenses som klienter uten nøkkel
+    let bearer = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .filter(|_| matches!(user, Ok(CurrentUser(Some(_)))));
+    let client = match (bearer, &meta.client_ip) {
+        (Some(key), _) => format!("key:{}", hash_api_key(key)),
+        (None, Some(ip)) => format!("ip:{ip}"),
+        (None, None) => "ip:unknown".into(),
+    };
+
+    match limiter.check(&client, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => ApiError::RateLimited {
+            retry_after: wait.as_secs_f64().ceil().max(1.0) as u64,
+        }
+        .into_response(),
+    }
+}
+
+/// Oversetter feilresponser til språket klienten ber om i `Accept-Language`
+pub async fn localize_errors(locale: Locale, request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+
+    match response.extensions().get::<ApiError>() {
+        Some(error) if locale != Locale::default() => error.render(locale),
+        _ => response,
+    }
+}
+
+/// Stokker kortstokken med seeden den ble laget med, eller med id-en for
+/// kortstokker som ikke er lagret. Kortstokker med oppgitt rekkefølge
+/// starter med de kortene. Returnerer også om kortstokken er tilfeldig.
+async fn load_deck(
+    deck_provider: &DeckProvider,
+    repository: &dyn Repository,
+    deck_id: Uuid,
+    user: Option<Uuid>,
+) -> Result<(Deck, bool), ApiError> {
+    let info = visible_deck(repository, deck_id, user).await?;
+    order_deck(deck_provider, deck_id, info)
+}
+
+/// Den lagrede kortstokken, dersom brukeren kan se den. Kortstokker med eier
+/// finnes ikke for andre enn eieren, og skjulte kortstokker finnes ikke for
+/// noen.
+async fn visible_deck(
+    repository: &dyn Repository,
+    deck_id: Uuid,
+    user: Option<Uuid>,
+) -> Result<Option<DeckInfo>, ApiError> {
+    match repository.deck(deck_id).await? {
+        Some(deck) if deck.sealed => Err(ApiError::DeckNotFound),
+        Some(deck) if deck.owner.is_some() && deck.owner != user => Err(ApiError::DeckNotFound),
+        deck => Ok(deck),
+    }
+}
+
+/// Rekkefølgen til kortstokken, og om den er tilfeldig
+fn order_deck(
+    deck_provider: &DeckProvider,
+    deck_id: Uuid,
+    info: Option<DeckInfo>,
+) -> Result<(Deck, bool), ApiError> {
+    let (seed, cards) = match info {
+        Some(deck) => (deck.seed, deck.cards),
+        None => (None, None),
+    };
+
+    let deck = match (&seed, &cards) {
+        (Some(seed), Some(cards)) => deck_provider.get_with_order(cards, seed.as_str()),
+        (None, Some(cards)) => deck_provider.get_with_order(cards, deck_id),
+        (Some(seed), None) => Ok(deck_provider.get_with_seed(seed.as_str())),
+        (None, None) => Ok(deck_provider.get_with_seed(deck_id)),
+    }
+    // Rekkefølgen ble sjekket da kortstokken ble laget
+    .map_err(|error| {
+        tracing::error!(%deck_id, %error, "stored deck order is invalid");
+        ApiError::InternalServer
+    })?;
+
+    Ok((deck, cards.is_none()))
+}
+
+/// Henter et lagret draw-spill og kortstokken det spilles med
+async fn load_draw(
+    deck_provider: &DeckProvider,
+    repository: &dyn Repository,
+    deck_id: Uuid,
+    user: Option<Uuid>,
+) -> Result<(DrawGame, Deck, bool), ApiError> {
+    let (deck, random) = load_deck(deck_provider, repository, deck_id, user).await?;
+    let game = repository
+        .draw_game(deck_id)
+        .await?
+        .ok_or(ApiError::DrawNotFound)?;
+
+    Ok((game, deck, random))
+}
+
+fn replay_draw(game: &DrawGame, deck: &[Card]) -> Result<Draw, ApiError> {
+    Draw::replay(deck, game.players, &game.discards).map_err(|error| {
+        tracing::error!(deck = %game.deck, %error, "stored draw game cannot be replayed");
+        ApiError::InternalServer
+    })
+}
+
+fn deal_error(error: DealError) -> ApiError {
+    match error {
+        DealError::PlayersOutOfRange { max, .. } => ApiError::InvalidQuery {
+            field: Some("players".into()),
+            reason: Reason::OutOfRange {
+                min: MIN_PLAYERS,
+                max,
+            },
+        },
+        // Kortstokken har alltid 52 kort
+        DealError::NotEnoughCards { .. } => ApiError::InternalServer,
+    }
+}
+
+fn check_length(field: &str, value: Option<&str>, max: usize) -> Result<(), ApiError> {
+    match value {
+        Some(value) if value.chars().count() > max => Err(ApiError::InvalidBody {
+            field: Some(field.into()),
+            reason: Reason::TooLong { max },
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Lager en hendelse med tidspunkt og metadata fra forespørselen
+fn new_event(meta: &RequestMeta, kind: EventKind) -> Event {
+    Event {
+        kind,
+        time: now(),
+        deck: None,
+        offset: None,
+        user_agent: meta.user_agent.clone(),
+        client_ip: meta.client_ip.clone(),
+        payload: None,
+    }
+}
+
+/// Millisekunder siden epoch
+fn now() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+fn to_stored_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<serde_json::Value, ApiError> {
+    serde_json::to_value(value).map_err(|error| {
+        tracing::error!(%error, "failed to serialize json for storage");
+        ApiError::InternalServer
+    })
+}
+
+fn from_stored_json<T: serde::de::DeserializeOwned>(
+    json: serde_json::Value,
+) -> Result<T, ApiError> {
+    serde_json::from_value(json).map_err(|error| {
+        tracing::error!(%error, "stored json cannot be deserialized");
+        ApiError::InternalServer
+    })
 }
@@ -1,28 +1,272 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
 use axum::{
-    extract::{FromRequest, rejection::JsonRejection},
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    extract::{
+        ConnectInfo, FromRef, FromRequest, FromRequestParts, OptionalFromRequest,
+        OptionalFromRequestParts, Request,
+    },
+    http::{
+        HeaderMap,
+        header::{ACCEPT_LANGUAGE, AUTHORIZATION, USER_AGENT},
+        request::Parts,
+    },
+    response::IntoResponse,
 };
-use serde::{Deserialize, Serialize};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use crate::{Card, Hand};
+use super::error::{ApiError, Problem};
+use crate::{
+    ApiKey, Board, Card, Deck, DeckInfo, Draw, Hand, HistoryEntry, HistoryFilter, HistoryKey,
+    HistoryPosition, Locale, Preset, RankingCategory, Repository, Table, Tiebreak, User, Variant,
+};
+
+/// Gjør det lett å se forskjell på API-nøkler og admin-tokenet
+const API_KEY_PREFIX: &str = "pk_";
 
 #[derive(Deserialize)]
 pub struct Pagination {
     #[serde(default)]
     pub offset: usize,
+    /// Antall kort. Fem gir en hånd, seks og sju gir den beste hånden.
+    #[serde(default = "default_count")]
+    pub count: usize,
+    /// Avstanden til `next_offset`, som standard `count`
+    pub stride: Option<usize>,
+}
+
+fn default_count() -> usize {
+    5
+}
+
+/// Valgfritt innhold i `POST /api/v1/decks`
+#[derive(Deserialize, Default)]
+pub struct CreateDeckRequest {
+    /// Kortstokken stokkes med denne, slik at samme seed gir samme rekkefølge
+    pub seed: Option<String>,
+    pub label: Option<String>,
+    pub notes: Option<String>,
+    /// Kortene kortstokken skal starte med, resten stokkes
+    pub cards: Option<Vec<Card>>,
+    /// Forhåndsdefinert rekkefølge, i stedet for `cards`
+    pub preset: Option<Preset>,
 }
 
 #[derive(Serialize)]
-pub struct CreateDeckResponse {
+pub struct DeckResponse {
     pub id: Uuid,
+    pub time: u64,
+    pub seed: Option<String>,
+    pub label: Option<String>,
+    pub notes: Option<String>,
+    /// `false` når rekkefølgen er oppgitt
+    pub random: bool,
+}
+
+impl From<DeckInfo> for DeckResponse {
+    fn from(deck: DeckInfo) -> Self {
+        Self {
+            id: deck.id,
+            time: deck.time,
+            seed: deck.seed,
+            label: deck.label,
+            notes: deck.notes,
+            random: deck.cards.is_none(),
+        }
+    }
 }
 
 #[derive(Serialize)]
 pub struct ListHandsResponse {
-    pub hand: Hand,
+    /// Mangler når det er færre enn fem eller flere enn sju kort
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hand: Option<EvaluatedHand>,
+    /// Alle kortene, når det ikke er akkurat fem
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cards: Option<Vec<Card>>,
     pub next_offset: Option<usize>,
+    pub random: bool,
+}
+
+#[derive(Deserialize)]
+pub struct DealQuery {
+    pub players: usize,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Serialize)]
+pub struct DealResponse {
+    pub deck_id: Uuid,
+    pub variant: Variant,
+    pub seats: Vec<SeatDto>,
+    /// Felleskortene i hold'em
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub board: Option<Board>,
+    pub burned: Vec<Card>,
+    /// Hvem som starter hver runde i stud
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub streets: Vec<StreetDto>,
+    /// Nummeret til plassene som vant, fra 1
+    pub winners: Vec<usize>,
+    pub random: bool,
+}
+
+#[derive(Serialize)]
+pub struct SeatDto {
+    /// Fra 1, i den rekkefølgen kortene ble delt ut
+    pub seat: usize,
+    pub hole_cards: Vec<Card>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub up_cards: Vec<Card>,
+    pub place: usize,
+    pub hand: EvaluatedHand,
+}
+
+#[derive(Serialize)]
+pub struct StreetDto {
+    pub street: usize,
+    /// Nummeret til plassen som handler først, fra 1
+    pub first_to_act: usize,
+    pub bring_in: bool,
+}
+
+impl DealResponse {
+    pub fn new(deck_id: Uuid, table: Table, random: bool, locale: Locale) -> Self {
+        Self {
+            deck_id,
+            variant: table.variant,
+            winners: table.winners().into_iter().map(|index| index + 1).collect(),
+            seats: table
+                .seats
+                .into_iter()
+                .enumerate()
+                .map(|(index, seat)| SeatDto {
+                    seat: index + 1,
+                    hole_cards: seat.hole_cards,
+                    up_cards: seat.up_cards,
+                    place: seat.place,
+                    hand: EvaluatedHand::new(seat.hand, locale),
+                })
+                .collect(),
+            board: table.board,
+            burned: table.burned,
+            streets: table
+                .streets
+                .into_iter()
+                .map(|street| StreetDto {
+                    street: street.street,
+                    first_to_act: street.first_to_act + 1,
+                    bring_in: street.bring_in,
+                })
+                .collect(),
+            random,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DrawQuery {
+    pub players: usize,
+}
+
+#[derive(Deserialize)]
+pub struct DiscardRequest {
+    /// Posisjonene i hånden, fra 0 til 4, som skal byttes
+    pub discard: Vec<usize>,
+}
+
+/// Valgfritt innhold i `POST /api/v1/tables`
+#[derive(Deserialize, Default)]
+pub struct CreateTableRequest {
+    pub small_blind: Option<u64>,
+    pub big_blind: Option<u64>,
+    /// Sjetongene hver spiller får når den setter seg
+    pub stack: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct TableResponse {
+    pub id: Uuid,
+    pub small_blind: u64,
+    pub big_blind: u64,
+    pub stack: u64,
+}
+
+/// Et spill femkorts draw. Plassene og `next_seat` er nummerert fra 1.
+#[derive(Serialize)]
+pub struct DrawResponse {
+    pub deck_id: Uuid,
+    pub players: usize,
+    pub seats: Vec<DrawSeatDto>,
+    pub next_seat: Option<usize>,
+    pub finished: bool,
+    /// Tom til alle har byttet
+    pub winners: Vec<usize>,
+    pub random: bool,
+}
+
+#[derive(Serialize)]
+pub struct DrawSeatDto {
+    pub seat: usize,
+    pub cards: [Card; 5],
+    /// `null` til plassen har byttet
+    pub discarded: Option<Vec<Card>>,
+    pub hand: EvaluatedHand,
+    /// Satt når alle har byttet
+    pub place: Option<usize>,
+}
+
+impl DrawResponse {
+    pub fn new(deck_id: Uuid, draw: Draw, random: bool, locale: Locale) -> Self {
+        let finished = draw.is_finished();
+        let places = draw.places();
+        let hands = draw.hands();
+        let next_seat = draw.next_seat().map(|seat| seat + 1);
+
+        let seats: Vec<_> = draw
+            .seats
+            .into_iter()
+            .zip(hands)
+            .zip(places)
+            .enumerate()
+            .map(|(index, ((seat, hand), place))| DrawSeatDto {
+                seat: index + 1,
+                cards: seat.cards,
+                discarded: seat.discarded,
+                hand: EvaluatedHand::new(hand, locale),
+                place: finished.then_some(place),
+            })
+            .collect();
+
+        Self {
+            deck_id,
+            players: seats.len(),
+            winners: seats
+                .iter()
+                .filter(|seat| seat.place == Some(1))
+                .map(|seat| seat.seat)
+                .collect(),
+            seats,
+            next_seat,
+            finished,
+            random,
+        }
+    }
+}
+
+/// Hele rekkefølgen til en kortstokk
+#[derive(Serialize)]
+pub struct DeckCardsResponse {
+    pub id: Uuid,
+    pub cards: Deck,
+    pub random: bool,
 }
 
 #[derive(Deserialize)]
@@ -30,15 +274,211 @@ pub struct CompareHandsRequest {
     pub hands: Vec<HandDto>,
 }
 
+#[derive(Deserialize)]
+pub struct CompareHandsQuery {
+    /// Returner plasseringen til alle hendene, ikke bare vinnerne
+    #[serde(default)]
+    pub standings: bool,
+}
+
 #[derive(Serialize)]
 pub struct CompareHandsResponse {
+    /// Id-en sammenligningen er lagret med, se `ComparisonResponse`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub winners: Vec<HandDto>,
+    pub decided_by: Option<Tiebreak>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub standings: Option<Vec<Standing>>,
+}
+
+/// En hånds plassering blant alle hendene som ble sammenlignet
+#[derive(Serialize)]
+pub struct Standing {
+    pub place: usize,
+    #[serde(flatten)]
+    pub hand: HandDto,
+    pub ranking_category: RankingCategory,
+    pub evaluation: EvaluatedHand,
+}
+
+/// En lagret sammenligning, sammen med resultatet av å evaluere hendene på
+/// nytt med gjeldende evaluator
+#[derive(Serialize)]
+pub struct ComparisonResponse {
+    pub id: Uuid,
+    pub time: u64,
+    pub evaluator_version: u32,
+    pub hands: Vec<HandDto>,
+    pub winners: Vec<HandDto>,
+    pub decided_by: Option<Tiebreak>,
+    pub reevaluation: Reevaluation,
+}
+
+#[derive(Serialize)]
+pub struct Reevaluation {
+    pub evaluator_version: u32,
     pub winners: Vec<HandDto>,
+    pub decided_by: Option<Tiebreak>,
+    /// Feltene som avviker fra det lagrede resultatet
+    pub differences: Vec<&'static str>,
+}
+
+/// En linje i en batch: en uavhengig gruppe hender som sammenlignes
+#[derive(Deserialize)]
+pub struct BatchGroup {
+    pub id: Option<String>,
+    pub hands: Vec<HandDto>,
+}
+
+/// Resultatet for én linje i en batch. `line` er linjenummeret i
+/// forespørselen, regnet fra 1.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BatchResult {
+    Compared {
+        line: usize,
+        id: Option<String>,
+        #[serde(flatten)]
+        result: CompareHandsResponse,
+    },
+    Failed {
+        line: usize,
+        id: Option<String>,
+        error: Problem,
+    },
 }
 
 #[derive(Serialize)]
 pub struct HistoryResponse {
     pub items: Vec<HistoryItem>,
     pub next_offset: Option<usize>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+/// Retning i historikken, sett fra elementet markøren peker på
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CursorDirection {
+    /// Eldre elementer
+    After,
+    /// Nyere elementer
+    Before,
+}
+
+/// Markør for keyset-paginering av historikken. Historikken er sortert på
+/// `(time, deck, offset)` synkende, så markøren peker på et element og en
+/// retning. Klienter ser den bare som en ugjennomsiktig streng.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HistoryCursor {
+    pub direction: CursorDirection,
+    pub time: u64,
+    pub deck: Uuid,
+    pub offset: i64,
+}
+
+impl HistoryCursor {
+    pub fn new(direction: CursorDirection, item: &HistoryItem) -> Self {
+        Self {
+            direction,
+            time: item.time,
+            deck: item.deck,
+            offset: item.offset,
+        }
+    }
+
+    pub fn position(&self) -> HistoryPosition {
+        let key = HistoryKey {
+            time: self.time,
+            deck: self.deck,
+            offset: self.offset,
+        };
+
+        match self.direction {
+            CursorDirection::After => HistoryPosition::After(key),
+            CursorDirection::Before => HistoryPosition::Before(key),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let direction = match self.direction {
+            CursorDirection::After => 'a',
+            CursorDirection::Before => 'b',
+        };
+        let raw = format!("{direction}:{}:{}:{}", self.time, self.deck, self.offset);
+
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let mut parts = raw.split(':');
+
+        let direction = match parts.next()? {
+            "a" => CursorDirection::After,
+            "b" => CursorDirection::Before,
+            _ => return None,
+        };
+        let cursor = Self {
+            direction,
+            time: parts.next()?.parse().ok()?,
+            deck: parts.next()?.parse().ok()?,
+            offset: parts.next()?.parse().ok()?,
+        };
+
+        parts.next().is_none().then_some(cursor)
+    }
+}
+
+/// Filtre for historikken. `offset` brukes til paginering, mens
+/// `hand_offset` filtrerer på hvor i kortstokken hånden ble hentet.
+#[derive(Deserialize, Default)]
+pub struct HistoryQuery {
+    #[serde(default)]
+    pub offset: usize,
+    /// `next_cursor` eller `prev_cursor` fra et tidligere svar. Brukes i
+    /// stedet for `offset` når den er satt.
+    pub cursor: Option<String>,
+    pub deck: Option<Uuid>,
+    /// Tidspunkt i millisekunder, inklusiv
+    pub from: Option<u64>,
+    /// Tidspunkt i millisekunder, eksklusiv
+    pub to: Option<u64>,
+    pub hand_offset: Option<usize>,
+    pub category: Option<RankingCategory>,
+}
+
+impl HistoryQuery {
+    pub fn filter(&self, owner: Option<Uuid>) -> HistoryFilter {
+        HistoryFilter {
+            owner,
+            deck: self.deck,
+            from: self.from,
+            to: self.to,
+            hand_offset: self.hand_offset,
+            ranking_category: self.category,
+        }
+    }
+}
+
+/// Filtre for `GET /api/v1/history/stream`
+#[derive(Deserialize)]
+pub struct HistoryStreamQuery {
+    pub deck: Option<Uuid>,
+    pub hand_offset: Option<usize>,
+    pub category: Option<RankingCategory>,
+}
+
+impl HistoryStreamQuery {
+    pub fn filter(&self, owner: Option<Uuid>) -> HistoryFilter {
+        HistoryFilter {
+            owner,
+            deck: self.deck,
+            hand_offset: self.hand_offset,
+            ranking_category: self.category,
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -46,9 +486,46 @@ pub struct HistoryItem {
     pub deck: Uuid,
     pub offset: i64,
     pub time: u64,
+    /// Antall ganger hånden er vist
+    pub views: i64,
+    /// Mangler for historikk lagret før kategorien ble tatt vare på
+    pub ranking_category: Option<RankingCategory>,
+}
+
+impl From<HistoryEntry> for HistoryItem {
+    fn from(entry: HistoryEntry) -> Self {
+        Self {
+            deck: entry.deck,
+            offset: entry.offset,
+            time: entry.time,
+            views: entry.views,
+            ranking_category: entry.ranking_category,
+        }
+    }
+}
+
+/// En evaluert hånd med forklaring av hva den består av
+#[derive(Serialize)]
+pub struct EvaluatedHand {
+    #[serde(flatten)]
+    pub hand: Hand,
+    pub description: String,
+    pub primary_cards: Vec<Card>,
+    pub kickers: Vec<Card>,
+}
+
+impl EvaluatedHand {
+    pub fn new(hand: Hand, locale: Locale) -> Self {
+        Self {
+            description: hand.describe(locale),
+            primary_cards: hand.primary_cards(),
+            kickers: hand.kicker_cards(),
+            hand,
+        }
+    }
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
 pub struct HandDto {
     pub external_id: String,
     pub hand: [Card; 5],
@@ -58,6 +535,30 @@ pub struct HandDto {
 #[from_request(via(axum::Json), rejection(ApiError))]
 pub struct Json<T>(pub T);
 
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(ApiError))]
+pub struct Path<T>(pub T);
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApiError))]
+pub struct Query<T>(pub T);
+
+/// Gir `None` når forespørselen ikke har noen `Content-Type`, slik at
+/// innholdet kan være valgfritt
+impl<T, S> OptionalFromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        let json = <axum::Json<T> as OptionalFromRequest<S>>::from_request(request, state).await?;
+
+        Ok(json.map(|axum::Json(value)| Json(value)))
+    }
+}
+
 impl<T: Serialize> IntoResponse for Json<T> {
     fn into_response(self) -> axum::response::Response {
         let Self(value) = self;
@@ -65,30 +566,263 @@ impl<T: Serialize> IntoResponse for Json<T> {
     }
 }
 
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(Locale::from_accept_language)
+            .unwrap_or_default())
+    }
+}
+
+/// Metadata om klienten som lagres sammen med hendelser
+#[derive(Clone, Default, Debug)]
+pub struct RequestMeta {
+    pub user_agent: Option<String>,
+    pub client_ip: Option<String>,
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+impl<S> FromRequestParts<S> for RequestMeta
+where
+    S: Send + Sync,
+    TrustedProxies: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        // Headerne kan settes av hvem som helst, så de brukes bare når
+        // motparten er en proxy vi stoler på
+        let proxies = TrustedProxies::from_ref(state);
+        let client_ip = match peer {
+            Some(peer) if proxies.contains(&peer) => {
+                Some(proxies.client(&parts.headers).unwrap_or(peer))
+            }
+            peer => peer,
+        };
+
+        Ok(Self {
+            user_agent: header(&parts.headers, USER_AGENT.as_str()),
+            client_ip: client_ip.map(|ip| ip.to_string()),
+        })
+    }
+}
+
+/// Proxyene som får oppgi klientens adresse i `X-Forwarded-For` og
+/// `X-Real-IP`
+#[derive(Clone, Default)]
+pub struct TrustedProxies(Arc<[IpAddr]>);
+
+impl TrustedProxies {
+    pub fn new(proxies: Vec<IpAddr>) -> Self {
+        Self(proxies.into())
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.contains(ip)
+    }
+
+    /// Klienten er den siste adressen i `X-Forwarded-For` som ikke er en av
+    /// proxyene våre. Adressene før den kan klienten ha satt selv.
+    fn client(&self, headers: &HeaderMap) -> Option<IpAddr> {
+        let forwarded_for = header(headers, "x-forwarded-for").and_then(|value| {
+            value
+                .rsplit(',')
+                .map(|ip| ip.trim().parse().ok())
+                .find(|ip| !ip.is_some_and(|ip| self.contains(&ip)))
+                .flatten()
+        });
+        forwarded_for.or_else(|| header(headers, "x-real-ip")?.trim().parse().ok())
+    }
+}
+
 #[derive(Serialize)]
-#[serde(untagged)]
-pub enum ApiError {
-    UserInput { description: String },
-    InternalServer,
-    JsonParsing { description: String },
+pub struct PurgeHistoryResponse {
+    pub deleted: u64,
+}
+
+/// Tokenet admin-endepunktene krever
+#[derive(Clone)]
+pub struct AdminToken(Option<Arc<str>>);
+
+impl AdminToken {
+    pub fn new(token: Option<String>) -> Self {
+        Self(token.filter(|token| !token.is_empty()).map(Into::into))
+    }
+
+    fn matches(&self, candidate: &str) -> bool {
+        let Some(token) = &self.0 else {
+            return false;
+        };
+
+        // Sammenligner hele strengen uansett hvor første forskjell er
+        token.len() == candidate.len()
+            && token
+                .bytes()
+                .zip(candidate.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
 }
 
-impl From<JsonRejection> for ApiError {
-    fn from(rejection: JsonRejection) -> Self {
-        Self::JsonParsing {
-            description: rejection.body_text(),
+/// Bevis på at forespørselen har gyldig `Authorization: Bearer <admin_token>`
+pub struct Admin;
+
+impl<S> FromRequestParts<S> for Admin
+where
+    S: Send + Sync,
+    AdminToken: FromRef<S>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let token = header(&parts.headers, AUTHORIZATION.as_str());
+        let bearer = token
+            .as_deref()
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match bearer {
+            Some(bearer) if AdminToken::from_ref(state).matches(bearer) => Ok(Admin),
+            _ => Err(ApiError::Unauthorized),
         }
     }
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let status = match self {
-            ApiError::UserInput { .. } => StatusCode::UNPROCESSABLE_ENTITY,
-            ApiError::InternalServer => StatusCode::INTERNAL_SERVER_ERROR,
-            ApiError::JsonParsing { .. } => StatusCode::BAD_REQUEST,
+/// `None` for alle som ikke har admin-tokenet, for endepunkter der admin bare
+/// får se mer
+impl<S> OptionalFromRequestParts<S> for Admin
+where
+    S: Send + Sync,
+    AdminToken: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        let admin = <Admin as FromRequestParts<S>>::from_request_parts(parts, state).await;
+        Ok(admin.ok())
+    }
+}
+
+/// Brukeren som eier API-nøkkelen i `Authorization: Bearer <nøkkel>`.
+/// `None` for anonyme forespørsler og for admin-tokenet.
+pub struct CurrentUser(pub Option<User>);
+
+impl CurrentUser {
+    pub fn id(&self) -> Option<Uuid> {
+        self.0.as_ref().map(|user| user.id)
+    }
+}
+
+impl<S> FromRequestParts<S> for CurrentUser
+where
+    S: Send + Sync,
+    AdminToken: FromRef<S>,
+    Arc<dyn Repository>: FromRef<S>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let token = header(&parts.headers, AUTHORIZATION.as_str());
+        let Some(bearer) = token
+            .as_deref()
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return Ok(Self(None));
         };
+        if AdminToken::from_ref(state).matches(bearer) {
+            return Ok(Self(None));
+        }
 
-        (status, axum::Json(self)).into_response()
+        let repository = Arc::<dyn Repository>::from_ref(state);
+        match repository.user_by_api_key(&hash_api_key(bearer)).await? {
+            Some(user) => Ok(Self(Some(user))),
+            None => Err(ApiError::Unauthorized),
+        }
     }
 }
+
+/// Ny API-nøkkel. Den vises bare én gang, og bare hashen lagres.
+pub fn generate_api_key() -> String {
+    let bytes: [u8; 32] = rand::random();
+    format!("{API_KEY_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes))
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Innhold i `POST /api/v1/admin/users`
+#[derive(Deserialize)]
+pub struct CreateUserRequest {
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct UserResponse {
+    pub id: Uuid,
+    pub time: u64,
+    pub name: String,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            time: user.time,
+            name: user.name,
+        }
+    }
+}
+
+/// Valgfritt innhold i `POST /api/v1/admin/users/{user_id}/keys`
+#[derive(Deserialize, Default)]
+pub struct CreateApiKeyRequest {
+    pub label: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub user: Uuid,
+    pub time: u64,
+    pub label: Option<String>,
+    pub revoked: Option<u64>,
+    /// Selve nøkkelen, bare når den nettopp er laget
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id,
+            user: key.user,
+            time: key.time,
+            label: key.label,
+            revoked: key.revoked,
+            key: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApiKeysResponse {
+    pub keys: Vec<ApiKeyResponse>,
+}
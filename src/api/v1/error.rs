@@ -0,0 +1,677 @@
+//! Feilresponser etter RFC 7807 (`application/problem+json`). Hver feil har
+//! en stabil, maskinlesbar `code`, mens `title` og `detail` oversettes.
+use std::error::Error;
+
+use axum::{
+    Extension,
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{
+        HeaderValue, StatusCode,
+        header::{CONTENT_TYPE, RETRY_AFTER},
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::{Card, DuplicateCard, Locale, StorageError};
+
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+#[derive(Clone, Debug)]
+pub enum ApiError {
+    InvalidOffset {
+        max: usize,
+        got: usize,
+    },
+    InvalidQuery {
+        field: Option<String>,
+        reason: Reason,
+    },
+    InvalidCursor,
+    /// Flere enn `max` kort krever admin eller at brukeren eier kortstokken
+    CountNotAllowed {
+        max: usize,
+    },
+    InvalidPath {
+        field: Option<String>,
+        reason: Reason,
+    },
+    MalformedJson {
+        detail: String,
+    },
+    InvalidBody {
+        field: Option<String>,
+        reason: Reason,
+    },
+    UnsupportedMediaType,
+    /// Innholdet er større enn grensen for endepunktet
+    BodyTooLarge,
+    TooManyHands {
+        max: usize,
+        got: usize,
+    },
+    /// `index` er hånden med for lang `external_id`
+    ExternalIdTooLong {
+        index: usize,
+        max: usize,
+    },
+    DuplicateCards(Vec<DuplicateCard>),
+    ComparisonNotFound,
+    DeckNotFound,
+    DrawNotFound,
+    DrawAlreadyStarted,
+    TableNotFound,
+    /// Det er så mange bord som serveren tar imot
+    TooManyTables,
+    UserNotFound,
+    ApiKeyNotFound,
+    /// `next` er plassen som skal bytte, fra 0, eller `None` når alle har
+    /// byttet
+    NotYourTurn {
+        next: Option<usize>,
+    },
+    Unauthorized,
+    /// `retry_after` er antall sekunder klienten må vente
+    RateLimited {
+        retry_after: u64,
+    },
+    Database,
+    InternalServer,
+}
+
+/// Hvorfor en verdi i forespørselen er ugyldig. `Rejected` er meldinger fra
+/// serde og axum, som bare finnes på engelsk.
+#[derive(Clone, Debug)]
+pub enum Reason {
+    Empty,
+    TooLong {
+        max: usize,
+    },
+    OutOfRange {
+        min: usize,
+        max: usize,
+    },
+    TooSmall {
+        min: usize,
+    },
+    /// Verdien må være minst like stor som feltet `other`
+    BelowField {
+        other: &'static str,
+    },
+    /// `big_blind` er mindre enn `small_blind`, eller blindene er 0
+    Blinds,
+    PresetWithCards,
+    UnknownCard(Card),
+    RepeatedCard(Card),
+    TooManyDiscards {
+        max: usize,
+        got: usize,
+    },
+    /// Posisjonen er ikke mellom 0 og 4, eller er oppgitt før
+    InvalidDiscard {
+        position: usize,
+    },
+    LineTooLong {
+        max: usize,
+    },
+    Rejected(String),
+}
+
+impl Reason {
+    /// `field` brukes i meldinger som handler om selve feltet
+    fn text(&self, field: Option<&str>, locale: Locale) -> String {
+        let field = field.unwrap_or("value");
+
+        match (self, locale) {
+            (Reason::Empty, Locale::En) => format!("{field} must not be empty"),
+            (Reason::Empty, Locale::Nb) => format!("{field} kan ikke være tom"),
+            (Reason::TooLong { max }, Locale::En) => {
+                format!("{field} must be at most {max} characters")
+            }
+            (Reason::TooLong { max }, Locale::Nb) => format!("{field} kan være høyst {max} tegn"),
+            (Reason::OutOfRange { min, max }, Locale::En) => {
+                format!("{field} must be between {min} and {max}")
+            }
+            (Reason::OutOfRange { min, max }, Locale::Nb) => {
+                format!("{field} må være mellom {min} og {max}")
+            }
+            (Reason::TooSmall { min }, Locale::En) => format!("{field} must be at least {min}"),
+            (Reason::TooSmall { min }, Locale::Nb) => format!("{field} må være minst {min}"),
+            (Reason::BelowField { other }, Locale::En) => {
+                format!("{field} must be at least {other}")
+            }
+            (Reason::BelowField { other }, Locale::Nb) => {
+                format!("{field} må være minst like stor som {other}")
+            }
+            (Reason::Blinds, Locale::En) => {
+                "blinds must be positive and big_blind at least small_blind".into()
+            }
+            (Reason::Blinds, Locale::Nb) => {
+                "blindene må være positive og big_blind minst like stor som small_blind".into()
+            }
+            (Reason::PresetWithCards, Locale::En) => "preset cannot be combined with cards".into(),
+            (Reason::PresetWithCards, Locale::Nb) => "preset kan ikke kombineres med cards".into(),
+            (Reason::UnknownCard(card), Locale::En) => format!("card {card} is not in the deck"),
+            (Reason::UnknownCard(card), Locale::Nb) => {
+                format!("kortet {card} finnes ikke i kortstokken")
+            }
+            (Reason::RepeatedCard(card), Locale::En) => {
+                format!("card {card} appears more than once")
+            }
+            (Reason::RepeatedCard(card), Locale::Nb) => {
+                format!("kortet {card} forekommer flere ganger")
+            }
+            (Reason::TooManyDiscards { max, got }, Locale::En) => {
+                format!("at most {max} cards can be discarded, got {got}")
+            }
+            (Reason::TooManyDiscards { max, got }, Locale::Nb) => {
+                format!("høyst {max} kort kan byttes, fikk {got}")
+            }
+            (Reason::InvalidDiscard { position }, Locale::En) => {
+                format!("position {position} must be between 0 and 4 and only given once")
+            }
+            (Reason::InvalidDiscard { position }, Locale::Nb) => {
+                format!("posisjon {position} må være mellom 0 og 4 og bare oppgis én gang")
+            }
+            (Reason::LineTooLong { max }, Locale::En) => format!("Line is longer than {max} bytes"),
+            (Reason::LineTooLong { max }, Locale::Nb) => format!("Linjen er lengre enn {max} byte"),
+            (Reason::Rejected(detail), _) => detail.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: &'static str,
+    status: u16,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+/// Feil knyttet til et bestemt felt i forespørselen
+#[derive(Serialize)]
+struct FieldError {
+    field: String,
+    code: &'static str,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    card: Option<Card>,
+}
+
+impl FieldError {
+    fn new(field: impl Into<String>, code: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code,
+            detail: detail.into(),
+            external_id: None,
+            card: None,
+        }
+    }
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidOffset { .. }
+            | ApiError::TooManyHands { .. }
+            | ApiError::ExternalIdTooLong { .. }
+            | ApiError::DuplicateCards(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::InvalidQuery { .. }
+            | ApiError::InvalidCursor
+            | ApiError::InvalidPath { .. }
+            | ApiError::MalformedJson { .. }
+            | ApiError::InvalidBody { .. } => StatusCode::BAD_REQUEST,
+            ApiError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::BodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::ComparisonNotFound
+            | ApiError::DeckNotFound
+            | ApiError::DrawNotFound
+            | ApiError::TableNotFound
+            | ApiError::UserNotFound
+            | ApiError::ApiKeyNotFound => StatusCode::NOT_FOUND,
+            ApiError::DrawAlreadyStarted | ApiError::NotYourTurn { .. } => StatusCode::CONFLICT,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::CountNotAllowed { .. } => StatusCode::FORBIDDEN,
+            ApiError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::TooManyTables => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Database | ApiError::InternalServer => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stabil kode som klienter kan skille feil på
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidOffset { .. } => "invalid_offset",
+            ApiError::InvalidQuery { .. } => "invalid_query",
+            ApiError::InvalidCursor => "invalid_cursor",
+            ApiError::CountNotAllowed { .. } => "count_not_allowed",
+            ApiError::InvalidPath { .. } => "invalid_path",
+            ApiError::MalformedJson { .. } => "malformed_json",
+            ApiError::InvalidBody { .. } => "invalid_body",
+            ApiError::UnsupportedMediaType => "unsupported_media_type",
+            ApiError::BodyTooLarge => "body_too_large",
+            ApiError::TooManyHands { .. } => "too_many_hands",
+            ApiError::ExternalIdTooLong { .. } => "external_id_too_long",
+            ApiError::DuplicateCards(_) => "duplicate_cards",
+            ApiError::ComparisonNotFound => "comparison_not_found",
+            ApiError::DeckNotFound => "deck_not_found",
+            ApiError::DrawNotFound => "draw_not_found",
+            ApiError::DrawAlreadyStarted => "draw_already_started",
+            ApiError::TableNotFound => "table_not_found",
+            ApiError::TooManyTables => "too_many_tables",
+            ApiError::UserNotFound => "user_not_found",
+            ApiError::ApiKeyNotFound => "api_key_not_found",
+            ApiError::NotYourTurn { .. } => "not_your_turn",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::Database => "database_error",
+            ApiError::InternalServer => "internal_error",
+        }
+    }
+
+    fn title(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (ApiError::InvalidOffset { .. }, Locale::En) => "Invalid offset",
+            (ApiError::InvalidOffset { .. }, Locale::Nb) => "Ugyldig offset",
+            (ApiError::InvalidQuery { .. }, Locale::En) => "Invalid query string",
+            (ApiError::InvalidQuery { .. }, Locale::Nb) => "Ugyldige query-parametre",
+            (ApiError::InvalidCursor, Locale::En) => "Invalid cursor",
+            (ApiError::InvalidCursor, Locale::Nb) => "Ugyldig markør",
+            (ApiError::CountNotAllowed { .. }, Locale::En) => "Too many cards",
+            (ApiError::CountNotAllowed { .. }, Locale::Nb) => "For mange kort",
+            (ApiError::InvalidPath { .. }, Locale::En) => "Invalid path",
+            (ApiError::InvalidPath { .. }, Locale::Nb) => "Ugyldig sti",
+            (ApiError::MalformedJson { .. }, Locale::En) => "Malformed JSON",
+            (ApiError::MalformedJson { .. }, Locale::Nb) => "Ugyldig JSON",
+            (ApiError::InvalidBody { .. }, Locale::En) => "Invalid request body",
+            (ApiError::InvalidBody { .. }, Locale::Nb) => "Ugyldig innhold i forespørselen",
+            (ApiError::UnsupportedMediaType, Locale::En) => "Unsupported media type",
+            (ApiError::UnsupportedMediaType, Locale::Nb) => "Innholdstypen støttes ikke",
+            (ApiError::BodyTooLarge, Locale::En) => "Request body too large",
+            (ApiError::BodyTooLarge, Locale::Nb) => "For stort innhold",
+            (ApiError::TooManyHands { .. }, Locale::En) => "Too many hands",
+            (ApiError::TooManyHands { .. }, Locale::Nb) => "For mange hender",
+            (ApiError::ExternalIdTooLong { .. }, Locale::En) => "External id too long",
+            (ApiError::ExternalIdTooLong { .. }, Locale::Nb) => "For lang ekstern id",
+            (ApiError::DuplicateCards(_), Locale::En) => "Duplicate cards",
+            (ApiError::DuplicateCards(_), Locale::Nb) => "Kort brukt flere ganger",
+            (ApiError::ComparisonNotFound, Locale::En) => "Comparison not found",
+            (ApiError::ComparisonNotFound, Locale::Nb) => "Fant ikke sammenligningen",
+            (ApiError::DeckNotFound, Locale::En) => "Deck not found",
+            (ApiError::DeckNotFound, Locale::Nb) => "Fant ikke kortstokken",
+            (ApiError::DrawNotFound, Locale::En) => "Draw game not found",
+            (ApiError::DrawNotFound, Locale::Nb) => "Fant ikke draw-spillet",
+            (ApiError::DrawAlreadyStarted, Locale::En) => "Draw game already started",
+            (ApiError::DrawAlreadyStarted, Locale::Nb) => "Draw-spillet er allerede startet",
+            (ApiError::TableNotFound, Locale::En) => "Table not found",
+            (ApiError::TableNotFound, Locale::Nb) => "Fant ikke bordet",
+            (ApiError::TooManyTables, Locale::En) => "Too many tables",
+            (ApiError::TooManyTables, Locale::Nb) => "For mange bord",
+            (ApiError::UserNotFound, Locale::En) => "User not found",
+            (ApiError::UserNotFound, Locale::Nb) => "Fant ikke brukeren",
+            (ApiError::ApiKeyNotFound, Locale::En) => "API key not found",
+            (ApiError::ApiKeyNotFound, Locale::Nb) => "Fant ikke API-nøkkelen",
+            (ApiError::NotYourTurn { .. }, Locale::En) => "Not your turn",
+            (ApiError::NotYourTurn { .. }, Locale::Nb) => "Ikke din tur",
+            (ApiError::Unauthorized, Locale::En) => "Unauthorized",
+            (ApiError::Unauthorized, Locale::Nb) => "Ikke autorisert",
+            (ApiError::RateLimited { .. }, Locale::En) => "Too many requests",
+            (ApiError::RateLimited { .. }, Locale::Nb) => "For mange forespørsler",
+            (ApiError::Database, Locale::En) => "Database error",
+            (ApiError::Database, Locale::Nb) => "Databasefeil",
+            (ApiError::InternalServer, Locale::En) => "Internal server error",
+            (ApiError::InternalServer, Locale::Nb) => "Intern feil",
+        }
+    }
+
+    fn detail(&self, locale: Locale) -> Option<String> {
+        let detail = match (self, locale) {
+            (ApiError::InvalidOffset { max, got }, Locale::En) => {
+                format!("Invalid offset. Expected a number between 0 and {max}, got {got}")
+            }
+            (ApiError::InvalidOffset { max, got }, Locale::Nb) => {
+                format!("Ugyldig offset. Forventet et tall mellom 0 og {max}, fikk {got}")
+            }
+            (
+                ApiError::InvalidQuery { field, reason }
+                | ApiError::InvalidPath { field, reason }
+                | ApiError::InvalidBody { field, reason },
+                locale,
+            ) => reason.text(field.as_deref(), locale),
+            (ApiError::MalformedJson { detail }, _) => detail.clone(),
+            (ApiError::InvalidCursor, Locale::En) => {
+                "The cursor must be a `next_cursor` or `prev_cursor` from an earlier response"
+                    .into()
+            }
+            (ApiError::InvalidCursor, Locale::Nb) => {
+                "Markøren må være en `next_cursor` eller `prev_cursor` fra et tidligere svar".into()
+            }
+            (ApiError::CountNotAllowed { max }, Locale::En) => format!(
+                "Only an admin or the owner of the deck can get more than {max} cards at once"
+            ),
+            (ApiError::CountNotAllowed { max }, Locale::Nb) => format!(
+                "Bare admin eller eieren av kortstokken kan hente mer enn {max} kort om gangen"
+            ),
+            (ApiError::UnsupportedMediaType, Locale::En) => {
+                "Expected request with `Content-Type: application/json`".into()
+            }
+            (ApiError::UnsupportedMediaType, Locale::Nb) => {
+                "Forventet en forespørsel med `Content-Type: application/json`".into()
+            }
+            (ApiError::BodyTooLarge, Locale::En) => {
+                "The request body is larger than this endpoint accepts".into()
+            }
+            (ApiError::BodyTooLarge, Locale::Nb) => {
+                "Innholdet i forespørselen er større enn endepunktet tar imot".into()
+            }
+            (ApiError::TooManyHands { max, got }, Locale::En) => {
+                format!("At most {max} hands can be compared at once, got {got}")
+            }
+            (ApiError::TooManyHands { max, got }, Locale::Nb) => {
+                format!("Høyst {max} hender kan sammenlignes samtidig, fikk {got}")
+            }
+            (ApiError::ExternalIdTooLong { max, .. }, Locale::En) => {
+                format!("external_id must be at most {max} characters")
+            }
+            (ApiError::ExternalIdTooLong { max, .. }, Locale::Nb) => {
+                format!("external_id kan være høyst {max} tegn")
+            }
+            (ApiError::DuplicateCards(duplicates), Locale::En) => format!(
+                "{} card(s) are used more than once. Each card can only appear once across all hands",
+                duplicates.len()
+            ),
+            (ApiError::DuplicateCards(duplicates), Locale::Nb) => format!(
+                "{} kort er brukt mer enn én gang. Hvert kort kan bare forekomme én gang i alle hendene",
+                duplicates.len()
+            ),
+            (ApiError::ComparisonNotFound, Locale::En) => {
+                "No comparison with this id has been stored".into()
+            }
+            (ApiError::ComparisonNotFound, Locale::Nb) => {
+                "Det er ikke lagret noen sammenligning med denne id-en".into()
+            }
+            (ApiError::DeckNotFound, Locale::En) => {
+                "No deck with this id has been created with `POST /api/v1/decks`".into()
+            }
+            (ApiError::DeckNotFound, Locale::Nb) => {
+                "Ingen kortstokk med denne id-en er laget med `POST /api/v1/decks`".into()
+            }
+            (ApiError::DrawNotFound, Locale::En) => {
+                "No draw game has been started with this deck".into()
+            }
+            (ApiError::DrawNotFound, Locale::Nb) => {
+                "Det er ikke startet noe draw-spill med denne kortstokken".into()
+            }
+            (ApiError::DrawAlreadyStarted, Locale::En) => {
+                "Each deck can only be used for one draw game".into()
+            }
+            (ApiError::DrawAlreadyStarted, Locale::Nb) => {
+                "Hver kortstokk kan bare brukes til ett draw-spill".into()
+            }
+            (ApiError::TableNotFound, Locale::En) => {
+                "The table does not exist or every player has left it".into()
+            }
+            (ApiError::TableNotFound, Locale::Nb) => {
+                "Bordet finnes ikke, eller alle spillerne har gått".into()
+            }
+            (ApiError::TooManyTables, Locale::En) => {
+                "The server has as many open tables as it allows, try again later".into()
+            }
+            (ApiError::TooManyTables, Locale::Nb) => {
+                "Serveren har så mange åpne bord som den tillater, prøv igjen senere".into()
+            }
+            (ApiError::UserNotFound, Locale::En) => "No user with this id exists".into(),
+            (ApiError::UserNotFound, Locale::Nb) => {
+                "Det finnes ingen bruker med denne id-en".into()
+            }
+            (ApiError::ApiKeyNotFound, Locale::En) => {
+                "No active API key with this id exists".into()
+            }
+            (ApiError::ApiKeyNotFound, Locale::Nb) => {
+                "Det finnes ingen aktiv API-nøkkel med denne id-en".into()
+            }
+            (ApiError::NotYourTurn { next: Some(next) }, Locale::En) => {
+                format!("It is seat {}'s turn to draw", next + 1)
+            }
+            (ApiError::NotYourTurn { next: Some(next) }, Locale::Nb) => {
+                format!("Det er plass {} sin tur til å bytte", next + 1)
+            }
+            (ApiError::NotYourTurn { next: None }, Locale::En) => "Every seat has drawn".into(),
+            (ApiError::NotYourTurn { next: None }, Locale::Nb) => "Alle plassene har byttet".into(),
+            (ApiError::Unauthorized, Locale::En) => {
+                "This endpoint requires `Authorization: Bearer <token>` with a valid token".into()
+            }
+            (ApiError::Unauthorized, Locale::Nb) => {
+                "Endepunktet krever `Authorization: Bearer <token>` med et gyldig token".into()
+            }
+            (ApiError::RateLimited { retry_after }, Locale::En) => {
+                format!("Too many requests, try again in {retry_after} seconds")
+            }
+            (ApiError::RateLimited { retry_after }, Locale::Nb) => {
+                format!("For mange forespørsler, prøv igjen om {retry_after} sekunder")
+            }
+            (ApiError::Database | ApiError::InternalServer, _) => return None,
+        };
+
+        Some(detail)
+    }
+
+    fn errors(&self, locale: Locale) -> Vec<FieldError> {
+        match self {
+            ApiError::InvalidOffset { .. } => vec![FieldError::new(
+                "offset",
+                "out_of_range",
+                self.detail(locale).unwrap_or_default(),
+            )],
+            ApiError::InvalidCursor => vec![FieldError::new(
+                "cursor",
+                "invalid_value",
+                self.detail(locale).unwrap_or_default(),
+            )],
+            ApiError::CountNotAllowed { .. } => vec![FieldError::new(
+                "count",
+                "out_of_range",
+                self.detail(locale).unwrap_or_default(),
+            )],
+            ApiError::TooManyHands { .. } => vec![FieldError::new(
+                "hands",
+                "too_many",
+                self.detail(locale).unwrap_or_default(),
+            )],
+            ApiError::ExternalIdTooLong { index, .. } => vec![FieldError::new(
+                format!("hands[{index}].external_id"),
+                "too_long",
+                self.detail(locale).unwrap_or_default(),
+            )],
+            ApiError::DuplicateCards(duplicates) => duplicates
+                .iter()
+                .map(|duplicate| FieldError {
+                    external_id: Some(duplicate.external_id.clone()),
+                    card: Some(duplicate.card.clone()),
+                    ..FieldError::new(
+                        format!("hands[{}].hand[{}]", duplicate.hand, duplicate.position),
+                        "duplicate_card",
+                        duplicate_detail(duplicate, locale),
+                    )
+                })
+                .collect(),
+            ApiError::InvalidQuery {
+                field: Some(field), ..
+            }
+            | ApiError::InvalidPath {
+                field: Some(field), ..
+            }
+            | ApiError::InvalidBody {
+                field: Some(field), ..
+            } => vec![FieldError::new(
+                field.clone(),
+                "invalid_value",
+                self.detail(locale).unwrap_or_default(),
+            )],
+            _ => vec![],
+        }
+    }
+
+    pub fn problem(&self, locale: Locale) -> Problem {
+        Problem {
+            problem_type: format!("urn:pokerhaand:problem:{}", self.code()),
+            title: self.title(locale),
+            status: self.status().as_u16(),
+            code: self.code(),
+            detail: self.detail(locale),
+            errors: self.errors(locale),
+        }
+    }
+
+    /// Lager responsen på gitt språk. Feilen legges også ved som
+    /// extension slik at den kan oversettes av `localize_errors`.
+    pub fn render(&self, locale: Locale) -> Response {
+        let status = self.status();
+        let problem = self.problem(locale);
+
+        let mut response = (
+            status,
+            [(CONTENT_TYPE, PROBLEM_CONTENT_TYPE)],
+            Extension(self.clone()),
+            axum::Json(problem),
+        )
+            .into_response();
+        if let ApiError::RateLimited { retry_after } = self {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(*retry_after));
+        }
+
+        response
+    }
+}
+
+/// Klienten får bare `database_error`, så den opprinnelige feilen logges her
+impl From<StorageError> for ApiError {
+    fn from(error: StorageError) -> Self {
+        tracing::error!(%error, source = ?error.source(), "storage request failed");
+        ApiError::Database
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        self.render(Locale::default())
+    }
+}
+
+fn duplicate_detail(duplicate: &DuplicateCard, locale: Locale) -> String {
+    let DuplicateCard {
+        external_id, card, ..
+    } = duplicate;
+
+    match (&duplicate.first_seen_in, locale) {
+        (Some(other), Locale::En) => {
+            format!("Card {card} in hand {external_id:?} is already used in hand {other:?}")
+        }
+        (Some(other), Locale::Nb) => {
+            format!("Kortet {card} i hånd {external_id:?} er allerede brukt i hånd {other:?}")
+        }
+        (None, Locale::En) => {
+            format!("Card {card} appears more than once in hand {external_id:?}")
+        }
+        (None, Locale::Nb) => {
+            format!("Kortet {card} forekommer flere ganger i hånd {external_id:?}")
+        }
+    }
+}
+
+/// Finner feltet og feilmeldingen fra serde, dersom avvisningen skyldes
+/// at et felt ikke kunne deserialiseres
+fn field_error<E: Error + 'static>(rejection: &dyn Error) -> Option<(String, String)> {
+    let mut source = rejection.source();
+
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<serde_path_to_error::Error<E>>() {
+            return Some((error.path().to_string(), error.inner().to_string()));
+        }
+        source = error.source();
+    }
+
+    None
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::JsonDataError(ref error) => Self::InvalidBody {
+                field: field_error::<serde_json::Error>(error).map(|(field, _)| field),
+                reason: Reason::Rejected(rejection.body_text()),
+            },
+            JsonRejection::JsonSyntaxError(_) => Self::MalformedJson {
+                detail: rejection.body_text(),
+            },
+            JsonRejection::MissingJsonContentType(_) => Self::UnsupportedMediaType,
+            JsonRejection::BytesRejection(_)
+                if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE =>
+            {
+                Self::BodyTooLarge
+            }
+            _ => Self::InvalidBody {
+                field: None,
+                reason: Reason::Rejected(rejection.body_text()),
+            },
+        }
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for ApiError {
+    fn from(error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        use serde_json::error::Category;
+
+        match error.inner().classify() {
+            Category::Data => Self::InvalidBody {
+                field: Some(error.path().to_string()),
+                reason: Reason::Rejected(error.to_string()),
+            },
+            _ => Self::MalformedJson {
+                detail: error.inner().to_string(),
+            },
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        let field = field_error::<serde_urlencoded::de::Error>(&rejection).map(|(field, _)| field);
+
+        Self::InvalidQuery {
+            field,
+            reason: Reason::Rejected(rejection.body_text()),
+        }
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        use axum::extract::path::ErrorKind;
+
+        let field = match &rejection {
+            PathRejection::FailedToDeserializePathParams(error) => match error.kind() {
+                ErrorKind::ParseErrorAtKey { key, .. }
+                | ErrorKind::DeserializeError { key, .. }
+                | ErrorKind::InvalidUtf8InPathParam { key } => Some(key.clone()),
+                _ => None,
+            },
+            _ => return Self::InternalServer,
+        };
+
+        Self::InvalidPath {
+            field,
+            reason: Reason::Rejected(rejection.body_text()),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! WebSocket for bordene. Klienten sender `join` først, og deretter `start`,
+//! `action` eller `leave`. Hendelsene fra bordet sendes som JSON-tekst.
+use axum::extract::ws::{Message, WebSocket};
+use serde::Deserialize;
+
+use crate::{
+    holdem::game::Action,
+    tables::{TableEvent, TableHandle},
+};
+
+const NAME_MAX_LENGTH: usize = 50;
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Join {
+        name: String,
+    },
+    Start,
+    Action {
+        #[serde(flatten)]
+        action: Action,
+    },
+    Leave,
+}
+
+pub async fn play(mut socket: WebSocket, table: TableHandle) {
+    let name = loop {
+        match receive(&mut socket).await {
+            None | Some(Ok(ClientMessage::Leave)) => return,
+            Some(Ok(ClientMessage::Join { name })) => match check_name(&name) {
+                Ok(()) => break name,
+                Err(detail) => send_error(&mut socket, "invalid_message", detail).await,
+            },
+            Some(Ok(_)) => {
+                send_error(&mut socket, "not_seated", "join the table first".into()).await
+            }
+            Some(Err(detail)) => send_error(&mut socket, "invalid_message", detail).await,
+        }
+    };
+
+    let mut seat = match table.join(name).await {
+        Some(Ok(seat)) => seat,
+        Some(Err(error)) => {
+            send_error(&mut socket, error.code(), error.to_string()).await;
+            return;
+        }
+        // Alle gikk mens klienten koblet seg til
+        None => {
+            send_error(&mut socket, "table_not_found", "the table is closed".into()).await;
+            return;
+        }
+    };
+
+    // `seat` droppes når løkken er ferdig, og da forlater spilleren bordet
+    loop {
+        tokio::select! {
+            event = seat.events.recv() => {
+                let Some(event) = event else { break };
+                if send(&mut socket, &event).await.is_err() {
+                    break;
+                }
+            }
+            message = receive(&mut socket) => match message {
+                None | Some(Ok(ClientMessage::Leave)) => break,
+                Some(Ok(ClientMessage::Start)) => seat.start(),
+                Some(Ok(ClientMessage::Action { action })) => seat.act(action),
+                Some(Ok(ClientMessage::Join { .. })) => {
+                    let detail = "already seated at the table".into();
+                    send_error(&mut socket, "already_seated", detail).await;
+                }
+                Some(Err(detail)) => send_error(&mut socket, "invalid_message", detail).await,
+            },
+        }
+    }
+}
+
+/// Neste melding fra klienten. `None` når forbindelsen er lukket.
+async fn receive(socket: &mut WebSocket) -> Option<Result<ClientMessage, String>> {
+    loop {
+        match socket.recv().await? {
+            Ok(Message::Text(text)) => {
+                return Some(serde_json::from_str(&text).map_err(|error| error.to_string()));
+            }
+            Ok(Message::Binary(_)) => return Some(Err("messages must be JSON text".into())),
+            Ok(Message::Close(_)) | Err(_) => return None,
+            // Axum svarer selv på ping
+            Ok(Message::Ping(_) | Message::Pong(_)) => {}
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, event: &TableEvent) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(event).expect("table events serialize");
+    socket.send(Message::Text(text.into())).await
+}
+
+async fn send_error(socket: &mut WebSocket, code: &'static str, detail: String) {
+    let _ = send(socket, &TableEvent::Error { code, detail }).await;
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("name must not be empty".into());
+    }
+    if name.chars().count() > NAME_MAX_LENGTH {
+        return Err(format!("name must be at most {NAME_MAX_LENGTH} characters"));
+    }
+    Ok(())
+}
@@ -1,2 +1,7 @@
+pub mod deal;
 pub mod deck;
+pub mod draw;
+pub mod game;
 pub mod hand;
+pub mod showdown;
+pub mod validation;
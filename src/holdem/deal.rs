@@ -0,0 +1,436 @@
+//! Utdeling av et helt bord fra en kortstokk, i samme rekkefølge som en
+//! dealer ville delt ut kortene
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    deck::{Card, Rank},
+    hand::Hand,
+    showdown::places,
+};
+
+pub const MIN_PLAYERS: usize = 2;
+
+/// Pokervarianter som kan deles ut
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    /// Texas Hold'em, med to egne kort og fem på bordet
+    #[default]
+    Holdem,
+    /// Seven-card stud, med tre kort på hånden og fire synlige
+    Stud,
+}
+
+impl Variant {
+    /// Flere spillere får ikke plass rundt bordet, eller kortene holder ikke
+    pub fn max_players(&self) -> usize {
+        match self {
+            Variant::Holdem => 10,
+            // 7 kort hver og 4 brente kort
+            Variant::Stud => 6,
+        }
+    }
+}
+
+/// Et utdelt bord
+#[derive(Clone, Debug)]
+pub struct Table {
+    pub variant: Variant,
+    pub seats: Vec<Seat>,
+    /// Felleskortene i hold'em
+    pub board: Option<Board>,
+    /// Kortene som ble brent før hver runde
+    pub burned: Vec<Card>,
+    /// Hvem som starter hver runde i stud. Tom for hold'em.
+    pub streets: Vec<Street>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Seat {
+    /// Kortene bare spilleren ser
+    pub hole_cards: Vec<Card>,
+    /// Kortene alle ser, i stud
+    pub up_cards: Vec<Card>,
+    /// Den beste hånden av fem av kortene spilleren kan bruke
+    pub hand: Hand,
+    /// 1 for vinnerne, se `places`
+    pub place: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Board {
+    pub flop: [Card; 3],
+    pub turn: Card,
+    pub river: Card,
+}
+
+/// En runde i stud
+#[derive(Clone, Debug)]
+pub struct Street {
+    /// Fra 3 til 7
+    pub street: usize,
+    /// Indeksen til plassen som handler først
+    pub first_to_act: usize,
+    /// Om plassen som handler først må by inn, som på tredje runde
+    pub bring_in: bool,
+}
+
+impl Table {
+    /// Indeksene til plassene som vant
+    pub fn winners(&self) -> Vec<usize> {
+        self.seats
+            .iter()
+            .enumerate()
+            .filter(|(_, seat)| seat.place == 1)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DealError {
+    PlayersOutOfRange {
+        max: usize,
+        got: usize,
+    },
+    /// Kortstokken har for få kort til alle spillerne
+    NotEnoughCards {
+        needed: usize,
+        got: usize,
+    },
+}
+
+impl fmt::Display for DealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DealError::PlayersOutOfRange { max, got } => write!(
+                f,
+                "players must be between {MIN_PLAYERS} and {max}, got {got}"
+            ),
+            DealError::NotEnoughCards { needed, got } => {
+                write!(f, "the deal needs {needed} cards, the deck has {got}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DealError {}
+
+/// Deler ut fra toppen av kortstokken. Hver spiller får ett kort om gangen,
+/// og ett kort brennes før hver runde etter den første.
+pub fn deal(deck: &[Card], variant: Variant, players: usize) -> Result<Table, DealError> {
+    let max = variant.max_players();
+    if !(MIN_PLAYERS..=max).contains(&players) {
+        return Err(DealError::PlayersOutOfRange { max, got: players });
+    }
+
+    let needed = match variant {
+        Variant::Holdem => 2 * players + 8,
+        Variant::Stud => 7 * players + 4,
+    };
+    if deck.len() < needed {
+        return Err(DealError::NotEnoughCards {
+            needed,
+            got: deck.len(),
+        });
+    }
+
+    let mut dealer = Dealer {
+        cards: deck.iter(),
+        burned: vec![],
+    };
+    match variant {
+        Variant::Holdem => Ok(deal_holdem(&mut dealer, players)),
+        Variant::Stud => Ok(deal_stud(&mut dealer, players)),
+    }
+}
+
+/// Holder styr på hvor langt i kortstokken utdelingen har kommet
+struct Dealer<'a> {
+    cards: core::slice::Iter<'a, Card>,
+    burned: Vec<Card>,
+}
+
+impl Dealer<'_> {
+    /// Antall kort er sjekket på forhånd
+    fn next(&mut self) -> Card {
+        self.cards.next().expect("enough cards").clone()
+    }
+
+    fn burn(&mut self) {
+        let card = self.next();
+        self.burned.push(card);
+    }
+
+    /// Ett kort til hver spiller
+    fn round(&mut self, players: usize) -> Vec<Card> {
+        (0..players).map(|_| self.next()).collect()
+    }
+}
+
+fn deal_holdem(dealer: &mut Dealer, players: usize) -> Table {
+    let mut hole_cards = vec![vec![]; players];
+    for _ in 0..2 {
+        for (seat, card) in dealer.round(players).into_iter().enumerate() {
+            hole_cards[seat].push(card);
+        }
+    }
+
+    dealer.burn();
+    let flop = [dealer.next(), dealer.next(), dealer.next()];
+    dealer.burn();
+    let turn = dealer.next();
+    dealer.burn();
+    let river = dealer.next();
+    let board = Board { flop, turn, river };
+
+    let seats = hole_cards
+        .into_iter()
+        .map(|hole_cards| {
+            let cards: Vec<Card> = hole_cards
+                .iter()
+                .chain(&board.flop)
+                .chain([&board.turn, &board.river])
+                .cloned()
+                .collect();
+            (hole_cards, vec![], cards)
+        })
+        .collect();
+
+    finish(Variant::Holdem, seats, Some(board), dealer, vec![])
+}
+
+fn deal_stud(dealer: &mut Dealer, players: usize) -> Table {
+    let mut hole_cards = vec![vec![]; players];
+    let mut up_cards = vec![vec![]; players];
+    let mut streets = vec![];
+
+    // Tredje runde: to kort ned og ett opp
+    for _ in 0..2 {
+        for (seat, card) in dealer.round(players).into_iter().enumerate() {
+            hole_cards[seat].push(card);
+        }
+    }
+    for (seat, card) in dealer.round(players).into_iter().enumerate() {
+        up_cards[seat].push(card);
+    }
+    streets.push(Street {
+        street: 3,
+        first_to_act: bring_in(&up_cards),
+        bring_in: true,
+    });
+
+    // Fjerde til sjette runde med ett kort opp, og sjuende med ett ned
+    for street in 4..=7 {
+        dealer.burn();
+        for (seat, card) in dealer.round(players).into_iter().enumerate() {
+            match street {
+                7 => hole_cards[seat].push(card),
+                _ => up_cards[seat].push(card),
+            }
+        }
+        streets.push(Street {
+            street,
+            first_to_act: best_showing(&up_cards),
+            bring_in: false,
+        });
+    }
+
+    let seats = hole_cards
+        .into_iter()
+        .zip(up_cards)
+        .map(|(hole_cards, up_cards)| {
+            let cards = hole_cards.iter().chain(&up_cards).cloned().collect();
+            (hole_cards, up_cards, cards)
+        })
+        .collect();
+
+    finish(Variant::Stud, seats, None, dealer, streets)
+}
+
+/// Finner den beste hånden til hver plass og hvem som vant
+fn finish(
+    variant: Variant,
+    seats: Vec<(Vec<Card>, Vec<Card>, Vec<Card>)>,
+    board: Option<Board>,
+    dealer: &mut Dealer,
+    streets: Vec<Street>,
+) -> Table {
+    let hands: Vec<Hand> = seats
+        .iter()
+        .map(|(_, _, cards)| Hand::best_of(cards).expect("seven cards make a hand"))
+        .collect();
+    let places = places(&hands);
+
+    let seats = seats
+        .into_iter()
+        .zip(hands)
+        .zip(places)
+        .map(|(((hole_cards, up_cards, _), hand), place)| Seat {
+            hole_cards,
+            up_cards,
+            hand,
+            place,
+        })
+        .collect();
+
+    Table {
+        variant,
+        seats,
+        board,
+        burned: core::mem::take(&mut dealer.burned),
+        streets,
+    }
+}
+
+/// Plassen med det laveste synlige kortet på tredje runde. Ess er høyt, og
+/// ved lik valør har laveste farge bring-in.
+fn bring_in(up_cards: &[Vec<Card>]) -> usize {
+    up_cards
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, cards)| (cards[0].rank, cards[0].suit))
+        .map(|(seat, _)| seat)
+        .expect("at least two players")
+}
+
+/// Plassen med den beste synlige hånden. Med like hender starter den
+/// første av dem.
+fn best_showing(up_cards: &[Vec<Card>]) -> usize {
+    let mut best = 0;
+    for seat in 1..up_cards.len() {
+        if showing(&up_cards[seat]) > showing(&up_cards[best]) {
+            best = seat;
+        }
+    }
+    best
+}
+
+/// Styrken til opptil fire synlige kort. Bare par, tress og firlinger teller,
+/// så antallet av hver valør sammenlignes først, og så valørene.
+fn showing(cards: &[Card]) -> (Vec<usize>, Vec<Rank>) {
+    let mut groups: Vec<(usize, Rank)> = vec![];
+    for card in cards {
+        match groups.iter_mut().find(|(_, rank)| *rank == card.rank) {
+            Some((count, _)) => *count += 1,
+            None => groups.push((1, card.rank)),
+        }
+    }
+    groups.sort_by(|a, b| b.cmp(a));
+
+    groups.into_iter().unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::deck::{DeckProvider, Suit},
+        *,
+    };
+
+    fn c(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    #[test]
+    fn deals_round_robin_and_burns_before_each_street() {
+        let deck = DeckProvider::default().get_with_seed(42);
+        let table = deal(&deck, Variant::Holdem, 3).unwrap();
+        let board = table.board.as_ref().unwrap();
+
+        assert_eq!(
+            table.seats[0].hole_cards,
+            [deck[0].clone(), deck[3].clone()]
+        );
+        assert_eq!(
+            table.seats[2].hole_cards,
+            [deck[2].clone(), deck[5].clone()]
+        );
+        assert_eq!(
+            table.burned,
+            [deck[6].clone(), deck[10].clone(), deck[12].clone()]
+        );
+        assert_eq!(
+            board.flop,
+            [deck[7].clone(), deck[8].clone(), deck[9].clone()]
+        );
+        assert_eq!(board.turn, deck[11]);
+        assert_eq!(board.river, deck[13]);
+        assert!(!table.winners().is_empty());
+    }
+
+    #[test]
+    fn rejects_player_count_out_of_range() {
+        let deck = DeckProvider::default().get_with_seed(0);
+
+        assert_eq!(
+            deal(&deck, Variant::Holdem, 1).unwrap_err(),
+            DealError::PlayersOutOfRange { max: 10, got: 1 }
+        );
+        assert!(deal(&deck, Variant::Holdem, 11).is_err());
+        assert!(deal(&deck, Variant::Stud, 7).is_err());
+        assert!(matches!(
+            deal(&deck[..10], Variant::Holdem, 2),
+            Err(DealError::NotEnoughCards { needed: 12, .. })
+        ));
+    }
+
+    #[test]
+    fn deals_stud_streets_with_up_and_down_cards() {
+        let deck = DeckProvider::default().get_with_seed(7);
+        let table = deal(&deck, Variant::Stud, 2).unwrap();
+        let seat = &table.seats[1];
+
+        assert_eq!(
+            seat.hole_cards,
+            [deck[1].clone(), deck[3].clone(), deck[17].clone()]
+        );
+        assert_eq!(
+            seat.up_cards,
+            [
+                deck[5].clone(),
+                deck[8].clone(),
+                deck[11].clone(),
+                deck[14].clone()
+            ]
+        );
+        assert_eq!(table.burned.len(), 4);
+        assert!(table.board.is_none());
+        assert_eq!(table.streets.len(), 5);
+        assert!(table.streets[0].bring_in);
+    }
+
+    #[test]
+    fn lowest_upcard_brings_in_with_suit_as_tiebreak() {
+        let up_cards = vec![
+            vec![c(Rank::Numeral(2), Suit::Hearts)],
+            vec![c(Rank::Ace, Suit::Clubs)],
+            vec![c(Rank::Numeral(2), Suit::Diamonds)],
+        ];
+
+        assert_eq!(bring_in(&up_cards), 2);
+    }
+
+    #[test]
+    fn best_visible_hand_acts_first() {
+        let up_cards = vec![
+            vec![c(Rank::Ace, Suit::Clubs), c(Rank::King, Suit::Clubs)],
+            vec![
+                c(Rank::Numeral(3), Suit::Clubs),
+                c(Rank::Numeral(3), Suit::Hearts),
+            ],
+            vec![c(Rank::Ace, Suit::Spades), c(Rank::King, Suit::Hearts)],
+        ];
+        assert_eq!(best_showing(&up_cards), 1);
+
+        // Like hender: den første starter
+        assert_eq!(best_showing(&[up_cards[0].clone(), up_cards[2].clone()]), 0);
+
+        // To par slår et høyere par
+        let two_pair = [2, 2, 3, 3].map(|n| c(Rank::Numeral(n), Suit::Clubs));
+        let aces = [c(Rank::Ace, Suit::Clubs), c(Rank::Ace, Suit::Hearts)];
+        assert!(showing(&two_pair) > showing(&aces));
+    }
+}
@@ -2,7 +2,7 @@
 //! med 52 kort.
 mod provider;
 
-use core::cmp::Ordering;
+use core::{cmp::Ordering, fmt};
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -43,8 +43,9 @@ impl Ord for Rank {
     }
 }
 
-/// Farge, fransk standard
-#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+/// Farge, fransk standard. Sorteres kløver, ruter, hjerter, spar, slik
+/// fargene rangeres når de avgjør, f.eks. hvem som har bring-in i stud.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Debug)]
 pub enum Suit {
     Clubs,
     Diamonds,
@@ -59,28 +60,32 @@ pub struct Card {
     pub(crate) rank: Rank,
 }
 
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suit_char = match self.suit {
+            Suit::Clubs => 'k',
+            Suit::Diamonds => 'r',
+            Suit::Hearts => 'h',
+            Suit::Spades => 's',
+        };
+
+        match self.rank {
+            Rank::Numeral(10) => write!(f, "t{suit_char}"),
+            Rank::Numeral(n) => write!(f, "{n}{suit_char}"),
+            Rank::Jack => write!(f, "j{suit_char}"),
+            Rank::Queen => write!(f, "q{suit_char}"),
+            Rank::King => write!(f, "k{suit_char}"),
+            Rank::Ace => write!(f, "a{suit_char}"),
+        }
+    }
+}
+
 impl Serialize for Card {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let rank_str = match self.rank {
-            Rank::Numeral(10) => "t".to_string(),
-            Rank::Numeral(n) => n.to_string(),
-            Rank::Jack => "j".to_string(),
-            Rank::Queen => "q".to_string(),
-            Rank::King => "k".to_string(),
-            Rank::Ace => "a".to_string(),
-        };
-
-        let suit_char = match self.suit {
-            Suit::Clubs => "k",
-            Suit::Diamonds => "r",
-            Suit::Hearts => "h",
-            Suit::Spades => "s",
-        };
-
-        serializer.serialize_str(&format!("{}{}", rank_str, suit_char))
+        serializer.collect_str(self)
     }
 }
 
@@ -158,6 +163,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_suits_are_ordered_clubs_to_spades() {
+        let mut suits = vec![Suit::Spades, Suit::Clubs, Suit::Hearts, Suit::Diamonds];
+        suits.sort();
+        assert_eq!(
+            suits,
+            vec![Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades]
+        );
+    }
+
     #[test]
     fn test_serialize_card() {
         let card = Card {
@@ -1,8 +1,10 @@
-use core::hash::Hash;
+use core::{fmt, hash::Hash};
+use std::collections::HashSet;
 
 use rand::prelude::*;
 use rand_chacha::ChaCha20Rng;
 use rand_seeder::Seeder;
+use serde::Deserialize;
 
 use super::{Card, Deck, Rank, Suit};
 
@@ -38,8 +40,104 @@ impl DeckProvider {
         deck_copy.shuffle(&mut rng);
         deck_copy
     }
+
+    /// Returnerer en kortstokk som starter med `order`. Resten av kortene
+    /// stokkes med `seed`, så en fullstendig rekkefølge gir akkurat den.
+    pub fn get_with_order<H: Hash>(&self, order: &[Card], seed: H) -> Result<Deck, OrderError> {
+        let mut seen = HashSet::new();
+        for (position, card) in order.iter().enumerate() {
+            if !self.sorted_deck.contains(card) {
+                return Err(OrderError::UnknownCard {
+                    position,
+                    card: card.clone(),
+                });
+            }
+            if !seen.insert(card) {
+                return Err(OrderError::RepeatedCard {
+                    position,
+                    card: card.clone(),
+                });
+            }
+        }
+
+        let rest = self
+            .get_with_seed(seed)
+            .into_iter()
+            .filter(|card| !seen.contains(card));
+
+        Ok(order.iter().cloned().chain(rest).collect())
+    }
+
+    /// Kortene en forhåndsdefinert rekkefølge starter med
+    pub fn preset(&self, preset: Preset) -> Vec<Card> {
+        let card = |rank, suit| Card { rank, suit };
+
+        match preset {
+            Preset::Sorted => self.sorted_deck.clone(),
+            Preset::RoyalFlush => [Rank::Ace, Rank::King, Rank::Queen, Rank::Jack]
+                .into_iter()
+                .chain([Rank::Numeral(10)])
+                .map(|rank| card(rank, Suit::Spades))
+                .collect(),
+            Preset::FourOfAKind => [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades]
+                .into_iter()
+                .map(|suit| card(Rank::Ace, suit))
+                .chain([card(Rank::King, Suit::Hearts)])
+                .collect(),
+            Preset::FullHouse => vec![
+                card(Rank::King, Suit::Clubs),
+                card(Rank::King, Suit::Diamonds),
+                card(Rank::King, Suit::Hearts),
+                card(Rank::Numeral(7), Suit::Clubs),
+                card(Rank::Numeral(7), Suit::Spades),
+            ],
+        }
+    }
+}
+
+/// Forhåndsdefinerte rekkefølger for testing og undervisning. Alle unntatt
+/// `Sorted` gir bare den første hånden, resten stokkes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// Alle kortene sortert på valør og så farge
+    Sorted,
+    RoyalFlush,
+    FourOfAKind,
+    FullHouse,
 }
 
+/// Feil i en oppgitt rekkefølge
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OrderError {
+    /// Kortet finnes ikke i en vanlig kortstokk
+    UnknownCard { position: usize, card: Card },
+    /// Kortet er allerede brukt tidligere i rekkefølgen
+    RepeatedCard { position: usize, card: Card },
+}
+
+impl OrderError {
+    pub fn position(&self) -> usize {
+        match self {
+            OrderError::UnknownCard { position, .. }
+            | OrderError::RepeatedCard { position, .. } => *position,
+        }
+    }
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::UnknownCard { card, .. } => write!(f, "card {card} is not in the deck"),
+            OrderError::RepeatedCard { card, .. } => {
+                write!(f, "card {card} appears more than once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -80,6 +178,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_partial_order_starts_deck_and_rest_is_shuffled() {
+        let deck_provider = DeckProvider::default();
+        let order = deck_provider.preset(Preset::RoyalFlush);
+        let deck = deck_provider.get_with_order(&order, "seed").unwrap();
+
+        assert_eq!(deck[..5], order[..]);
+        assert_eq!(deck.iter().collect::<HashSet<_>>().len(), DECK_SIZE);
+        assert_eq!(deck, deck_provider.get_with_order(&order, "seed").unwrap());
+    }
+
+    #[test]
+    fn test_full_order_is_kept() {
+        let deck_provider = DeckProvider::default();
+        let order = deck_provider.get_with_seed(7);
+
+        assert_eq!(deck_provider.get_with_order(&order, 0).unwrap(), order);
+    }
+
+    #[test]
+    fn test_order_rejects_repeated_and_unknown_cards() {
+        let deck_provider = DeckProvider::default();
+        let ace = Card {
+            rank: Rank::Ace,
+            suit: Suit::Spades,
+        };
+        let eleven = Card {
+            rank: Rank::Numeral(11),
+            suit: Suit::Spades,
+        };
+
+        assert_eq!(
+            deck_provider.get_with_order(&[ace.clone(), ace.clone()], 0),
+            Err(OrderError::RepeatedCard {
+                position: 1,
+                card: ace
+            })
+        );
+        assert_eq!(
+            deck_provider
+                .get_with_order(&[eleven], 0)
+                .unwrap_err()
+                .position(),
+            0
+        );
+    }
+
     #[test]
     fn test_deck_provider_randomizations_are_stable() {
         let deck_provider = DeckProvider::default();
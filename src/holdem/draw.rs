@@ -0,0 +1,257 @@
+//! Femkorts draw. Alle får fem kort, og så bytter hver spiller, i tur og
+//! orden, inntil tre kort med nye fra toppen av kortstokken. Spillet er
+//! bestemt av kortstokken og kastene, så det kan spilles av igjen.
+use core::fmt;
+
+use super::{
+    deal::{DealError, MIN_PLAYERS},
+    deck::Card,
+    hand::Hand,
+    showdown::places,
+};
+
+/// Fem kort hver og tre byttet gir 48 kort med seks spillere
+pub const DRAW_MAX_PLAYERS: usize = 6;
+pub const MAX_DISCARDS: usize = 3;
+
+/// Et spill femkorts draw
+#[derive(Clone, Debug)]
+pub struct Draw {
+    pub seats: Vec<DrawSeat>,
+    /// Neste kort i kortstokken
+    position: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct DrawSeat {
+    pub cards: [Card; 5],
+    /// Kortene spilleren kastet, `None` før spilleren har byttet
+    pub discarded: Option<Vec<Card>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DrawError {
+    /// Det er en annen plass sin tur, eller alle har byttet
+    NotYourTurn {
+        next: Option<usize>,
+    },
+    TooManyDiscards {
+        got: usize,
+    },
+    /// Posisjonen er ikke mellom 0 og 4, eller er oppgitt før
+    InvalidPosition {
+        index: usize,
+        position: usize,
+    },
+}
+
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawError::NotYourTurn { next: Some(next) } => {
+                write!(f, "it is seat {}'s turn to draw", next + 1)
+            }
+            DrawError::NotYourTurn { next: None } => write!(f, "every seat has drawn"),
+            DrawError::TooManyDiscards { got } => {
+                write!(
+                    f,
+                    "at most {MAX_DISCARDS} cards can be discarded, got {got}"
+                )
+            }
+            DrawError::InvalidPosition { position, .. } => write!(
+                f,
+                "position {position} must be between 0 and 4 and only given once"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DrawError {}
+
+impl Draw {
+    /// Deler ut fem kort til hver spiller, ett om gangen
+    pub fn deal(deck: &[Card], players: usize) -> Result<Self, DealError> {
+        if !(MIN_PLAYERS..=DRAW_MAX_PLAYERS).contains(&players) {
+            return Err(DealError::PlayersOutOfRange {
+                max: DRAW_MAX_PLAYERS,
+                got: players,
+            });
+        }
+        let needed = (5 + MAX_DISCARDS) * players;
+        if deck.len() < needed {
+            return Err(DealError::NotEnoughCards {
+                needed,
+                got: deck.len(),
+            });
+        }
+
+        let seats = (0..players)
+            .map(|seat| DrawSeat {
+                cards: core::array::from_fn(|round| deck[round * players + seat].clone()),
+                discarded: None,
+            })
+            .collect();
+
+        Ok(Self {
+            seats,
+            position: 5 * players,
+        })
+    }
+
+    /// Spiller av kastene på nytt, i rekkefølgen plassene byttet
+    pub fn replay(
+        deck: &[Card],
+        players: usize,
+        discards: &[Vec<usize>],
+    ) -> Result<Self, DrawReplayError> {
+        let mut draw = Self::deal(deck, players).map_err(DrawReplayError::Deal)?;
+        for (seat, positions) in discards.iter().enumerate() {
+            draw.discard(deck, seat, positions)
+                .map_err(DrawReplayError::Draw)?;
+        }
+        Ok(draw)
+    }
+
+    /// Plassen som skal bytte nå, `None` når alle har byttet
+    pub fn next_seat(&self) -> Option<usize> {
+        self.seats.iter().position(|seat| seat.discarded.is_none())
+    }
+
+    /// Bytter kortene på de oppgitte posisjonene i hånden med nye fra
+    /// kortstokken. De nye kortene havner på samme plass i hånden.
+    pub fn discard(
+        &mut self,
+        deck: &[Card],
+        seat: usize,
+        positions: &[usize],
+    ) -> Result<(), DrawError> {
+        let next = self.next_seat();
+        if next != Some(seat) {
+            return Err(DrawError::NotYourTurn { next });
+        }
+        if positions.len() > MAX_DISCARDS {
+            return Err(DrawError::TooManyDiscards {
+                got: positions.len(),
+            });
+        }
+        for (index, &position) in positions.iter().enumerate() {
+            if position >= 5 || positions[..index].contains(&position) {
+                return Err(DrawError::InvalidPosition { index, position });
+            }
+        }
+
+        let seat = &mut self.seats[seat];
+        let mut discarded = vec![];
+        for &position in positions {
+            let card = deck[self.position].clone();
+            self.position += 1;
+            discarded.push(core::mem::replace(&mut seat.cards[position], card));
+        }
+        seat.discarded = Some(discarded);
+
+        Ok(())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_seat().is_none()
+    }
+
+    pub fn hands(&self) -> Vec<Hand> {
+        self.seats
+            .iter()
+            .map(|seat| Hand::from_distinct(seat.cards.clone()))
+            .collect()
+    }
+
+    /// Plasseringen til hver plass, se `places`
+    pub fn places(&self) -> Vec<usize> {
+        places(&self.hands())
+    }
+}
+
+/// Et lagret spill som ikke lar seg spille av igjen
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DrawReplayError {
+    Deal(DealError),
+    Draw(DrawError),
+}
+
+impl fmt::Display for DrawReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawReplayError::Deal(error) => write!(f, "cannot replay deal: {error}"),
+            DrawReplayError::Draw(error) => write!(f, "cannot replay discard: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for DrawReplayError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::deck::DeckProvider, *};
+
+    #[test]
+    fn deals_five_cards_round_robin() {
+        let deck = DeckProvider::default().get_with_seed(3);
+        let draw = Draw::deal(&deck, 3).unwrap();
+
+        assert_eq!(draw.seats[1].cards[0], deck[1]);
+        assert_eq!(draw.seats[1].cards[4], deck[13]);
+        assert_eq!(draw.next_seat(), Some(0));
+    }
+
+    #[test]
+    fn discards_are_replaced_in_turn_from_the_deck() {
+        let deck = DeckProvider::default().get_with_seed(3);
+        let mut draw = Draw::deal(&deck, 2).unwrap();
+
+        assert_eq!(
+            draw.discard(&deck, 1, &[0]),
+            Err(DrawError::NotYourTurn { next: Some(0) })
+        );
+
+        draw.discard(&deck, 0, &[1, 3]).unwrap();
+        draw.discard(&deck, 1, &[]).unwrap();
+
+        assert_eq!(draw.seats[0].cards[1], deck[10]);
+        assert_eq!(draw.seats[0].cards[3], deck[11]);
+        assert_eq!(
+            draw.seats[0].discarded,
+            Some(vec![deck[2].clone(), deck[6].clone()])
+        );
+        assert!(draw.is_finished());
+        assert_eq!(draw.places().len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_discards() {
+        let deck = DeckProvider::default().get_with_seed(3);
+        let mut draw = Draw::deal(&deck, 2).unwrap();
+
+        assert_eq!(
+            draw.discard(&deck, 0, &[0, 1, 2, 3]),
+            Err(DrawError::TooManyDiscards { got: 4 })
+        );
+        assert_eq!(
+            draw.discard(&deck, 0, &[2, 2]),
+            Err(DrawError::InvalidPosition {
+                index: 1,
+                position: 2
+            })
+        );
+        assert!(draw.discard(&deck, 0, &[5]).is_err());
+    }
+
+    #[test]
+    fn replay_gives_the_same_game() {
+        let deck = DeckProvider::default().get_with_seed(9);
+        let discards = vec![vec![0, 4], vec![2]];
+        let a = Draw::replay(&deck, 2, &discards).unwrap();
+        let b = Draw::replay(&deck, 2, &discards).unwrap();
+
+        assert_eq!(a.seats[0].cards, b.seats[0].cards);
+        assert_eq!(a.places(), b.places());
+        assert!(Draw::replay(&deck, 7, &[]).is_err());
+    }
+}
@@ -0,0 +1,883 @@
+//! Texas Hold'em med innsatser, for bordene som spilles over WebSocket.
+//! Modulen har ingen I/O: bordet tar imot handlinger og returnerer
+//! resultatet når en hånd er ferdig. Plassene er indekser fra 0, mens
+//! visningene nummererer dem fra 1 som resten av API-et.
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    deck::{Card, Deck},
+    hand::Hand,
+};
+
+pub const MAX_SEATS: usize = 10;
+
+/// Det en spiller kan gjøre når det er dens tur
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    Fold,
+    Check,
+    Call,
+    /// Øker innsatsen i runden til `to`. Uten innsats er det en bet.
+    Raise {
+        to: u64,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Street {
+    Preflop,
+    Flop,
+    Turn,
+    River,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GameError {
+    TableFull,
+    HandInProgress,
+    NotEnoughPlayers,
+    NoHand,
+    NotInHand,
+    /// `to_act` er plassen som skal handle
+    NotYourTurn {
+        to_act: Option<usize>,
+    },
+    InvalidAction {
+        detail: &'static str,
+    },
+    /// Minste lovlige `to`
+    RaiseTooSmall {
+        min: u64,
+    },
+}
+
+impl GameError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GameError::TableFull => "table_full",
+            GameError::HandInProgress => "hand_in_progress",
+            GameError::NotEnoughPlayers => "not_enough_players",
+            GameError::NoHand => "no_hand",
+            GameError::NotInHand => "not_in_hand",
+            GameError::NotYourTurn { .. } => "not_your_turn",
+            GameError::InvalidAction { .. } => "invalid_action",
+            GameError::RaiseTooSmall { .. } => "raise_too_small",
+        }
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::TableFull => write!(f, "all {MAX_SEATS} seats are taken"),
+            GameError::HandInProgress => write!(f, "a hand is already being played"),
+            GameError::NotEnoughPlayers => write!(f, "a hand needs two players with chips"),
+            GameError::NoHand => write!(f, "no hand is being played"),
+            GameError::NotInHand => write!(f, "the seat is not in the hand"),
+            GameError::NotYourTurn { to_act: Some(seat) } => {
+                write!(f, "it is seat {}'s turn", seat + 1)
+            }
+            GameError::NotYourTurn { to_act: None } => write!(f, "no one can act now"),
+            GameError::InvalidAction { detail } => f.write_str(detail),
+            GameError::RaiseTooSmall { min } => write!(f, "a raise must be to at least {min}"),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+#[derive(Clone, Debug)]
+pub struct Player {
+    pub name: String,
+    pub stack: u64,
+}
+
+/// En plass i hånden som spilles
+#[derive(Clone, Debug)]
+struct InHand {
+    hole_cards: [Card; 2],
+    folded: bool,
+    /// Innsatsen i denne runden
+    bet: u64,
+    /// Alt plassen har satt inn i hånden
+    contributed: u64,
+    /// Om plassen har handlet siden siste økning
+    acted: bool,
+}
+
+#[derive(Clone, Debug)]
+struct HandState {
+    deck: Deck,
+    next_card: usize,
+    board: Vec<Card>,
+    street: Street,
+    seats: Vec<Option<InHand>>,
+    to_act: Option<usize>,
+    current_bet: u64,
+    min_raise: u64,
+}
+
+impl HandState {
+    fn deal_card(&mut self) -> Card {
+        let card = self.deck[self.next_card].clone();
+        self.next_card += 1;
+        card
+    }
+
+    fn live(&self) -> impl Iterator<Item = (usize, &InHand)> {
+        self.seats
+            .iter()
+            .enumerate()
+            .filter_map(|(seat, state)| state.as_ref().map(|state| (seat, state)))
+            .filter(|(_, state)| !state.folded)
+    }
+
+    fn pot(&self) -> u64 {
+        self.seats
+            .iter()
+            .flatten()
+            .map(|seat| seat.contributed)
+            .sum()
+    }
+}
+
+/// En ferdig hånd
+#[derive(Clone, Debug, Serialize)]
+pub struct HandResult {
+    pub board: Vec<Card>,
+    /// Hendene som ble vist, tom når alle andre kastet seg
+    pub shown: Vec<ShownHand>,
+    pub winnings: Vec<Winning>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ShownHand {
+    /// Fra 1
+    pub seat: usize,
+    pub hole_cards: [Card; 2],
+    pub hand: Hand,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Winning {
+    /// Fra 1
+    pub seat: usize,
+    pub amount: u64,
+}
+
+/// Det alle ved bordet kan se
+#[derive(Clone, Debug, Serialize)]
+pub struct TableView {
+    pub small_blind: u64,
+    pub big_blind: u64,
+    /// Fra 1
+    pub button: Option<usize>,
+    pub seats: Vec<SeatView>,
+    pub hand: Option<HandView>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SeatView {
+    /// Fra 1
+    pub seat: usize,
+    pub name: String,
+    pub stack: u64,
+    pub in_hand: bool,
+    pub folded: bool,
+    pub bet: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HandView {
+    pub street: Street,
+    pub board: Vec<Card>,
+    pub pot: u64,
+    pub current_bet: u64,
+    /// Fra 1
+    pub to_act: Option<usize>,
+    /// Minste lovlige `to` for `raise`
+    pub min_raise_to: u64,
+}
+
+/// Et bord med opptil `MAX_SEATS` plasser
+#[derive(Clone, Debug)]
+pub struct Game {
+    pub small_blind: u64,
+    pub big_blind: u64,
+    seats: Vec<Option<Player>>,
+    button: Option<usize>,
+    hand: Option<HandState>,
+}
+
+impl Game {
+    pub fn new(small_blind: u64, big_blind: u64) -> Self {
+        Self {
+            small_blind,
+            big_blind,
+            seats: vec![None; MAX_SEATS],
+            button: None,
+            hand: None,
+        }
+    }
+
+    /// Setter en spiller på første ledige plass. En plass som er forlatt
+    /// midt i en hånd er ikke ledig før hånden er ferdig.
+    pub fn sit(&mut self, name: String, stack: u64) -> Result<usize, GameError> {
+        let seat = (0..MAX_SEATS)
+            .find(|&seat| {
+                self.seats[seat].is_none()
+                    && self
+                        .hand
+                        .as_ref()
+                        .is_none_or(|hand| hand.seats[seat].is_none())
+            })
+            .ok_or(GameError::TableFull)?;
+        self.seats[seat] = Some(Player { name, stack });
+        Ok(seat)
+    }
+
+    /// Spilleren kaster seg først dersom den er med i hånden
+    pub fn leave(&mut self, seat: usize) -> Option<HandResult> {
+        let result = match &self.hand {
+            Some(hand) if hand.to_act == Some(seat) => self.act(seat, Action::Fold).ok().flatten(),
+            Some(hand) if hand.seats[seat].as_ref().is_some_and(|state| !state.folded) => {
+                self.in_hand(seat).folded = true;
+                let hand = self.hand.as_ref().expect("hand is being played");
+                (hand.live().count() == 1).then(|| self.end_hand())
+            }
+            _ => None,
+        };
+        self.seats[seat] = None;
+        result
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.hand.is_some()
+    }
+
+    pub fn hole_cards(&self, seat: usize) -> Option<&[Card; 2]> {
+        let hand = self.hand.as_ref()?;
+        hand.seats[seat].as_ref().map(|state| &state.hole_cards)
+    }
+
+    /// Plassene som er med i hånden som spilles
+    pub fn seats_in_hand(&self) -> Vec<usize> {
+        self.hand.as_ref().map_or(vec![], |hand| {
+            hand.seats
+                .iter()
+                .enumerate()
+                .filter(|(_, state)| state.is_some())
+                .map(|(seat, _)| seat)
+                .collect()
+        })
+    }
+
+    /// Om en ny hånd kan startes, slik at kortstokken bare lages når den
+    /// trengs
+    pub fn can_start(&self) -> Result<(), GameError> {
+        if self.hand.is_some() {
+            return Err(GameError::HandInProgress);
+        }
+        if self.playing().len() < 2 {
+            return Err(GameError::NotEnoughPlayers);
+        }
+        Ok(())
+    }
+
+    /// Starter en ny hånd med kortstokken. Knappen flyttes til neste plass
+    /// med sjetonger, blindene settes inn og hver spiller får to kort, ett om
+    /// gangen fra plassen etter knappen.
+    pub fn start_hand(&mut self, deck: Deck) -> Result<Option<HandResult>, GameError> {
+        self.can_start()?;
+        let playing = self.playing();
+
+        let next_after = |seat: usize| {
+            playing
+                .iter()
+                .copied()
+                .find(|&other| other > seat)
+                .unwrap_or(playing[0])
+        };
+        let button = self.button.map_or(playing[0], next_after);
+        // To spillere: knappen har small blind
+        let small_blind = match playing.len() {
+            2 => button,
+            _ => next_after(button),
+        };
+        let big_blind = next_after(small_blind);
+
+        let mut hand = HandState {
+            deck,
+            next_card: 0,
+            board: vec![],
+            street: Street::Preflop,
+            seats: vec![None; MAX_SEATS],
+            to_act: None,
+            current_bet: self.big_blind,
+            min_raise: self.big_blind,
+        };
+
+        let mut order = vec![next_after(button)];
+        while order.len() < playing.len() {
+            order.push(next_after(order[order.len() - 1]));
+        }
+        let first: Vec<Card> = order.iter().map(|_| hand.deal_card()).collect();
+        let second: Vec<Card> = order.iter().map(|_| hand.deal_card()).collect();
+        for (index, &seat) in order.iter().enumerate() {
+            hand.seats[seat] = Some(InHand {
+                hole_cards: [first[index].clone(), second[index].clone()],
+                folded: false,
+                bet: 0,
+                contributed: 0,
+                acted: false,
+            });
+        }
+
+        self.button = Some(button);
+        self.hand = Some(hand);
+        self.put_in(small_blind, self.small_blind);
+        self.put_in(big_blind, self.big_blind);
+
+        let hand = self.hand.as_mut().expect("hand was just started");
+        // En big blind som er all-in for mindre setter innsatsen
+        hand.current_bet = hand
+            .seats
+            .iter()
+            .flatten()
+            .map(|state| state.bet)
+            .max()
+            .unwrap_or(0);
+        hand.to_act = Some(big_blind);
+        // Når alle er all-in etter blindene spilles resten ut uten handlinger
+        Ok(self.advance(big_blind).then(|| self.end_hand()))
+    }
+
+    pub fn act(&mut self, seat: usize, action: Action) -> Result<Option<HandResult>, GameError> {
+        let hand = self.hand.as_ref().ok_or(GameError::NoHand)?;
+        if hand.seats[seat].is_none() {
+            return Err(GameError::NotInHand);
+        }
+        if hand.to_act != Some(seat) {
+            return Err(GameError::NotYourTurn {
+                to_act: hand.to_act,
+            });
+        }
+
+        let state = hand.seats[seat].as_ref().expect("seat is in the hand");
+        let stack = self.stack(seat);
+        let current_bet = hand.current_bet;
+        let min_raise = hand.min_raise;
+
+        match action {
+            Action::Fold => {}
+            Action::Check if state.bet < current_bet => {
+                return Err(GameError::InvalidAction {
+                    detail: "cannot check when facing a bet",
+                });
+            }
+            Action::Check => {}
+            Action::Call if state.bet >= current_bet => {
+                return Err(GameError::InvalidAction {
+                    detail: "nothing to call, check instead",
+                });
+            }
+            Action::Call => {}
+            Action::Raise { to } if to <= current_bet => {
+                return Err(GameError::RaiseTooSmall {
+                    min: current_bet + min_raise,
+                });
+            }
+            Action::Raise { to } if to - state.bet > stack => {
+                return Err(GameError::InvalidAction {
+                    detail: "the raise is larger than the stack",
+                });
+            }
+            // Mindre økninger er bare lov når spilleren går all-in
+            Action::Raise { to } if to < current_bet + min_raise && to - state.bet < stack => {
+                return Err(GameError::RaiseTooSmall {
+                    min: current_bet + min_raise,
+                });
+            }
+            // En for liten all-in åpner ikke budrunden igjen for de som har handlet
+            Action::Raise { .. } if state.acted => {
+                return Err(GameError::InvalidAction {
+                    detail: "the betting was not reopened, call or fold",
+                });
+            }
+            Action::Raise { .. } => {}
+        }
+
+        let bet = state.bet;
+        match action {
+            Action::Fold => self.in_hand(seat).folded = true,
+            Action::Check => {}
+            Action::Call => self.put_in(seat, current_bet - bet),
+            Action::Raise { to } => {
+                self.put_in(seat, to - bet);
+                let hand = self.hand.as_mut().expect("hand is being played");
+                if to - current_bet >= min_raise {
+                    hand.min_raise = to - current_bet;
+                    for state in hand.seats.iter_mut().flatten() {
+                        state.acted = false;
+                    }
+                }
+                hand.current_bet = to;
+            }
+        }
+        self.in_hand(seat).acted = true;
+
+        Ok(self.advance(seat).then(|| self.end_hand()))
+    }
+
+    pub fn view(&self) -> TableView {
+        let seats = self
+            .seats
+            .iter()
+            .enumerate()
+            .filter_map(|(seat, player)| {
+                let player = player.as_ref()?;
+                let state = self
+                    .hand
+                    .as_ref()
+                    .and_then(|hand| hand.seats[seat].as_ref());
+                Some(SeatView {
+                    seat: seat + 1,
+                    name: player.name.clone(),
+                    stack: player.stack,
+                    in_hand: state.is_some(),
+                    folded: state.is_some_and(|state| state.folded),
+                    bet: state.map_or(0, |state| state.bet),
+                })
+            })
+            .collect();
+
+        TableView {
+            small_blind: self.small_blind,
+            big_blind: self.big_blind,
+            button: self.button.map(|button| button + 1),
+            seats,
+            hand: self.hand.as_ref().map(|hand| HandView {
+                street: hand.street,
+                board: hand.board.clone(),
+                pot: hand.pot(),
+                current_bet: hand.current_bet,
+                to_act: hand.to_act.map(|seat| seat + 1),
+                min_raise_to: hand.current_bet + hand.min_raise,
+            }),
+        }
+    }
+
+    /// Plassene med sjetonger
+    fn playing(&self) -> Vec<usize> {
+        self.seats
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.as_ref().is_some_and(|player| player.stack > 0))
+            .map(|(seat, _)| seat)
+            .collect()
+    }
+
+    fn stack(&self, seat: usize) -> u64 {
+        self.seats[seat].as_ref().map_or(0, |player| player.stack)
+    }
+
+    fn in_hand(&mut self, seat: usize) -> &mut InHand {
+        self.hand
+            .as_mut()
+            .and_then(|hand| hand.seats[seat].as_mut())
+            .expect("seat is in the hand")
+    }
+
+    /// Flytter opptil `amount` fra stacken til innsatsen
+    fn put_in(&mut self, seat: usize, amount: u64) {
+        let amount = amount.min(self.stack(seat));
+        if let Some(player) = &mut self.seats[seat] {
+            player.stack -= amount;
+        }
+        let state = self.in_hand(seat);
+        state.bet += amount;
+        state.contributed += amount;
+    }
+
+    /// Finner neste plass som skal handle etter `seat`, og går videre til
+    /// neste runde når runden er ferdig. `true` når hånden er over.
+    fn advance(&mut self, seat: usize) -> bool {
+        let stacks: Vec<u64> = (0..MAX_SEATS).map(|seat| self.stack(seat)).collect();
+        let hand = self.hand.as_mut().expect("hand is being played");
+
+        if hand.live().count() == 1 {
+            return true;
+        }
+
+        let needs_action = |hand: &HandState, seat: usize| {
+            hand.seats[seat].as_ref().is_some_and(|state| {
+                !state.folded && stacks[seat] > 0 && (!state.acted || state.bet < hand.current_bet)
+            })
+        };
+        hand.to_act = (1..=MAX_SEATS)
+            .map(|step| (seat + step) % MAX_SEATS)
+            .find(|&other| needs_action(hand, other));
+        if hand.to_act.is_some() {
+            return false;
+        }
+
+        // Runden er ferdig. Del ut neste gate, eller resten av bordet når
+        // færre enn to spillere kan handle.
+        loop {
+            for state in hand.seats.iter_mut().flatten() {
+                state.bet = 0;
+                state.acted = false;
+            }
+            hand.current_bet = 0;
+            hand.min_raise = self.big_blind;
+
+            let (street, cards) = match hand.street {
+                Street::Preflop => (Street::Flop, 3),
+                Street::Flop => (Street::Turn, 1),
+                Street::Turn => (Street::River, 1),
+                Street::River => return true,
+            };
+            hand.deal_card();
+            for _ in 0..cards {
+                let card = hand.deal_card();
+                hand.board.push(card);
+            }
+            hand.street = street;
+
+            let can_act = hand.live().filter(|(seat, _)| stacks[*seat] > 0).count();
+            if can_act >= 2 {
+                let button = self.button.unwrap_or(0);
+                hand.to_act = (1..=MAX_SEATS)
+                    .map(|step| (button + step) % MAX_SEATS)
+                    .find(|&other| needs_action(hand, other));
+                return false;
+            }
+        }
+    }
+
+    /// Avslutter hånden og fordeler potten, med sidepotter for spillere som
+    /// er all-in
+    fn end_hand(&mut self) -> HandResult {
+        let hand = self.hand.take().expect("hand is being played");
+
+        // Innsats ingen har matchet går tilbake
+        let mut contributions: Vec<u64> = hand
+            .seats
+            .iter()
+            .map(|state| state.as_ref().map_or(0, |state| state.contributed))
+            .collect();
+        let mut sorted = contributions.clone();
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+        let mut winnings = vec![0; MAX_SEATS];
+        if sorted[0] > sorted[1] {
+            let seat = contributions
+                .iter()
+                .position(|&amount| amount == sorted[0])
+                .expect("the largest contribution exists");
+            winnings[seat] += sorted[0] - sorted[1];
+            contributions[seat] = sorted[1];
+        }
+
+        let live: Vec<usize> = hand.live().map(|(seat, _)| seat).collect();
+        let mut shown = vec![];
+        let mut hands: Vec<Option<Hand>> = vec![None; MAX_SEATS];
+        if live.len() > 1 {
+            for &seat in &live {
+                let state = hand.seats[seat].as_ref().expect("live seat is in hand");
+                let cards: Vec<Card> = state
+                    .hole_cards
+                    .iter()
+                    .chain(&hand.board)
+                    .cloned()
+                    .collect();
+                let best = Hand::best_of(&cards).expect("seven cards make a hand");
+                shown.push(ShownHand {
+                    seat: seat + 1,
+                    hole_cards: state.hole_cards.clone(),
+                    hand: best.clone(),
+                });
+                hands[seat] = Some(best);
+            }
+        }
+
+        // Plassene etter knappen får sjetonger som ikke kan deles likt
+        let button = self.button.unwrap_or(0);
+        let order: Vec<usize> = (1..=MAX_SEATS)
+            .map(|step| (button + step) % MAX_SEATS)
+            .collect();
+
+        let mut levels: Vec<u64> = contributions.clone();
+        levels.sort_unstable();
+        levels.dedup();
+        let mut previous = 0;
+        let mut carried = 0;
+        for level in levels.into_iter().filter(|&level| level > 0) {
+            let pot: u64 = contributions
+                .iter()
+                .map(|&amount| amount.min(level) - amount.min(previous))
+                .sum::<u64>()
+                + carried;
+            previous = level;
+
+            let eligible: Vec<usize> = live
+                .iter()
+                .copied()
+                .filter(|&seat| contributions[seat] >= level)
+                .collect();
+            if eligible.is_empty() {
+                carried = pot;
+                continue;
+            }
+            carried = 0;
+
+            let best = eligible
+                .iter()
+                .filter_map(|&seat| hands[seat].as_ref())
+                .max();
+            let winners: Vec<usize> = order
+                .iter()
+                .copied()
+                .filter(|seat| eligible.contains(seat))
+                .filter(|&seat| best.is_none() || hands[seat].as_ref() == best)
+                .collect();
+
+            let share = pot / winners.len() as u64;
+            let rest = pot % winners.len() as u64;
+            for (index, &seat) in winners.iter().enumerate() {
+                winnings[seat] += share + u64::from((index as u64) < rest);
+            }
+        }
+        if carried > 0 {
+            winnings[live[0]] += carried;
+        }
+
+        let result = HandResult {
+            board: hand.board.clone(),
+            shown,
+            winnings: winnings
+                .into_iter()
+                .enumerate()
+                .filter(|(_, amount)| *amount > 0)
+                .map(|(seat, amount)| Winning {
+                    seat: seat + 1,
+                    amount,
+                })
+                .collect(),
+        };
+        self.pay_out(&result);
+        result
+    }
+
+    fn pay_out(&mut self, result: &HandResult) {
+        for winning in &result.winnings {
+            if let Some(player) = &mut self.seats[winning.seat - 1] {
+                player.stack += winning.amount;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::deck::DeckProvider, *};
+
+    fn table(stacks: &[u64]) -> Game {
+        let mut game = Game::new(1, 2);
+        for (index, &stack) in stacks.iter().enumerate() {
+            game.sit(format!("spiller {index}"), stack).unwrap();
+        }
+        game
+    }
+
+    fn total(game: &Game) -> u64 {
+        game.view().seats.iter().map(|seat| seat.stack).sum::<u64>()
+            + game.view().hand.map_or(0, |hand| hand.pot)
+    }
+
+    #[test]
+    fn heads_up_button_posts_small_blind_and_acts_first() {
+        let mut game = table(&[100, 100]);
+        game.start_hand(DeckProvider::default().get_with_seed(1))
+            .unwrap();
+        let view = game.view();
+
+        assert_eq!(view.button, Some(1));
+        assert_eq!(view.seats[0].bet, 1);
+        assert_eq!(view.seats[1].bet, 2);
+        assert_eq!(view.hand.unwrap().to_act, Some(1));
+        assert_eq!(
+            game.act(1, Action::Check).unwrap_err(),
+            GameError::NotYourTurn { to_act: Some(0) }
+        );
+    }
+
+    #[test]
+    fn fold_gives_the_pot_to_the_last_player() {
+        let mut game = table(&[100, 100, 100]);
+        game.start_hand(DeckProvider::default().get_with_seed(1))
+            .unwrap();
+
+        // Knappen er plass 0, så plass 0 handler først med tre spillere
+        assert_eq!(game.view().hand.unwrap().to_act, Some(1));
+        assert!(game.act(0, Action::Fold).unwrap().is_none());
+        let result = game.act(1, Action::Fold).unwrap().unwrap();
+
+        assert!(result.shown.is_empty());
+        assert_eq!(result.winnings.len(), 1);
+        assert_eq!(result.winnings[0].seat, 3);
+        assert_eq!(game.view().seats[2].stack, 101);
+        assert!(!game.is_playing());
+    }
+
+    #[test]
+    fn checked_down_hand_reaches_showdown() {
+        let mut game = table(&[100, 100]);
+        game.start_hand(DeckProvider::default().get_with_seed(5))
+            .unwrap();
+
+        game.act(0, Action::Call).unwrap();
+        game.act(1, Action::Check).unwrap();
+        assert_eq!(game.view().hand.unwrap().board.len(), 3);
+
+        let mut result = None;
+        for _ in 0..3 {
+            // Etter floppen handler plassen etter knappen først
+            game.act(1, Action::Check).unwrap();
+            result = game.act(0, Action::Check).unwrap();
+        }
+        let result = result.unwrap();
+
+        assert_eq!(result.board.len(), 5);
+        assert_eq!(result.shown.len(), 2);
+        assert_eq!(total(&game), 200);
+    }
+
+    #[test]
+    fn raises_must_be_at_least_the_last_raise() {
+        let mut game = table(&[100, 100]);
+        game.start_hand(DeckProvider::default().get_with_seed(1))
+            .unwrap();
+
+        assert_eq!(
+            game.act(0, Action::Raise { to: 3 }).unwrap_err(),
+            GameError::RaiseTooSmall { min: 4 }
+        );
+        game.act(0, Action::Raise { to: 10 }).unwrap();
+        assert_eq!(game.view().hand.unwrap().min_raise_to, 18);
+        assert_eq!(
+            game.act(1, Action::Check).unwrap_err(),
+            GameError::InvalidAction {
+                detail: "cannot check when facing a bet"
+            }
+        );
+    }
+
+    #[test]
+    fn short_all_in_does_not_reopen_betting() {
+        let mut game = table(&[100, 100, 15]);
+        game.start_hand(DeckProvider::default().get_with_seed(3))
+            .unwrap();
+
+        // Plass 2 går all-in for mindre enn en hel økning
+        game.act(0, Action::Raise { to: 10 }).unwrap();
+        game.act(1, Action::Call).unwrap();
+        game.act(2, Action::Raise { to: 15 }).unwrap();
+
+        let hand = game.view().hand.unwrap();
+        assert_eq!(hand.to_act, Some(1));
+        assert_eq!(hand.min_raise_to, 23);
+        assert_eq!(
+            game.act(0, Action::Raise { to: 30 }).unwrap_err(),
+            GameError::InvalidAction {
+                detail: "the betting was not reopened, call or fold"
+            }
+        );
+        game.act(0, Action::Call).unwrap();
+        game.act(1, Action::Call).unwrap();
+
+        let hand = game.view().hand.unwrap();
+        assert_eq!(hand.street, Street::Flop);
+        assert_eq!(hand.pot, 45);
+    }
+
+    #[test]
+    fn all_in_runs_out_the_board_with_side_pot() {
+        let mut game = table(&[20, 100, 100]);
+        game.start_hand(DeckProvider::default().get_with_seed(9))
+            .unwrap();
+
+        // Plass 0 er knappen og går all-in, de to andre går over
+        game.act(0, Action::Raise { to: 20 }).unwrap();
+        game.act(1, Action::Raise { to: 50 }).unwrap();
+        let result = game.act(2, Action::Raise { to: 100 }).unwrap();
+        assert!(result.is_none());
+        let result = game.act(1, Action::Call).unwrap().unwrap();
+
+        assert_eq!(result.board.len(), 5);
+        assert_eq!(result.shown.len(), 3);
+        let won: u64 = result.winnings.iter().map(|winning| winning.amount).sum();
+        assert_eq!(won, 220);
+        assert_eq!(total(&game), 220);
+    }
+
+    #[test]
+    fn uncalled_bet_is_returned() {
+        let mut game = table(&[100, 30]);
+        game.start_hand(DeckProvider::default().get_with_seed(2))
+            .unwrap();
+
+        game.act(0, Action::Raise { to: 100 }).unwrap();
+        let result = game.act(1, Action::Call).unwrap().unwrap();
+        let won: u64 = result.winnings.iter().map(|winning| winning.amount).sum();
+
+        assert_eq!(won, 130);
+        assert!(game.view().seats[0].stack >= 70);
+    }
+
+    #[test]
+    fn leaving_folds_the_hand() {
+        let mut game = table(&[100, 100]);
+        game.start_hand(DeckProvider::default().get_with_seed(1))
+            .unwrap();
+
+        let result = game.leave(1).unwrap();
+
+        assert_eq!(result.winnings[0].seat, 1);
+        assert_eq!(game.view().seats.len(), 1);
+        assert_eq!(
+            game.start_hand(vec![]).unwrap_err(),
+            GameError::NotEnoughPlayers
+        );
+    }
+
+    #[test]
+    fn seat_left_during_a_hand_is_free_when_the_hand_ends() {
+        let mut game = table(&[100, 100, 100]);
+        game.start_hand(DeckProvider::default().get_with_seed(1))
+            .unwrap();
+        let to_act = game.view().hand.unwrap().to_act.unwrap() - 1;
+        let leaving = (to_act + 1) % 3;
+
+        assert!(game.leave(leaving).is_none());
+        let seat = game.sit("ny".into(), 100).unwrap();
+
+        assert_eq!(seat, 3);
+        assert!(game.hole_cards(seat).is_none());
+        assert!(game.hole_cards(leaving).is_some());
+
+        let result = game.act(to_act, Action::Fold).unwrap();
+        assert!(result.is_some());
+        assert!(!game.is_playing());
+        assert_eq!(game.sit("neste".into(), 100).unwrap(), leaving);
+    }
+
+    #[test]
+    fn action_is_parsed_from_json() {
+        let action: Action = serde_json::from_str(r#"{"action":"raise","to":40}"#).unwrap();
+        assert_eq!(action, Action::Raise { to: 40 });
+    }
+}
@@ -1,15 +1,23 @@
 //! Representerer en poker hånd, dvs den beste kombinasjonen av fem kort
 //! spilleren sitter med.
 //! https://en.wikipedia.org/wiki/List_of_poker_hands
-use core::cmp::Ordering;
+use core::{cmp::Ordering, str::FromStr};
 use std::collections::{HashMap, HashSet};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use super::deck::{Card, Rank};
+use super::{
+    deck::{Card, Rank},
+    validation::{DuplicateCard, validate_hands},
+};
+use crate::Locale;
+
+/// Versjonen av reglene for å evaluere og sammenligne hender. Økes når en
+/// endring kan gi et annet resultat for de samme hendene.
+pub const EVALUATOR_VERSION: u32 = 1;
 
 /// Representerer en rangering av en pokerhånd
-#[derive(PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum RankingCategory {
     StraightFlush,
     FourOfAKind,
@@ -68,6 +76,21 @@ impl PartialOrd for RankingCategory {
 }
 
 impl RankingCategory {
+    /// Samme navn som ved serialisering, f.eks. "StraightFlush"
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::StraightFlush => "StraightFlush",
+            Self::FourOfAKind => "FourOfAKind",
+            Self::FullHouse => "FullHouse",
+            Self::Flush => "Flush",
+            Self::Straight => "Straight",
+            Self::ThreeOfAKind => "ThreeOfAKind",
+            Self::TwoPair => "TwoPair",
+            Self::OnePair => "OnePair",
+            Self::HighCard => "HighCard",
+        }
+    }
+
     fn rank_value(&self) -> u8 {
         match self {
             Self::StraightFlush => 9,
@@ -83,16 +106,65 @@ impl RankingCategory {
     }
 }
 
+impl FromStr for RankingCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::StraightFlush,
+            Self::FourOfAKind,
+            Self::FullHouse,
+            Self::Flush,
+            Self::Straight,
+            Self::ThreeOfAKind,
+            Self::TwoPair,
+            Self::OnePair,
+            Self::HighCard,
+        ]
+        .into_iter()
+        .find(|category| category.as_str() == s)
+        .ok_or_else(|| format!("Unknown ranking category {s:?}"))
+    }
+}
+
+/// Beskriver hva som avgjorde en sammenligning mellom to hender
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Tiebreak {
+    /// Hendene har ulik kategori
+    RankingCategory,
+    /// Firlingen, trellingen eller det (høyeste) paret
+    PrimaryRank,
+    /// Paret i hus, eller det laveste paret i to par
+    SecondaryRank,
+    /// Det n-te høyeste kortet i høyt kort, flush og straight
+    HighCard { position: usize },
+    /// Den n-te kickeren
+    Kicker { position: usize },
+}
+
 /// Representerer en poker hånd, dvs den beste kombinasjonen av fem kort
 /// spilleren sitter med.
-#[derive(Debug, Eq, Serialize)]
+#[derive(Clone, Debug, Eq, Serialize)]
 pub struct Hand {
     ranking_category: RankingCategory,
     cards: [Card; 5],
 }
 
-impl From<[Card; 5]> for Hand {
-    fn from(mut cards: [Card; 5]) -> Self {
+/// Samme kort to ganger er ingen gyldig hånd, og gir duplikatene
+impl TryFrom<[Card; 5]> for Hand {
+    type Error = Vec<DuplicateCard>;
+
+    fn try_from(cards: [Card; 5]) -> Result<Self, Self::Error> {
+        validate_hands([("", &cards[..])])?;
+        Ok(Hand::from_distinct(cards))
+    }
+}
+
+impl Hand {
+    /// Hånden av fem kort som allerede er kjent for å være forskjellige,
+    /// f.eks. fordi de er delt ut fra samme kortstokk
+    pub(crate) fn from_distinct(mut cards: [Card; 5]) -> Self {
         cards.sort_by_key(|card| card.rank);
 
         Hand {
@@ -100,9 +172,42 @@ impl From<[Card; 5]> for Hand {
             cards,
         }
     }
-}
 
-impl Hand {
+    pub fn ranking_category(&self) -> RankingCategory {
+        self.ranking_category
+    }
+
+    pub fn cards(&self) -> &[Card; 5] {
+        &self.cards
+    }
+
+    /// Den beste hånden av fem av kortene, f.eks. av sju kort i Texas
+    /// Hold'em. `None` med færre enn fem kort.
+    pub fn best_of(cards: &[Card]) -> Option<Hand> {
+        let n = cards.len();
+        if n < 5 {
+            return None;
+        }
+
+        let mut indices = [0, 1, 2, 3, 4];
+        let mut best: Option<Hand> = None;
+        loop {
+            let hand = Hand::from_distinct(indices.map(|i| cards[i].clone()));
+            if best.as_ref().is_none_or(|best| hand > *best) {
+                best = Some(hand);
+            }
+
+            // Neste kombinasjon i leksikografisk rekkefølge
+            let Some(i) = (0..5).rev().find(|&i| indices[i] < n - 5 + i) else {
+                return best;
+            };
+            indices[i] += 1;
+            for j in i + 1..5 {
+                indices[j] = indices[j - 1] + 1;
+            }
+        }
+    }
+
     fn find_rank_with_count(&self, count: usize) -> Rank {
         self.find_all_ranks_with_count(count)[0]
     }
@@ -141,65 +246,132 @@ impl Hand {
         kickers
     }
 
-    fn compare_n_of_a_kind(&self, other: &Self, group_size: usize) -> Ordering {
-        let self_n = self.find_rank_with_count(group_size);
-        let other_n = other.find_rank_with_count(group_size);
-        match self_n.cmp(&other_n) {
-            Ordering::Equal => self.kickers(&[self_n]).cmp(&other.kickers(&[other_n])),
-            ord => ord,
+    /// Rangene som utgjør selve kombinasjonen, viktigste først
+    fn primary_ranks(&self) -> Vec<Rank> {
+        use RankingCategory::*;
+
+        match self.ranking_category {
+            FourOfAKind => self.find_all_ranks_with_count(4),
+            ThreeOfAKind => self.find_all_ranks_with_count(3),
+            TwoPair | OnePair => self.find_all_ranks_with_count(2),
+            FullHouse => vec![self.find_rank_with_count(3), self.find_rank_with_count(2)],
+            HighCard => vec![self.cards[4].rank],
+            Flush | StraightFlush | Straight => self.kickers(&[]),
         }
     }
 
-    fn compare_same_category(&self, other: &Self) -> Ordering {
+    /// Rangene som sammenlignes, i rekkefølge, når to hender har samme kategori
+    fn tiebreakers(&self) -> Vec<(Tiebreak, Rank)> {
         use RankingCategory::*;
 
-        match self.ranking_category {
+        let primary = self.primary_ranks();
+        let groups = match self.ranking_category {
             Flush | HighCard | StraightFlush | Straight => {
-                // sammenlign kort baklengs for høyest først
-                self.cards
-                    .iter()
-                    .rev()
-                    .map(|c| c.rank)
-                    .cmp(other.cards.iter().rev().map(|c| c.rank))
+                return self
+                    .kickers(&[])
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, rank)| (Tiebreak::HighCard { position: i + 1 }, rank))
+                    .collect();
             }
+            FullHouse | TwoPair => vec![
+                (Tiebreak::PrimaryRank, primary[0]),
+                (Tiebreak::SecondaryRank, primary[1]),
+            ],
+            FourOfAKind | ThreeOfAKind | OnePair => vec![(Tiebreak::PrimaryRank, primary[0])],
+        };
+
+        let kickers = self
+            .kickers(&primary)
+            .into_iter()
+            .enumerate()
+            .map(|(i, rank)| (Tiebreak::Kicker { position: i + 1 }, rank));
+
+        groups.into_iter().chain(kickers).collect()
+    }
 
-            FourOfAKind => self.compare_n_of_a_kind(other, 4),
+    fn compare_same_category(&self, other: &Self) -> Ordering {
+        self.tiebreakers()
+            .iter()
+            .map(|(_, rank)| rank)
+            .cmp(other.tiebreakers().iter().map(|(_, rank)| rank))
+    }
 
-            ThreeOfAKind => self.compare_n_of_a_kind(other, 3),
+    /// Kortene som utgjør selve kombinasjonen, viktigste først
+    pub fn primary_cards(&self) -> Vec<Card> {
+        let primary = self.primary_ranks();
+        let mut cards: Vec<Card> = self
+            .cards
+            .iter()
+            .rev()
+            .filter(|card| primary.contains(&card.rank))
+            .cloned()
+            .collect();
 
-            OnePair => self.compare_n_of_a_kind(other, 2),
+        cards.sort_by_key(|card| primary.iter().position(|rank| *rank == card.rank));
+        cards
+    }
 
-            FullHouse => {
-                let self_three = self.find_rank_with_count(3);
-                let other_three = other.find_rank_with_count(3);
-                match self_three.cmp(&other_three) {
-                    Ordering::Equal => {
-                        let self_pair = self.find_rank_with_count(2);
-                        let other_pair = other.find_rank_with_count(2);
-                        self_pair.cmp(&other_pair)
-                    }
-                    ord => ord,
-                }
-            }
+    /// Kortene som ikke inngår i kombinasjonen, høyest først
+    pub fn kicker_cards(&self) -> Vec<Card> {
+        let primary = self.primary_ranks();
 
-            TwoPair => {
-                let self_pairs = self.find_all_ranks_with_count(2);
-                let other_pairs = other.find_all_ranks_with_count(2);
+        self.cards
+            .iter()
+            .rev()
+            .filter(|card| !primary.contains(&card.rank))
+            .cloned()
+            .collect()
+    }
 
-                let ord = self_pairs[0].cmp(&other_pairs[0]);
-                if ord != Ordering::Equal {
-                    return ord;
-                }
+    /// Menneskelesbar beskrivelse, f.eks. "Two Pair, Aces and Fives, Queen kicker"
+    pub fn describe(&self, locale: Locale) -> String {
+        use RankingCategory::*;
 
-                let ord = self_pairs[1].cmp(&other_pairs[1]);
-                if ord != Ordering::Equal {
-                    return ord;
-                }
+        let primary = self.primary_ranks();
+        let name = locale.category(&self.ranking_category);
+        let suit = &self.cards[0].suit;
+        let kicker = self
+            .kickers(&primary)
+            .first()
+            .map(|rank| format!(", {}", locale.kicker(rank)))
+            .unwrap_or_default();
 
-                self.kickers(&self_pairs).cmp(&other.kickers(&other_pairs))
+        match self.ranking_category {
+            StraightFlush if primary[0] == Rank::Ace => {
+                format!("{} {}", locale.royal_flush(), locale.in_suit(suit))
+            }
+            StraightFlush | Flush => format!(
+                "{name} {}, {}",
+                locale.in_suit(suit),
+                locale.high(&primary[0])
+            ),
+            Straight => format!("{name}, {}", locale.high(&primary[0])),
+            FourOfAKind | ThreeOfAKind | OnePair => {
+                format!("{name}, {}{kicker}", locale.ranks(&primary[0]))
             }
+            FullHouse => format!("{name}, {}", locale.full_of(&primary[0], &primary[1])),
+            TwoPair => format!(
+                "{name}, {}{kicker}",
+                locale.two_pair(&primary[0], &primary[1])
+            ),
+            HighCard => format!("{name}, {}", locale.rank(&primary[0])),
         }
     }
+
+    /// Finner ut hva som avgjorde sammenligningen mellom to hender.
+    /// Gir `None` dersom hendene er like sterke.
+    pub fn decided_by(&self, other: &Self) -> Option<Tiebreak> {
+        if self.ranking_category != other.ranking_category {
+            return Some(Tiebreak::RankingCategory);
+        }
+
+        self.tiebreakers()
+            .into_iter()
+            .zip(other.tiebreakers())
+            .find(|((_, a), (_, b))| a != b)
+            .map(|((tiebreak, _), _)| tiebreak)
+    }
 }
 
 impl PartialEq for Hand {
@@ -254,6 +426,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn try_from_rejects_duplicate_cards() {
+        let cards = [
+            c(Hearts, Numeral(6)),
+            c(Hearts, Numeral(7)),
+            c(Spades, Ace),
+            c(Hearts, Numeral(7)),
+            c(Hearts, Numeral(10)),
+        ];
+        let duplicates = Hand::try_from(cards.clone()).unwrap_err();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].position, 3);
+        assert_eq!(duplicates[0].card, c(Hearts, Numeral(7)));
+
+        let mut cards = cards;
+        cards[3] = c(Hearts, Numeral(9));
+        let hand = Hand::try_from(cards).unwrap();
+        assert_eq!(hand.ranking_category(), RankingCategory::HighCard);
+    }
+
+    #[test]
+    fn best_of_seven_finds_hidden_flush() {
+        let cards = [
+            c(Spades, Ace),
+            c(Hearts, Numeral(2)),
+            c(Hearts, Numeral(5)),
+            c(Clubs, Ace),
+            c(Hearts, Numeral(9)),
+            c(Hearts, Jack),
+            c(Hearts, King),
+        ];
+        let hand = Hand::best_of(&cards).unwrap();
+
+        assert_eq!(hand.ranking_category(), RankingCategory::Flush);
+        assert!(Hand::best_of(&cards[..4]).is_none());
+    }
+
     #[test]
     fn detects_four_of_a_kind() {
         let cards = [
@@ -373,7 +582,7 @@ mod tests {
 
     #[test]
     fn higher_category_wins() {
-        let flush = Hand::from([
+        let flush = Hand::from_distinct([
             c(Hearts, Numeral(2)),
             c(Hearts, Numeral(4)),
             c(Hearts, Numeral(6)),
@@ -381,7 +590,7 @@ mod tests {
             c(Hearts, Queen),
         ]);
 
-        let straight = Hand::from([
+        let straight = Hand::from_distinct([
             c(Clubs, Numeral(5)),
             c(Diamonds, Numeral(6)),
             c(Spades, Numeral(7)),
@@ -394,7 +603,7 @@ mod tests {
 
     #[test]
     fn kicker_breaks_tie_for_one_pair() {
-        let hand1 = Hand::from([
+        let hand1 = Hand::from_distinct([
             c(Hearts, Numeral(9)),
             c(Diamonds, Numeral(9)),
             c(Spades, Numeral(3)),
@@ -402,7 +611,7 @@ mod tests {
             c(Spades, Queen),
         ]);
 
-        let hand2 = Hand::from([
+        let hand2 = Hand::from_distinct([
             c(Clubs, Numeral(9)),
             c(Spades, Numeral(9)),
             c(Diamonds, Numeral(3)),
@@ -415,7 +624,7 @@ mod tests {
 
     #[test]
     fn same_hand_different_order_is_equal() {
-        let hand1 = Hand::from([
+        let hand1 = Hand::from_distinct([
             c(Hearts, Ace),
             c(Clubs, King),
             c(Diamonds, Queen),
@@ -423,7 +632,7 @@ mod tests {
             c(Hearts, Numeral(10)),
         ]);
 
-        let hand2 = Hand::from([
+        let hand2 = Hand::from_distinct([
             c(Hearts, Numeral(10)),
             c(Spades, Jack),
             c(Diamonds, Queen),
@@ -436,7 +645,7 @@ mod tests {
 
     #[test]
     fn two_pair_with_higher_pair_wins() {
-        let hand1 = Hand::from([
+        let hand1 = Hand::from_distinct([
             c(Clubs, Numeral(4)),
             c(Diamonds, Numeral(4)),
             c(Spades, Numeral(2)),
@@ -444,7 +653,7 @@ mod tests {
             c(Clubs, Queen),
         ]);
 
-        let hand2 = Hand::from([
+        let hand2 = Hand::from_distinct([
             c(Clubs, Numeral(5)),
             c(Diamonds, Numeral(5)),
             c(Spades, Numeral(2)),
@@ -454,4 +663,113 @@ mod tests {
 
         assert!(hand2 > hand1); // 5/2 beats 4/2
     }
+
+    #[test]
+    fn describes_two_pair_with_kicker() {
+        let hand = Hand::from_distinct([
+            c(Spades, Ace),
+            c(Clubs, Ace),
+            c(Hearts, Numeral(5)),
+            c(Diamonds, Numeral(5)),
+            c(Clubs, Queen),
+        ]);
+
+        assert_eq!(
+            hand.describe(Locale::En),
+            "Two Pair, Aces and Fives, Queen kicker"
+        );
+        assert_eq!(
+            hand.describe(Locale::Nb),
+            "To par, ess og femmere, dame som kicker"
+        );
+    }
+
+    #[test]
+    fn describes_full_house_and_royal_flush() {
+        let full_house = Hand::from_distinct([
+            c(Clubs, Jack),
+            c(Clubs, Numeral(3)),
+            c(Spades, Numeral(3)),
+            c(Diamonds, Jack),
+            c(Hearts, Numeral(3)),
+        ]);
+        let royal_flush = Hand::from_distinct([
+            c(Hearts, Ace),
+            c(Hearts, King),
+            c(Hearts, Queen),
+            c(Hearts, Jack),
+            c(Hearts, Numeral(10)),
+        ]);
+
+        assert_eq!(
+            full_house.describe(Locale::En),
+            "Full House, Threes full of Jacks"
+        );
+        assert_eq!(royal_flush.describe(Locale::En), "Royal Flush in Hearts");
+        assert_eq!(royal_flush.describe(Locale::Nb), "Royal flush i hjerter");
+    }
+
+    #[test]
+    fn splits_primary_cards_and_kickers() {
+        let hand = Hand::from_distinct([
+            c(Hearts, Numeral(9)),
+            c(Spades, Numeral(3)),
+            c(Diamonds, Numeral(9)),
+            c(Clubs, Jack),
+            c(Spades, Queen),
+        ]);
+
+        assert_eq!(
+            hand.primary_cards(),
+            vec![c(Diamonds, Numeral(9)), c(Hearts, Numeral(9))]
+        );
+        assert_eq!(
+            hand.kicker_cards(),
+            vec![c(Spades, Queen), c(Clubs, Jack), c(Spades, Numeral(3))]
+        );
+    }
+
+    #[test]
+    fn reports_deciding_tiebreak() {
+        let pair_jack_kicker = Hand::from_distinct([
+            c(Hearts, Numeral(9)),
+            c(Diamonds, Numeral(9)),
+            c(Spades, Numeral(3)),
+            c(Clubs, Jack),
+            c(Spades, Queen),
+        ]);
+        let pair_ten_kicker = Hand::from_distinct([
+            c(Clubs, Numeral(9)),
+            c(Spades, Numeral(9)),
+            c(Diamonds, Numeral(3)),
+            c(Hearts, Numeral(10)),
+            c(Diamonds, Queen),
+        ]);
+        let flush = Hand::from_distinct([
+            c(Hearts, Numeral(2)),
+            c(Hearts, Numeral(4)),
+            c(Hearts, Numeral(6)),
+            c(Hearts, Numeral(8)),
+            c(Hearts, Queen),
+        ]);
+
+        assert_eq!(
+            pair_jack_kicker.decided_by(&pair_ten_kicker),
+            Some(Tiebreak::Kicker { position: 2 })
+        );
+        assert_eq!(
+            flush.decided_by(&pair_jack_kicker),
+            Some(Tiebreak::RankingCategory)
+        );
+        assert_eq!(flush.decided_by(&flush), None);
+    }
+
+    #[test]
+    fn ranking_category_string_roundtrip() {
+        for category in [RankingCategory::StraightFlush, RankingCategory::HighCard] {
+            assert_eq!(category.as_str().parse(), Ok(category));
+            assert_eq!(serde_json::to_value(category).unwrap(), category.as_str());
+        }
+        assert!("Royal".parse::<RankingCategory>().is_err());
+    }
 }
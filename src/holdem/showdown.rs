@@ -0,0 +1,65 @@
+//! Rangering av flere hender mot hverandre
+
+use super::hand::Hand;
+
+/// Plasseringen til hver hånd, i samme rekkefølge som `hands`. Like sterke
+/// hender deler plass, og neste plass hoppes over tilsvarende (1, 1, 3).
+pub fn places(hands: &[Hand]) -> Vec<usize> {
+    hands
+        .iter()
+        .map(|hand| 1 + hands.iter().filter(|other| *other > hand).count())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::deck::{
+            Card,
+            Rank::{self, *},
+            Suit::{self, *},
+        },
+        *,
+    };
+
+    fn c(suit: Suit, rank: Rank) -> Card {
+        Card { suit, rank }
+    }
+
+    #[test]
+    fn ties_share_place() {
+        let royal_hearts = Hand::from_distinct([
+            c(Hearts, Ace),
+            c(Hearts, King),
+            c(Hearts, Queen),
+            c(Hearts, Jack),
+            c(Hearts, Numeral(10)),
+        ]);
+        let royal_spades = Hand::from_distinct([
+            c(Spades, Ace),
+            c(Spades, King),
+            c(Spades, Queen),
+            c(Spades, Jack),
+            c(Spades, Numeral(10)),
+        ]);
+        let pair = Hand::from_distinct([
+            c(Clubs, Numeral(9)),
+            c(Diamonds, Numeral(9)),
+            c(Clubs, Numeral(3)),
+            c(Clubs, Jack),
+            c(Diamonds, Queen),
+        ]);
+        let high_card = Hand::from_distinct([
+            c(Clubs, Numeral(2)),
+            c(Diamonds, Numeral(4)),
+            c(Clubs, Numeral(7)),
+            c(Diamonds, Jack),
+            c(Clubs, King),
+        ]);
+
+        assert_eq!(
+            places(&[pair, royal_hearts, high_card, royal_spades]),
+            vec![3, 1, 4, 1]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Validering av hender før de sammenlignes. En kortstokk har bare ett
+//! eksemplar av hvert kort, så samme kort kan ikke forekomme to ganger,
+//! verken i samme hånd eller på tvers av hender.
+use core::fmt;
+use std::collections::HashMap;
+
+use super::deck::Card;
+
+/// Et kort som allerede er brukt
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DuplicateCard {
+    /// Indeksen til hånden kortet ble funnet i
+    pub hand: usize,
+    pub external_id: String,
+    /// Kortets plass i hånden
+    pub position: usize,
+    pub card: Card,
+    /// Hånden kortet først ble brukt i, `None` dersom det er samme hånd
+    pub first_seen_in: Option<String>,
+}
+
+impl fmt::Display for DuplicateCard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.first_seen_in {
+            Some(other) => write!(
+                f,
+                "card {} in hand {:?} is already used in hand {:?}",
+                self.card, self.external_id, other
+            ),
+            None => write!(
+                f,
+                "card {} appears more than once in hand {:?}",
+                self.card, self.external_id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DuplicateCard {}
+
+/// Sjekker at ingen kort forekommer mer enn én gang. Hendene gis som par av
+/// ekstern id og kort. Returnerer alle duplikater, i den rekkefølgen de
+/// forekommer.
+pub fn validate_hands<'a, I>(hands: I) -> Result<(), Vec<DuplicateCard>>
+where
+    I: IntoIterator<Item = (&'a str, &'a [Card])>,
+{
+    let mut seen: HashMap<&Card, (usize, &str)> = HashMap::new();
+    let mut duplicates = vec![];
+
+    for (hand, (external_id, cards)) in hands.into_iter().enumerate() {
+        for (position, card) in cards.iter().enumerate() {
+            let Some(&(first_hand, first_id)) = seen.get(card) else {
+                seen.insert(card, (hand, external_id));
+                continue;
+            };
+
+            duplicates.push(DuplicateCard {
+                hand,
+                external_id: external_id.to_string(),
+                position,
+                card: card.clone(),
+                first_seen_in: (first_hand != hand).then(|| first_id.to_string()),
+            });
+        }
+    }
+
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        Err(duplicates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::deck::{
+            Rank::{self, *},
+            Suit::{self, *},
+        },
+        *,
+    };
+
+    fn c(suit: Suit, rank: Rank) -> Card {
+        Card { suit, rank }
+    }
+
+    #[test]
+    fn accepts_distinct_cards() {
+        let a = [c(Hearts, Ace), c(Hearts, King)];
+        let b = [c(Spades, Ace), c(Spades, King)];
+
+        assert_eq!(validate_hands([("a", &a[..]), ("b", &b[..])]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_duplicate_within_hand() {
+        let a: [Card; 5] = core::array::from_fn(|_| c(Hearts, Ace));
+
+        let duplicates = validate_hands([("a", &a[..])]).unwrap_err();
+
+        assert_eq!(duplicates.len(), 4);
+        assert_eq!(duplicates[0].position, 1);
+        assert_eq!(duplicates[0].first_seen_in, None);
+    }
+
+    #[test]
+    fn rejects_duplicate_across_hands() {
+        let a = [c(Hearts, Ace), c(Hearts, King)];
+        let b = [c(Spades, Ace), c(Hearts, Ace)];
+
+        let duplicates = validate_hands([("a", &a[..]), ("b", &b[..])]).unwrap_err();
+
+        assert_eq!(
+            duplicates,
+            vec![DuplicateCard {
+                hand: 1,
+                external_id: "b".into(),
+                position: 1,
+                card: c(Hearts, Ace),
+                first_seen_in: Some("a".into()),
+            }]
+        );
+        assert_eq!(
+            duplicates[0].to_string(),
+            r#"card ah in hand "b" is already used in hand "a""#
+        );
+    }
+}
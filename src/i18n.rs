@@ -0,0 +1,225 @@
+//! Oversettelser av kort- og håndnavn. Støtter norsk bokmål og engelsk,
+//! engelsk er standard.
+use crate::{Rank, RankingCategory, Suit};
+
+/// Språket tekster presenteres på
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Nb,
+}
+
+impl Locale {
+    /// Velger det best egnede språket fra en `Accept-Language`-header,
+    /// f.eks. "nb-NO,nb;q=0.9,en;q=0.8". Ukjente språk ignoreres.
+    pub fn from_accept_language(header: &str) -> Self {
+        header
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                let locale = Self::from_tag(params.next()?.trim())?;
+                let quality = match params.find_map(|p| p.trim().strip_prefix("q=")) {
+                    Some(q) => q.parse().ok()?,
+                    None => 1.0,
+                };
+
+                Some((locale, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .fold(
+                None,
+                |best: Option<(Self, f32)>, (locale, quality)| match best {
+                    Some((_, best_quality)) if best_quality >= quality => best,
+                    _ => Some((locale, quality)),
+                },
+            )
+            .map(|(locale, _)| locale)
+            .unwrap_or_default()
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split('-').next()?.to_ascii_lowercase();
+
+        match primary.as_str() {
+            "nb" | "no" | "nn" => Some(Self::Nb),
+            "en" => Some(Self::En),
+            _ => None,
+        }
+    }
+
+    /// Navnet på en rang i entall, f.eks. "Queen"
+    pub fn rank(&self, rank: &Rank) -> &'static str {
+        match (self, rank.value()) {
+            (Self::En, 2) => "Two",
+            (Self::En, 3) => "Three",
+            (Self::En, 4) => "Four",
+            (Self::En, 5) => "Five",
+            (Self::En, 6) => "Six",
+            (Self::En, 7) => "Seven",
+            (Self::En, 8) => "Eight",
+            (Self::En, 9) => "Nine",
+            (Self::En, 10) => "Ten",
+            (Self::En, 11) => "Jack",
+            (Self::En, 12) => "Queen",
+            (Self::En, 13) => "King",
+            (Self::En, _) => "Ace",
+            (Self::Nb, 2) => "toer",
+            (Self::Nb, 3) => "treer",
+            (Self::Nb, 4) => "firer",
+            (Self::Nb, 5) => "femmer",
+            (Self::Nb, 6) => "sekser",
+            (Self::Nb, 7) => "sjuer",
+            (Self::Nb, 8) => "åtter",
+            (Self::Nb, 9) => "nier",
+            (Self::Nb, 10) => "tier",
+            (Self::Nb, 11) => "knekt",
+            (Self::Nb, 12) => "dame",
+            (Self::Nb, 13) => "konge",
+            (Self::Nb, _) => "ess",
+        }
+    }
+
+    /// Navnet på en rang i flertall, f.eks. "Queens"
+    pub fn ranks(&self, rank: &Rank) -> &'static str {
+        match (self, rank.value()) {
+            (Self::En, 2) => "Twos",
+            (Self::En, 3) => "Threes",
+            (Self::En, 4) => "Fours",
+            (Self::En, 5) => "Fives",
+            (Self::En, 6) => "Sixes",
+            (Self::En, 7) => "Sevens",
+            (Self::En, 8) => "Eights",
+            (Self::En, 9) => "Nines",
+            (Self::En, 10) => "Tens",
+            (Self::En, 11) => "Jacks",
+            (Self::En, 12) => "Queens",
+            (Self::En, 13) => "Kings",
+            (Self::En, _) => "Aces",
+            (Self::Nb, 2) => "toere",
+            (Self::Nb, 3) => "treere",
+            (Self::Nb, 4) => "firere",
+            (Self::Nb, 5) => "femmere",
+            (Self::Nb, 6) => "seksere",
+            (Self::Nb, 7) => "sjuere",
+            (Self::Nb, 8) => "åttere",
+            (Self::Nb, 9) => "niere",
+            (Self::Nb, 10) => "tiere",
+            (Self::Nb, 11) => "knekter",
+            (Self::Nb, 12) => "damer",
+            (Self::Nb, 13) => "konger",
+            (Self::Nb, _) => "ess",
+        }
+    }
+
+    pub fn suit(&self, suit: &Suit) -> &'static str {
+        match (self, suit) {
+            (Self::En, Suit::Clubs) => "Clubs",
+            (Self::En, Suit::Diamonds) => "Diamonds",
+            (Self::En, Suit::Hearts) => "Hearts",
+            (Self::En, Suit::Spades) => "Spades",
+            (Self::Nb, Suit::Clubs) => "kløver",
+            (Self::Nb, Suit::Diamonds) => "ruter",
+            (Self::Nb, Suit::Hearts) => "hjerter",
+            (Self::Nb, Suit::Spades) => "spar",
+        }
+    }
+
+    pub fn category(&self, category: &RankingCategory) -> &'static str {
+        use RankingCategory::*;
+
+        match (self, category) {
+            (Self::En, StraightFlush) => "Straight Flush",
+            (Self::En, FourOfAKind) => "Four of a Kind",
+            (Self::En, FullHouse) => "Full House",
+            (Self::En, Flush) => "Flush",
+            (Self::En, Straight) => "Straight",
+            (Self::En, ThreeOfAKind) => "Three of a Kind",
+            (Self::En, TwoPair) => "Two Pair",
+            (Self::En, OnePair) => "One Pair",
+            (Self::En, HighCard) => "High Card",
+            (Self::Nb, StraightFlush) => "Straight flush",
+            (Self::Nb, FourOfAKind) => "Fire like",
+            (Self::Nb, FullHouse) => "Hus",
+            (Self::Nb, Flush) => "Flush",
+            (Self::Nb, Straight) => "Straight",
+            (Self::Nb, ThreeOfAKind) => "Tre like",
+            (Self::Nb, TwoPair) => "To par",
+            (Self::Nb, OnePair) => "Ett par",
+            (Self::Nb, HighCard) => "Høyt kort",
+        }
+    }
+
+    pub(crate) fn royal_flush(&self) -> &'static str {
+        match self {
+            Self::En => "Royal Flush",
+            Self::Nb => "Royal flush",
+        }
+    }
+
+    /// "in Hearts"
+    pub(crate) fn in_suit(&self, suit: &Suit) -> String {
+        match self {
+            Self::En => format!("in {}", self.suit(suit)),
+            Self::Nb => format!("i {}", self.suit(suit)),
+        }
+    }
+
+    /// "King high"
+    pub(crate) fn high(&self, rank: &Rank) -> String {
+        match self {
+            Self::En => format!("{} high", self.rank(rank)),
+            Self::Nb => format!("{} høy", self.rank(rank)),
+        }
+    }
+
+    /// "Queen kicker"
+    pub(crate) fn kicker(&self, rank: &Rank) -> String {
+        match self {
+            Self::En => format!("{} kicker", self.rank(rank)),
+            Self::Nb => format!("{} som kicker", self.rank(rank)),
+        }
+    }
+
+    /// "Aces and Fives"
+    pub(crate) fn two_pair(&self, high: &Rank, low: &Rank) -> String {
+        match self {
+            Self::En => format!("{} and {}", self.ranks(high), self.ranks(low)),
+            Self::Nb => format!("{} og {}", self.ranks(high), self.ranks(low)),
+        }
+    }
+
+    /// "Threes full of Jacks"
+    pub(crate) fn full_of(&self, three: &Rank, pair: &Rank) -> String {
+        match self {
+            Self::En => format!("{} full of {}", self.ranks(three), self.ranks(pair)),
+            Self::Nb => format!("{} over {}", self.ranks(three), self.ranks(pair)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_picks_highest_quality_supported_language() {
+        assert_eq!(
+            Locale::from_accept_language("nb-NO,nb;q=0.9,en;q=0.8"),
+            Locale::Nb
+        );
+        assert_eq!(
+            Locale::from_accept_language("de, en;q=0.5, nb;q=0.4"),
+            Locale::En
+        );
+        assert_eq!(Locale::from_accept_language("no;q=0.2, fr"), Locale::Nb);
+    }
+
+    #[test]
+    fn test_falls_back_to_english() {
+        assert_eq!(Locale::from_accept_language(""), Locale::En);
+        assert_eq!(Locale::from_accept_language("*"), Locale::En);
+        assert_eq!(Locale::from_accept_language("nb;q=0"), Locale::En);
+        assert_eq!(Locale::from_accept_language("nb;q=abc"), Locale::En);
+    }
+}
@@ -1,6 +1,31 @@
 mod api;
 mod holdem;
+mod i18n;
+mod metrics;
+mod retention;
+mod storage;
+mod tables;
 
-pub use api::router;
-pub use holdem::deck::{Card, DECK_SIZE, Deck, DeckProvider, Rank, Suit};
-pub use holdem::hand::Hand;
+pub use api::{CompareLimits, RateLimit, RouterOptions, router};
+pub use holdem::deal::{Board, DealError, MIN_PLAYERS, Seat, Street, Table, Variant, deal};
+pub use holdem::deck::{Card, DECK_SIZE, Deck, DeckProvider, OrderError, Preset, Rank, Suit};
+pub use holdem::draw::{
+    DRAW_MAX_PLAYERS, Draw, DrawError, DrawReplayError, DrawSeat, MAX_DISCARDS,
+};
+pub use holdem::game::{
+    Action, Game, GameError, HandResult, HandView, SeatView, ShownHand, TableView, Winning,
+};
+pub use holdem::hand::{EVALUATOR_VERSION, Hand, RankingCategory, Tiebreak};
+pub use holdem::showdown::places;
+pub use holdem::validation::{DuplicateCard, validate_hands};
+pub use i18n::Locale;
+pub use metrics::Metrics;
+pub use retention::Retention;
+#[cfg(feature = "postgres")]
+pub use storage::PostgresRepository;
+#[cfg(feature = "sqlite")]
+pub use storage::SqliteRepository;
+pub use storage::{
+    ApiKey, Comparison, DeckInfo, DrawGame, Event, EventKind, HistoryEntry, HistoryFilter,
+    HistoryKey, HistoryPosition, MemoryRepository, Repository, StorageError, User,
+};
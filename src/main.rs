@@ -1,16 +1,74 @@
 mod options;
 
-use pokerhaand::router;
-use sqlx::SqlitePool;
+use std::{net::SocketAddr, sync::Arc};
+
+use options::{Config, Database, LogFormat};
+use pokerhaand::{
+    Metrics, PostgresRepository, Repository, RouterOptions, SqliteRepository, router,
+};
+use sqlx::{PgPool, SqlitePool};
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> eyre::Result<()> {
     let options = options::from_env()?;
-    let pool = SqlitePool::connect(&options.database_url).await?;
+    init_tracing(&options)?;
+
+    let repository: Arc<dyn Repository> = match options.database() {
+        Database::Sqlite => {
+            let repository =
+                SqliteRepository::new(SqlitePool::connect(&options.database_url).await?);
+            repository.migrate().await?;
+            Arc::new(repository)
+        }
+        Database::Postgres => {
+            let repository = PostgresRepository::new(PgPool::connect(&options.database_url).await?);
+            repository.migrate().await?;
+            Arc::new(repository)
+        }
+    };
 
-    sqlx::migrate!().run(&pool).await?;
+    let metrics = Arc::new(Metrics::new());
 
-    let app = router(Default::default(), pool);
+    let retention = options.retention();
+    if retention.is_enabled() {
+        tokio::spawn(retention.run(
+            repository.clone(),
+            metrics.clone(),
+            options.history_prune_interval(),
+        ));
+    }
+
+    let router_options = RouterOptions {
+        rate_limit: options.rate_limit(),
+        compare_limits: options.compare_limits(),
+        trusted_proxies: options.trusted_proxies,
+        admin_token: options.admin_token,
+        metrics,
+    };
+    let app = router(Default::default(), repository, router_options);
     let listener = tokio::net::TcpListener::bind(options.address).await?;
-    axum::serve(listener, app).await.map_err(Into::into)
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .map_err(Into::into)
+}
+
+fn init_tracing(options: &Config) -> eyre::Result<()> {
+    let subscriber =
+        tracing_subscriber::fmt().with_env_filter(EnvFilter::try_new(&options.log_level)?);
+
+    match options.log_format {
+        LogFormat::Text => subscriber.init(),
+        // Feltene i hendelsen og spennet til forespørselen på toppnivå
+        LogFormat::Json => subscriber
+            .json()
+            .flatten_event(true)
+            .with_span_list(false)
+            .init(),
+    }
+
+    Ok(())
 }
@@ -0,0 +1,86 @@
+//! Enkle tellere for driften, eksportert i Prometheus sitt tekstformat
+use core::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Default, Debug)]
+pub struct Metrics {
+    history_prune_runs: AtomicU64,
+    history_prune_failures: AtomicU64,
+    history_pruned: AtomicU64,
+    history_purged: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// En runde med sletting av gammel historikk
+    pub fn record_prune(&self, deleted: u64) {
+        self.history_prune_runs.fetch_add(1, Ordering::Relaxed);
+        self.history_pruned.fetch_add(deleted, Ordering::Relaxed);
+    }
+
+    pub fn record_prune_failure(&self) {
+        self.history_prune_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Historikk slettet for en kortstokk av en administrator
+    pub fn record_purge(&self, deleted: u64) {
+        self.history_purged.fetch_add(deleted, Ordering::Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let counters = [
+            (
+                "pokerhaand_history_prune_runs_total",
+                "Completed runs of the history retention task",
+                &self.history_prune_runs,
+            ),
+            (
+                "pokerhaand_history_prune_failures_total",
+                "Failed runs of the history retention task",
+                &self.history_prune_failures,
+            ),
+            (
+                "pokerhaand_history_pruned_total",
+                "History entries deleted by the retention task",
+                &self.history_pruned,
+            ),
+            (
+                "pokerhaand_history_purged_total",
+                "History entries deleted for a deck by an administrator",
+                &self.history_purged,
+            ),
+        ];
+
+        let mut output = String::new();
+        for (name, help, counter) in counters {
+            let value = counter.load(Ordering::Relaxed);
+            let _ = writeln!(output, "# HELP {name} {help}");
+            let _ = writeln!(output, "# TYPE {name} counter");
+            let _ = writeln!(output, "{name} {value}");
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_counters() {
+        let metrics = Metrics::new();
+        metrics.record_prune(3);
+        metrics.record_prune(2);
+
+        let output = metrics.render();
+
+        assert!(output.contains("# TYPE pokerhaand_history_pruned_total counter\n"));
+        assert!(output.contains("pokerhaand_history_pruned_total 5\n"));
+        assert!(output.contains("pokerhaand_history_prune_runs_total 2\n"));
+        assert!(output.contains("pokerhaand_history_purged_total 0\n"));
+    }
+}
@@ -1,3 +1,6 @@
+use std::{net::IpAddr, num::NonZeroU64, time::Duration};
+
+use pokerhaand::{CompareLimits, RateLimit, Retention};
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -6,10 +9,103 @@ pub struct Config {
     pub database_url: String,
     #[serde(default = "default_address")]
     pub address: String,
+    /// Historikk eldre enn dette slettes
+    pub history_max_age_secs: Option<u64>,
+    /// Bare så mange av de nyeste elementene i historikken beholdes
+    pub history_max_rows: Option<usize>,
+    /// Hvor ofte historikken ryddes. 0 avvises, siden det ikke gir noe
+    /// intervall å vente på.
+    #[serde(default = "default_history_prune_interval_secs")]
+    pub history_prune_interval_secs: NonZeroU64,
+    /// Token for admin-endepunktene, som er stengt uten
+    pub admin_token: Option<String>,
+    /// Forespørsler per sekund per klient. Uten denne er det ingen grense.
+    pub rate_limit_per_second: Option<f64>,
+    /// Forespørsler en klient kan gjøre på en gang
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+    /// Største innhold i en sammenligning, i bytes
+    pub compare_max_body_bytes: Option<usize>,
+    pub compare_max_hands: Option<usize>,
+    pub compare_max_external_id_length: Option<usize>,
+    /// Filter for loggen, f.eks. `info` eller `info,sqlx=warn`
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Proxyer, adskilt med komma, som får oppgi klientens IP-adresse
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Formatet på loggen. JSON er ment for innsamling, tekst for utvikling.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Databasen som brukes, valgt ut fra skjemaet i `database_url`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Database {
+    Sqlite,
+    Postgres,
+}
+
+impl Config {
+    pub fn retention(&self) -> Retention {
+        Retention {
+            max_age: self.history_max_age_secs.map(Duration::from_secs),
+            max_rows: self.history_max_rows,
+        }
+    }
+
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit_per_second
+            .filter(|rate| *rate > 0.0)
+            .map(|requests_per_second| RateLimit {
+                requests_per_second,
+                burst: self.rate_limit_burst.max(1),
+            })
+    }
+
+    /// Grensene som ikke er satt får standardverdien
+    pub fn compare_limits(&self) -> CompareLimits {
+        let defaults = CompareLimits::default();
+        CompareLimits {
+            max_body_bytes: self
+                .compare_max_body_bytes
+                .unwrap_or(defaults.max_body_bytes),
+            max_hands: self.compare_max_hands.unwrap_or(defaults.max_hands),
+            max_external_id_length: self
+                .compare_max_external_id_length
+                .unwrap_or(defaults.max_external_id_length),
+        }
+    }
+
+    pub fn history_prune_interval(&self) -> Duration {
+        Duration::from_secs(self.history_prune_interval_secs.get())
+    }
+
+    pub fn database(&self) -> Database {
+        if self.database_url.starts_with("postgres://")
+            || self.database_url.starts_with("postgresql://")
+        {
+            Database::Postgres
+        } else {
+            Database::Sqlite
+        }
+    }
 }
 
 pub fn from_env() -> eyre::Result<Config> {
-    envy::from_env().map_err(Into::into)
+    from_vars(std::env::vars())
+}
+
+fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> eyre::Result<Config> {
+    envy::from_iter(vars).map_err(Into::into)
 }
 
 fn default_address() -> String {
@@ -19,3 +115,46 @@ fn default_address() -> String {
 fn default_database_url() -> String {
     "sqlite::memory:".into()
 }
+
+fn default_log_level() -> String {
+    "info".into()
+}
+
+fn default_rate_limit_burst() -> u32 {
+    20
+}
+
+fn default_history_prune_interval_secs() -> NonZeroU64 {
+    NonZeroU64::new(60 * 60).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(vars: &[(&str, &str)]) -> eyre::Result<Config> {
+        from_vars(
+            vars.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        )
+    }
+
+    #[test]
+    fn test_history_prune_interval_defaults_to_an_hour() {
+        let config = config(&[]).unwrap();
+
+        assert_eq!(config.history_prune_interval(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_history_prune_interval_can_be_set() {
+        let config = config(&[("HISTORY_PRUNE_INTERVAL_SECS", "60")]).unwrap();
+
+        assert_eq!(config.history_prune_interval(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_zero_history_prune_interval_is_rejected() {
+        assert!(config(&[("HISTORY_PRUNE_INTERVAL_SECS", "0")]).is_err());
+    }
+}
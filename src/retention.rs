@@ -0,0 +1,107 @@
+//! Sletting av gammel historikk. Hendelsesloggen beholdes, det er bare
+//! projeksjonen i `history` som begrenses.
+use std::{sync::Arc, time::Duration};
+
+use crate::{Metrics, Repository, StorageError};
+
+/// Hvor mye historikk som beholdes. Uten noen av grensene beholdes alt.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Retention {
+    pub max_age: Option<Duration>,
+    pub max_rows: Option<usize>,
+}
+
+impl Retention {
+    pub fn is_enabled(&self) -> bool {
+        self.max_age.is_some() || self.max_rows.is_some()
+    }
+
+    /// Sletter historikk utover grensene én gang, med alderen regnet fra
+    /// `now` i millisekunder siden epoch. Returnerer antall slettede
+    /// elementer.
+    pub async fn prune(
+        &self,
+        repository: &dyn Repository,
+        metrics: &Metrics,
+        now: u64,
+    ) -> Result<u64, StorageError> {
+        let before = self
+            .max_age
+            .map(|age| now.saturating_sub(age.as_millis() as u64));
+
+        let deleted = repository.prune_history(before, self.max_rows).await?;
+        metrics.record_prune(deleted);
+        tracing::info!(deleted, before, max_rows = self.max_rows, "pruned history");
+
+        Ok(deleted)
+    }
+
+    /// Kjører `prune` med fast intervall, første gang med en gang
+    pub async fn run(
+        self,
+        repository: Arc<dyn Repository>,
+        metrics: Arc<Metrics>,
+        every: Duration,
+    ) {
+        let mut interval = tokio::time::interval(every);
+
+        loop {
+            interval.tick().await;
+
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            if let Err(error) = self.prune(&*repository, &metrics, now).await {
+                metrics.record_prune_failure();
+                tracing::error!(%error, "failed to prune history");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{Event, EventKind, MemoryRepository, RankingCategory};
+
+    #[tokio::test]
+    async fn test_prune_keeps_newest_rows_and_counts_them() {
+        let repository = MemoryRepository::new();
+        let metrics = Metrics::new();
+        let now = 1_760_788_800_000;
+
+        for offset in 0..4 {
+            let event = Event {
+                kind: EventKind::View,
+                time: now - 1000 * offset as u64,
+                deck: Some(Uuid::new_v4()),
+                offset: Some(offset),
+                user_agent: None,
+                client_ip: None,
+                payload: None,
+            };
+            repository
+                .record_view(event, RankingCategory::HighCard, None)
+                .await
+                .unwrap();
+        }
+
+        let by_age = Retention {
+            max_age: Some(Duration::from_millis(2500)),
+            max_rows: None,
+        };
+        assert_eq!(by_age.prune(&repository, &metrics, now).await.unwrap(), 1);
+
+        let by_rows = Retention {
+            max_age: None,
+            max_rows: Some(1),
+        };
+        assert_eq!(by_rows.prune(&repository, &metrics, now).await.unwrap(), 2);
+
+        assert!(
+            metrics
+                .render()
+                .contains("pokerhaand_history_pruned_total 3\n")
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Lagring av hendelser, historikk og sammenligninger. API-et bruker bare
+//! `Repository`, slik at databasen kan byttes uten å endre handlerne.
+//! SQLite og PostgreSQL er egne features, mens `MemoryRepository` alltid er
+//! med.
+mod memory;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use core::fmt;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::{Card, RankingCategory};
+
+pub use memory::MemoryRepository;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteRepository;
+
+#[async_trait]
+pub trait Repository: Send + Sync {
+    async fn create_deck(&self, deck: &DeckInfo) -> Result<(), StorageError>;
+
+    /// `None` for kortstokker som ikke er lagret, f.eks. en tilfeldig UUID
+    async fn deck(&self, id: Uuid) -> Result<Option<DeckInfo>, StorageError>;
+
+    /// Gjør en skjult kortstokk synlig
+    async fn unseal_deck(&self, id: Uuid) -> Result<(), StorageError>;
+
+    /// Legger til en hendelse i hendelsesloggen
+    async fn record_event(&self, event: Event) -> Result<(), StorageError>;
+
+    /// Legger til en visning i hendelsesloggen og oppdaterer historikken,
+    /// som holder på siste visning og antall visninger per hånd og bruker.
+    /// `owner` er brukeren som så hånden. Returnerer elementet i historikken
+    /// slik det ble lagret.
+    async fn record_view(
+        &self,
+        event: Event,
+        ranking_category: RankingCategory,
+        owner: Option<Uuid>,
+    ) -> Result<HistoryEntry, StorageError>;
+
+    /// Henter historikk sortert på `(time, deck, offset)`, nyeste først. Med
+    /// `HistoryPosition::Before` hentes elementene nærmest nøkkelen først,
+    /// dvs. eldste først.
+    async fn history(
+        &self,
+        filter: &HistoryFilter,
+        position: HistoryPosition,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, StorageError>;
+
+    /// Sletter historikk som er eldre enn `before` eller som ikke er blant
+    /// de `keep` nyeste elementene. Returnerer antall slettede elementer.
+    async fn prune_history(
+        &self,
+        before: Option<u64>,
+        keep: Option<usize>,
+    ) -> Result<u64, StorageError>;
+
+    /// Sletter all historikk for en kortstokk. Returnerer antall slettede
+    /// elementer.
+    async fn purge_deck_history(&self, deck: Uuid) -> Result<u64, StorageError>;
+
+    /// Lagrer en sammenligning og hendelsen for den i samme transaksjon
+    async fn store_comparison(
+        &self,
+        comparison: &Comparison,
+        event: Event,
+    ) -> Result<(), StorageError>;
+
+    async fn comparison(&self, id: Uuid) -> Result<Option<Comparison>, StorageError>;
+
+    /// Returnerer `false` dersom kortstokken allerede har et spill
+    async fn create_draw_game(&self, game: &DrawGame) -> Result<bool, StorageError>;
+
+    async fn draw_game(&self, deck: Uuid) -> Result<Option<DrawGame>, StorageError>;
+
+    /// Lagrer posisjonene en plass kastet. Returnerer `false` dersom plassen
+    /// allerede har byttet.
+    async fn record_discard(
+        &self,
+        deck: Uuid,
+        seat: usize,
+        time: u64,
+        positions: &[usize],
+    ) -> Result<bool, StorageError>;
+
+    async fn create_user(&self, user: &User) -> Result<(), StorageError>;
+
+    async fn user(&self, id: Uuid) -> Result<Option<User>, StorageError>;
+
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), StorageError>;
+
+    /// Alle nøklene til en bruker, også de som er trukket tilbake
+    async fn api_keys(&self, user: Uuid) -> Result<Vec<ApiKey>, StorageError>;
+
+    /// Returnerer `false` dersom nøkkelen ikke finnes eller allerede er
+    /// trukket tilbake
+    async fn revoke_api_key(&self, id: Uuid, time: u64) -> Result<bool, StorageError>;
+
+    /// Brukeren som eier en aktiv nøkkel med hashen
+    async fn user_by_api_key(&self, key_hash: &str) -> Result<Option<User>, StorageError>;
+}
+
+/// Typer hendelser i hendelsesloggen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EventKind {
+    Deal,
+    View,
+    Compare,
+    /// En plass byttet kort i femkorts draw
+    Draw,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Deal => "deal",
+            EventKind::View => "view",
+            EventKind::Compare => "compare",
+            EventKind::Draw => "draw",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub kind: EventKind,
+    /// Millisekunder siden epoch
+    pub time: u64,
+    pub deck: Option<Uuid>,
+    pub offset: Option<usize>,
+    pub user_agent: Option<String>,
+    pub client_ip: Option<String>,
+    pub payload: Option<serde_json::Value>,
+}
+
+/// Filtre for historikken. Alle utenom `owner` er valgfrie.
+#[derive(Clone, Default, Debug)]
+pub struct HistoryFilter {
+    /// Hendene brukeren har sett. `None` gir det anonyme klienter har sett.
+    pub owner: Option<Uuid>,
+    pub deck: Option<Uuid>,
+    /// Inklusiv
+    pub from: Option<u64>,
+    /// Eksklusiv
+    pub to: Option<u64>,
+    pub hand_offset: Option<usize>,
+    pub ranking_category: Option<RankingCategory>,
+}
+
+/// Hvor i historikken en side starter
+#[derive(Clone, Copy, Debug)]
+pub enum HistoryPosition {
+    /// Hopp over så mange elementer fra det nyeste
+    Skip(usize),
+    /// Elementer eldre enn nøkkelen
+    After(HistoryKey),
+    /// Elementer nyere enn nøkkelen
+    Before(HistoryKey),
+}
+
+/// Sorteringsnøkkelen til et element i historikken
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct HistoryKey {
+    pub time: u64,
+    pub deck: Uuid,
+    pub offset: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    pub deck: Uuid,
+    pub offset: i64,
+    pub time: u64,
+    pub views: i64,
+    /// `None` for historikk lagret før kategorien ble tatt vare på
+    pub ranking_category: Option<RankingCategory>,
+    /// Brukeren som så hånden
+    pub owner: Option<Uuid>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        entry.owner == self.owner
+            && self.deck.is_none_or(|deck| entry.deck == deck)
+            && self.from.is_none_or(|from| entry.time >= from)
+            && self.to.is_none_or(|to| entry.time < to)
+            && self
+                .hand_offset
+                .is_none_or(|offset| entry.offset == offset as i64)
+            && self
+                .ranking_category
+                .is_none_or(|category| entry.ranking_category == Some(category))
+    }
+}
+
+impl HistoryEntry {
+    pub fn key(&self) -> HistoryKey {
+        HistoryKey {
+            time: self.time,
+            deck: self.deck,
+            offset: self.offset,
+        }
+    }
+}
+
+/// En kortstokk laget med `POST /api/v1/decks`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DeckInfo {
+    pub id: Uuid,
+    pub time: u64,
+    /// Kortstokken stokkes med denne i stedet for id-en når den er satt
+    pub seed: Option<String>,
+    pub label: Option<String>,
+    pub notes: Option<String>,
+    /// Kortene kortstokken starter med. Resten stokkes. `None` for
+    /// tilfeldige kortstokker.
+    pub cards: Option<Vec<Card>>,
+    /// Brukeren som laget kortstokken. Bare eieren ser kortstokker med eier.
+    pub owner: Option<Uuid>,
+    /// Skjult for alle til hånden ved bordet er ferdig, slik at spillerne
+    /// ikke kan se kortene til de andre
+    pub sealed: bool,
+}
+
+/// En spiller eller API-klient
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct User {
+    pub id: Uuid,
+    pub time: u64,
+    pub name: String,
+}
+
+/// En API-nøkkel. Selve nøkkelen lagres ikke, bare SHA-256 av den.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user: Uuid,
+    pub time: u64,
+    pub label: Option<String>,
+    pub key_hash: String,
+    /// Når nøkkelen ble trukket tilbake
+    pub revoked: Option<u64>,
+}
+
+/// Et spill femkorts draw på en kortstokk
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DrawGame {
+    pub deck: Uuid,
+    pub time: u64,
+    pub players: usize,
+    /// Posisjonene hver plass kastet, sortert på plass
+    pub discards: Vec<Vec<usize>>,
+}
+
+/// En lagret sammenligning. Hendene, vinnerne og det som avgjorde er JSON
+/// slik API-et returnerte dem.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub id: Uuid,
+    pub time: u64,
+    pub evaluator_version: u32,
+    pub hands: serde_json::Value,
+    pub winners: serde_json::Value,
+    pub decided_by: Option<serde_json::Value>,
+    /// Brukeren som sammenlignet hendene. `None` for anonyme klienter.
+    pub owner: Option<Uuid>,
+}
+
+/// En feil fra lagringen. Den opprinnelige feilen er tilgjengelig som
+/// `source`.
+#[derive(Debug)]
+pub struct StorageError(Box<dyn std::error::Error + Send + Sync>);
+
+impl StorageError {
+    pub fn new(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self(error.into())
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "storage error: {}", self.0)
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+impl From<sqlx::Error> for StorageError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for StorageError {
+    fn from(error: sqlx::migrate::MigrateError) -> Self {
+        Self::new(error)
+    }
+}
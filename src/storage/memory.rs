@@ -0,0 +1,471 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{
+    ApiKey, Comparison, DeckInfo, DrawGame, Event, HistoryEntry, HistoryFilter, HistoryPosition,
+    Repository, StorageError, User,
+};
+use crate::RankingCategory;
+
+/// Lagring i minnet, uten database. Alt forsvinner når den droppes, så den
+/// passer for tester og for å bygge inn API-et i andre programmer.
+#[derive(Default)]
+pub struct MemoryRepository {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    decks: HashMap<Uuid, DeckInfo>,
+    events: Vec<Event>,
+    /// Per kortstokk, posisjon og bruker
+    history: BTreeMap<(Uuid, i64, Option<Uuid>), HistoryEntry>,
+    comparisons: HashMap<Uuid, Comparison>,
+    draw_games: HashMap<Uuid, DrawGame>,
+    users: HashMap<Uuid, User>,
+    api_keys: Vec<ApiKey>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Alle hendelser, i den rekkefølgen de ble lagt til
+    pub fn events(&self) -> Vec<Event> {
+        self.state().events.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Tilstanden er gyldig selv om en annen tråd fikk panikk
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl Repository for MemoryRepository {
+    async fn create_deck(&self, deck: &DeckInfo) -> Result<(), StorageError> {
+        self.state().decks.insert(deck.id, deck.clone());
+        Ok(())
+    }
+
+    async fn deck(&self, id: Uuid) -> Result<Option<DeckInfo>, StorageError> {
+        Ok(self.state().decks.get(&id).cloned())
+    }
+
+    async fn unseal_deck(&self, id: Uuid) -> Result<(), StorageError> {
+        if let Some(deck) = self.state().decks.get_mut(&id) {
+            deck.sealed = false;
+        }
+        Ok(())
+    }
+
+    async fn record_event(&self, event: Event) -> Result<(), StorageError> {
+        self.state().events.push(event);
+        Ok(())
+    }
+
+    async fn record_view(
+        &self,
+        event: Event,
+        ranking_category: RankingCategory,
+        owner: Option<Uuid>,
+    ) -> Result<HistoryEntry, StorageError> {
+        let (Some(deck), Some(offset)) = (event.deck, event.offset) else {
+            return Err(StorageError::new("a view needs both deck and offset"));
+        };
+        let offset = offset as i64;
+
+        let mut state = self.state();
+        let views = state
+            .history
+            .get(&(deck, offset, owner))
+            .map_or(0, |entry| entry.views);
+
+        let entry = HistoryEntry {
+            deck,
+            offset,
+            time: event.time,
+            views: views + 1,
+            ranking_category: Some(ranking_category),
+            owner,
+        };
+        state.history.insert((deck, offset, owner), entry.clone());
+        state.events.push(event);
+
+        Ok(entry)
+    }
+
+    async fn history(
+        &self,
+        filter: &HistoryFilter,
+        position: HistoryPosition,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, StorageError> {
+        let state = self.state();
+        let mut entries: Vec<_> = state
+            .history
+            .values()
+            .filter(|entry| filter.matches(entry))
+            .cloned()
+            .collect();
+        entries.sort_by_key(HistoryEntry::key);
+
+        let entries = match position {
+            HistoryPosition::Skip(skip) => {
+                entries.into_iter().rev().skip(skip).take(limit).collect()
+            }
+            HistoryPosition::After(key) => entries
+                .into_iter()
+                .rev()
+                .filter(|entry| entry.key() < key)
+                .take(limit)
+                .collect(),
+            HistoryPosition::Before(key) => entries
+                .into_iter()
+                .filter(|entry| entry.key() > key)
+                .take(limit)
+                .collect(),
+        };
+
+        Ok(entries)
+    }
+
+    async fn prune_history(
+        &self,
+        before: Option<u64>,
+        keep: Option<usize>,
+    ) -> Result<u64, StorageError> {
+        let mut state = self.state();
+        let mut keys: Vec<_> = state
+            .history
+            .iter()
+            .map(|(id, entry)| (entry.key(), *id))
+            .collect();
+        keys.sort();
+        keys.reverse();
+
+        let expired: Vec<_> = keys
+            .into_iter()
+            .enumerate()
+            .filter(|(index, (key, _))| {
+                before.is_some_and(|before| key.time < before)
+                    || keep.is_some_and(|keep| *index >= keep)
+            })
+            .map(|(_, (_, id))| id)
+            .collect();
+
+        for key in &expired {
+            state.history.remove(key);
+        }
+
+        Ok(expired.len() as u64)
+    }
+
+    async fn purge_deck_history(&self, deck: Uuid) -> Result<u64, StorageError> {
+        let mut state = self.state();
+        let before = state.history.len();
+        state
+            .history
+            .retain(|(entry_deck, _, _), _| *entry_deck != deck);
+
+        Ok((before - state.history.len()) as u64)
+    }
+
+    async fn store_comparison(
+        &self,
+        comparison: &Comparison,
+        event: Event,
+    ) -> Result<(), StorageError> {
+        let mut state = self.state();
+        state.comparisons.insert(comparison.id, comparison.clone());
+        state.events.push(event);
+        Ok(())
+    }
+
+    async fn comparison(&self, id: Uuid) -> Result<Option<Comparison>, StorageError> {
+        Ok(self.state().comparisons.get(&id).cloned())
+    }
+
+    async fn create_draw_game(&self, game: &DrawGame) -> Result<bool, StorageError> {
+        let mut state = self.state();
+        if state.draw_games.contains_key(&game.deck) {
+            return Ok(false);
+        }
+        state.draw_games.insert(game.deck, game.clone());
+        Ok(true)
+    }
+
+    async fn draw_game(&self, deck: Uuid) -> Result<Option<DrawGame>, StorageError> {
+        Ok(self.state().draw_games.get(&deck).cloned())
+    }
+
+    async fn record_discard(
+        &self,
+        deck: Uuid,
+        seat: usize,
+        _time: u64,
+        positions: &[usize],
+    ) -> Result<bool, StorageError> {
+        let mut state = self.state();
+        let Some(game) = state.draw_games.get_mut(&deck) else {
+            return Err(StorageError::new("no draw game for the deck"));
+        };
+        // Plassene bytter i tur og orden, så andre plasser enn den neste har
+        // allerede byttet
+        if seat != game.discards.len() {
+            return Ok(false);
+        }
+        game.discards.push(positions.to_vec());
+        Ok(true)
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), StorageError> {
+        self.state().users.insert(user.id, user.clone());
+        Ok(())
+    }
+
+    async fn user(&self, id: Uuid) -> Result<Option<User>, StorageError> {
+        Ok(self.state().users.get(&id).cloned())
+    }
+
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), StorageError> {
+        self.state().api_keys.push(key.clone());
+        Ok(())
+    }
+
+    async fn api_keys(&self, user: Uuid) -> Result<Vec<ApiKey>, StorageError> {
+        let state = self.state();
+        Ok(state
+            .api_keys
+            .iter()
+            .filter(|key| key.user == user)
+            .cloned()
+            .collect())
+    }
+
+    async fn revoke_api_key(&self, id: Uuid, time: u64) -> Result<bool, StorageError> {
+        let mut state = self.state();
+        let key = state
+            .api_keys
+            .iter_mut()
+            .find(|key| key.id == id && key.revoked.is_none());
+
+        Ok(key.map(|key| key.revoked = Some(time)).is_some())
+    }
+
+    async fn user_by_api_key(&self, key_hash: &str) -> Result<Option<User>, StorageError> {
+        let state = self.state();
+        let user = state
+            .api_keys
+            .iter()
+            .find(|key| key.key_hash == key_hash && key.revoked.is_none())
+            .and_then(|key| state.users.get(&key.user));
+
+        Ok(user.cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventKind;
+
+    fn view(deck: Uuid, offset: usize, time: u64) -> Event {
+        Event {
+            kind: EventKind::View,
+            time,
+            deck: Some(deck),
+            offset: Some(offset),
+            user_agent: None,
+            client_ip: None,
+            payload: None,
+        }
+    }
+
+    async fn repository_with_views(deck: Uuid, count: u64) -> MemoryRepository {
+        let repository = MemoryRepository::new();
+        for time in 0..count {
+            repository
+                .record_view(
+                    view(deck, time as usize, time),
+                    RankingCategory::HighCard,
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        repository
+    }
+
+    fn times(entries: &[HistoryEntry]) -> Vec<u64> {
+        entries.iter().map(|entry| entry.time).collect()
+    }
+
+    #[tokio::test]
+    async fn test_repeated_views_update_projection() {
+        let deck = Uuid::new_v4();
+        let repository = MemoryRepository::new();
+
+        for time in [1, 2] {
+            repository
+                .record_view(view(deck, 0, time), RankingCategory::OnePair, None)
+                .await
+                .unwrap();
+        }
+
+        let history = repository
+            .history(&HistoryFilter::default(), HistoryPosition::Skip(0), 10)
+            .await
+            .unwrap();
+
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].time, 2);
+        assert_eq!(history[0].views, 2);
+        assert_eq!(repository.events().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_history_pages_in_both_directions() {
+        let deck = Uuid::new_v4();
+        let repository = repository_with_views(deck, 5).await;
+        let filter = HistoryFilter::default();
+
+        let first = repository
+            .history(&filter, HistoryPosition::Skip(0), 2)
+            .await
+            .unwrap();
+        assert_eq!(times(&first), [4, 3]);
+
+        let next = repository
+            .history(&filter, HistoryPosition::After(first[1].key()), 2)
+            .await
+            .unwrap();
+        assert_eq!(times(&next), [2, 1]);
+
+        let previous = repository
+            .history(&filter, HistoryPosition::Before(next[0].key()), 2)
+            .await
+            .unwrap();
+        assert_eq!(times(&previous), [3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_history_filters() {
+        let deck = Uuid::new_v4();
+        let repository = repository_with_views(deck, 5).await;
+        repository
+            .record_view(view(Uuid::new_v4(), 0, 10), RankingCategory::Flush, None)
+            .await
+            .unwrap();
+
+        let filter = HistoryFilter {
+            deck: Some(deck),
+            from: Some(1),
+            to: Some(4),
+            ..Default::default()
+        };
+        let history = repository
+            .history(&filter, HistoryPosition::Skip(0), 10)
+            .await
+            .unwrap();
+        assert_eq!(times(&history), [3, 2, 1]);
+
+        let filter = HistoryFilter {
+            ranking_category: Some(RankingCategory::Flush),
+            ..Default::default()
+        };
+        let history = repository
+            .history(&filter, HistoryPosition::Skip(0), 10)
+            .await
+            .unwrap();
+        assert_eq!(times(&history), [10]);
+    }
+
+    #[tokio::test]
+    async fn test_prune_history_by_age_and_rows() {
+        let deck = Uuid::new_v4();
+        let repository = repository_with_views(deck, 5).await;
+
+        assert_eq!(repository.prune_history(Some(1), None).await.unwrap(), 1);
+        assert_eq!(repository.prune_history(None, Some(2)).await.unwrap(), 2);
+
+        let history = repository
+            .history(&HistoryFilter::default(), HistoryPosition::Skip(0), 10)
+            .await
+            .unwrap();
+        assert_eq!(times(&history), [4, 3]);
+
+        assert_eq!(repository.purge_deck_history(deck).await.unwrap(), 2);
+        assert_eq!(repository.events().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_history_is_scoped_to_owner() {
+        let owner = Uuid::new_v4();
+        let repository = repository_with_views(Uuid::new_v4(), 2).await;
+        repository
+            .record_view(
+                view(Uuid::new_v4(), 0, 10),
+                RankingCategory::Flush,
+                Some(owner),
+            )
+            .await
+            .unwrap();
+
+        let filter = HistoryFilter {
+            owner: Some(owner),
+            ..Default::default()
+        };
+        let history = repository
+            .history(&filter, HistoryPosition::Skip(0), 10)
+            .await
+            .unwrap();
+        assert_eq!(times(&history), [10]);
+
+        let history = repository
+            .history(&HistoryFilter::default(), HistoryPosition::Skip(0), 10)
+            .await
+            .unwrap();
+        assert_eq!(times(&history), [1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_api_key_finds_no_user() {
+        let repository = MemoryRepository::new();
+        let user = User {
+            id: Uuid::new_v4(),
+            time: 0,
+            name: "Alice".into(),
+        };
+        let key = ApiKey {
+            id: Uuid::new_v4(),
+            user: user.id,
+            time: 0,
+            label: None,
+            key_hash: "hash".into(),
+            revoked: None,
+        };
+        repository.create_user(&user).await.unwrap();
+        repository.create_api_key(&key).await.unwrap();
+
+        assert_eq!(
+            repository.user_by_api_key("hash").await.unwrap(),
+            Some(user)
+        );
+        assert!(repository.revoke_api_key(key.id, 1).await.unwrap());
+        assert!(!repository.revoke_api_key(key.id, 2).await.unwrap());
+        assert_eq!(repository.user_by_api_key("hash").await.unwrap(), None);
+        assert_eq!(
+            repository.api_keys(key.user).await.unwrap()[0].revoked,
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,539 @@
+use async_trait::async_trait;
+use sqlx::{PgExecutor, PgPool, types::Json};
+use uuid::Uuid;
+
+use super::{
+    ApiKey, Comparison, DeckInfo, DrawGame, Event, HistoryEntry, HistoryFilter, HistoryPosition,
+    Repository, StorageError, User,
+};
+use crate::{Card, RankingCategory};
+
+/// Lagring i PostgreSQL. Skjemaet ligger i `migrations/postgres`, og
+/// spørringene sjekkes først når de kjøres.
+#[derive(Clone)]
+pub struct PostgresRepository {
+    pool: PgPool,
+}
+
+impl PostgresRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn migrate(&self) -> Result<(), StorageError> {
+        sqlx::migrate!("./migrations/postgres")
+            .run(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Repository for PostgresRepository {
+    async fn create_deck(&self, deck: &DeckInfo) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"INSERT INTO decks(id, time, seed, label, notes, cards, owner, sealed)
+               VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(deck.id)
+        .bind(deck.time as i64)
+        .bind(&deck.seed)
+        .bind(&deck.label)
+        .bind(&deck.notes)
+        .bind(deck.cards.as_ref().map(Json))
+        .bind(deck.owner)
+        .bind(deck.sealed)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn deck(&self, id: Uuid) -> Result<Option<DeckInfo>, StorageError> {
+        let row: Option<DeckRow> = sqlx::query_as(
+            r#"SELECT id, time, seed, label, notes, cards, owner, sealed
+               FROM decks
+               WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn unseal_deck(&self, id: Uuid) -> Result<(), StorageError> {
+        sqlx::query("UPDATE decks SET sealed = FALSE WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn record_event(&self, event: Event) -> Result<(), StorageError> {
+        insert_event(&self.pool, &event).await
+    }
+
+    async fn record_view(
+        &self,
+        event: Event,
+        ranking_category: RankingCategory,
+        owner: Option<Uuid>,
+    ) -> Result<HistoryEntry, StorageError> {
+        let (Some(deck), Some(offset)) = (event.deck, event.offset) else {
+            return Err(StorageError::new("a view needs both deck and offset"));
+        };
+        let mut transaction = self.pool.begin().await?;
+
+        insert_event(&mut *transaction, &event).await?;
+
+        let views: i64 = sqlx::query_scalar(
+            r#"INSERT INTO history(deck, "offset", time, ranking_category, owner)
+               VALUES ($1, $2, $3, $4, $5)
+               ON CONFLICT (deck, "offset", COALESCE(owner, '00000000-0000-0000-0000-000000000000'))
+               DO UPDATE
+               SET time = excluded.time,
+                   ranking_category = excluded.ranking_category,
+                   views = history.views + 1
+               RETURNING views
+            "#,
+        )
+        .bind(deck)
+        .bind(offset as i64)
+        .bind(event.time as i64)
+        .bind(ranking_category.as_str())
+        .bind(owner)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(HistoryEntry {
+            deck,
+            offset: offset as i64,
+            time: event.time,
+            views,
+            ranking_category: Some(ranking_category),
+            owner,
+        })
+    }
+
+    async fn history(
+        &self,
+        filter: &HistoryFilter,
+        position: HistoryPosition,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, StorageError> {
+        let (key, skip) = match position {
+            HistoryPosition::Skip(skip) => (None, skip as i64),
+            HistoryPosition::After(key) | HistoryPosition::Before(key) => (Some(key), 0),
+        };
+
+        let query = match position {
+            HistoryPosition::Before(_) => {
+                r#"SELECT deck, "offset", time, ranking_category, views, owner
+                   FROM history
+                   WHERE owner IS NOT DISTINCT FROM $11
+                     AND ($1::uuid IS NULL OR deck = $1)
+                     AND ($2::bigint IS NULL OR time >= $2)
+                     AND ($3::bigint IS NULL OR time < $3)
+                     AND ($4::bigint IS NULL OR "offset" = $4)
+                     AND ($5::text IS NULL OR ranking_category = $5)
+                     AND (time, deck, "offset") > ($7, $8, $9)
+                   ORDER BY time ASC, deck ASC, "offset" ASC
+                   LIMIT $6 OFFSET $10
+                "#
+            }
+            HistoryPosition::After(_) | HistoryPosition::Skip(_) => {
+                r#"SELECT deck, "offset", time, ranking_category, views, owner
+                   FROM history
+                   WHERE owner IS NOT DISTINCT FROM $11
+                     AND ($1::uuid IS NULL OR deck = $1)
+                     AND ($2::bigint IS NULL OR time >= $2)
+                     AND ($3::bigint IS NULL OR time < $3)
+                     AND ($4::bigint IS NULL OR "offset" = $4)
+                     AND ($5::text IS NULL OR ranking_category = $5)
+                     AND ($7::bigint IS NULL OR (time, deck, "offset") < ($7, $8, $9))
+                   ORDER BY time DESC, deck DESC, "offset" DESC
+                   LIMIT $6 OFFSET $10
+                "#
+            }
+        };
+
+        let rows: Vec<HistoryRow> = sqlx::query_as(query)
+            .bind(filter.deck)
+            .bind(filter.from.map(|time| time as i64))
+            .bind(filter.to.map(|time| time as i64))
+            .bind(filter.hand_offset.map(|offset| offset as i64))
+            .bind(filter.ranking_category.map(|category| category.as_str()))
+            .bind(limit as i64)
+            .bind(key.map(|key| key.time as i64))
+            .bind(key.map(|key| key.deck))
+            .bind(key.map(|key| key.offset))
+            .bind(skip)
+            .bind(filter.owner)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn prune_history(
+        &self,
+        before: Option<u64>,
+        keep: Option<usize>,
+    ) -> Result<u64, StorageError> {
+        let result = sqlx::query(
+            r#"DELETE FROM history
+               WHERE time < $1
+                  OR ($2::bigint IS NOT NULL AND ctid NOT IN (
+                        SELECT ctid
+                        FROM history
+                        ORDER BY time DESC, deck DESC, "offset" DESC
+                        LIMIT $2
+                      ))
+            "#,
+        )
+        .bind(before.map(|time| time as i64))
+        .bind(keep.map(|keep| keep as i64))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn purge_deck_history(&self, deck: Uuid) -> Result<u64, StorageError> {
+        let result = sqlx::query("DELETE FROM history WHERE deck = $1")
+            .bind(deck)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn store_comparison(
+        &self,
+        comparison: &Comparison,
+        event: Event,
+    ) -> Result<(), StorageError> {
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"INSERT INTO comparisons(id, time, evaluator_version, hands, winners, decided_by,
+                                       owner)
+               VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(comparison.id)
+        .bind(comparison.time as i64)
+        .bind(comparison.evaluator_version as i32)
+        .bind(&comparison.hands)
+        .bind(&comparison.winners)
+        .bind(&comparison.decided_by)
+        .bind(comparison.owner)
+        .execute(&mut *transaction)
+        .await?;
+
+        insert_event(&mut *transaction, &event).await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn comparison(&self, id: Uuid) -> Result<Option<Comparison>, StorageError> {
+        let row: Option<ComparisonRow> = sqlx::query_as(
+            r#"SELECT id, time, evaluator_version, hands, winners, decided_by, owner
+               FROM comparisons
+               WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn create_draw_game(&self, game: &DrawGame) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"INSERT INTO draw_games(deck, time, players)
+               VALUES ($1, $2, $3)
+               ON CONFLICT (deck) DO NOTHING
+            "#,
+        )
+        .bind(game.deck)
+        .bind(game.time as i64)
+        .bind(game.players as i32)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn draw_game(&self, deck: Uuid) -> Result<Option<DrawGame>, StorageError> {
+        let row: Option<(i64, i32)> =
+            sqlx::query_as("SELECT time, players FROM draw_games WHERE deck = $1")
+                .bind(deck)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        let Some((time, players)) = row else {
+            return Ok(None);
+        };
+
+        let discards: Vec<(Json<Vec<usize>>,)> =
+            sqlx::query_as("SELECT positions FROM draw_discards WHERE deck = $1 ORDER BY seat")
+                .bind(deck)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(Some(DrawGame {
+            deck,
+            time: time as u64,
+            players: players as usize,
+            discards: discards
+                .into_iter()
+                .map(|(Json(positions),)| positions)
+                .collect(),
+        }))
+    }
+
+    async fn record_discard(
+        &self,
+        deck: Uuid,
+        seat: usize,
+        time: u64,
+        positions: &[usize],
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"INSERT INTO draw_discards(deck, seat, time, positions)
+               VALUES ($1, $2, $3, $4)
+               ON CONFLICT (deck, seat) DO NOTHING
+            "#,
+        )
+        .bind(deck)
+        .bind(seat as i32)
+        .bind(time as i64)
+        .bind(Json(positions))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), StorageError> {
+        sqlx::query("INSERT INTO users(id, time, name) VALUES ($1, $2, $3)")
+            .bind(user.id)
+            .bind(user.time as i64)
+            .bind(&user.name)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn user(&self, id: Uuid) -> Result<Option<User>, StorageError> {
+        let row: Option<UserRow> = sqlx::query_as("SELECT id, time, name FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"INSERT INTO api_keys(id, user_id, time, label, key_hash, revoked)
+               VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(key.id)
+        .bind(key.user)
+        .bind(key.time as i64)
+        .bind(&key.label)
+        .bind(&key.key_hash)
+        .bind(key.revoked.map(|time| time as i64))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn api_keys(&self, user: Uuid) -> Result<Vec<ApiKey>, StorageError> {
+        let rows: Vec<ApiKeyRow> = sqlx::query_as(
+            r#"SELECT id, user_id, time, label, key_hash, revoked
+               FROM api_keys
+               WHERE user_id = $1
+               ORDER BY time, id
+            "#,
+        )
+        .bind(user)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn revoke_api_key(&self, id: Uuid, time: u64) -> Result<bool, StorageError> {
+        let result =
+            sqlx::query("UPDATE api_keys SET revoked = $1 WHERE id = $2 AND revoked IS NULL")
+                .bind(time as i64)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn user_by_api_key(&self, key_hash: &str) -> Result<Option<User>, StorageError> {
+        let row: Option<UserRow> = sqlx::query_as(
+            r#"SELECT users.id, users.time, users.name
+               FROM api_keys
+               JOIN users ON users.id = api_keys.user_id
+               WHERE api_keys.key_hash = $1 AND api_keys.revoked IS NULL
+            "#,
+        )
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Into::into))
+    }
+}
+
+async fn insert_event(executor: impl PgExecutor<'_>, event: &Event) -> Result<(), StorageError> {
+    sqlx::query(
+        r#"INSERT INTO events(kind, time, deck, "offset", user_agent, client_ip, payload)
+           VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(event.kind.as_str())
+    .bind(event.time as i64)
+    .bind(event.deck)
+    .bind(event.offset.map(|offset| offset as i64))
+    .bind(&event.user_agent)
+    .bind(&event.client_ip)
+    .bind(&event.payload)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(sqlx::FromRow)]
+struct DeckRow {
+    id: Uuid,
+    time: i64,
+    seed: Option<String>,
+    label: Option<String>,
+    notes: Option<String>,
+    cards: Option<Json<Vec<Card>>>,
+    owner: Option<Uuid>,
+    sealed: bool,
+}
+
+impl From<DeckRow> for DeckInfo {
+    fn from(row: DeckRow) -> Self {
+        Self {
+            id: row.id,
+            time: row.time as u64,
+            seed: row.seed,
+            label: row.label,
+            notes: row.notes,
+            cards: row.cards.map(|Json(cards)| cards),
+            owner: row.owner,
+            sealed: row.sealed,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct HistoryRow {
+    deck: Uuid,
+    offset: i64,
+    time: i64,
+    ranking_category: Option<String>,
+    views: i64,
+    owner: Option<Uuid>,
+}
+
+impl From<HistoryRow> for HistoryEntry {
+    fn from(row: HistoryRow) -> Self {
+        Self {
+            deck: row.deck,
+            offset: row.offset,
+            time: row.time as u64,
+            views: row.views,
+            ranking_category: row.ranking_category.and_then(|c| c.parse().ok()),
+            owner: row.owner,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ComparisonRow {
+    id: Uuid,
+    time: i64,
+    evaluator_version: i32,
+    hands: serde_json::Value,
+    winners: serde_json::Value,
+    decided_by: Option<serde_json::Value>,
+    owner: Option<Uuid>,
+}
+
+impl From<ComparisonRow> for Comparison {
+    fn from(row: ComparisonRow) -> Self {
+        Self {
+            id: row.id,
+            time: row.time as u64,
+            evaluator_version: row.evaluator_version as u32,
+            hands: row.hands,
+            winners: row.winners,
+            decided_by: row.decided_by,
+            owner: row.owner,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    id: Uuid,
+    time: i64,
+    name: String,
+}
+
+impl From<UserRow> for User {
+    fn from(row: UserRow) -> Self {
+        Self {
+            id: row.id,
+            time: row.time as u64,
+            name: row.name,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ApiKeyRow {
+    id: Uuid,
+    user_id: Uuid,
+    time: i64,
+    label: Option<String>,
+    key_hash: String,
+    revoked: Option<i64>,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(row: ApiKeyRow) -> Self {
+        Self {
+            id: row.id,
+            user: row.user_id,
+            time: row.time as u64,
+            label: row.label,
+            key_hash: row.key_hash,
+            revoked: row.revoked.map(|time| time as u64),
+        }
+    }
+}
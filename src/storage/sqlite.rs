@@ -0,0 +1,587 @@
+use async_trait::async_trait;
+use sqlx::{SqliteExecutor, SqlitePool};
+use uuid::Uuid;
+
+use super::{
+    ApiKey, Comparison, DeckInfo, DrawGame, Event, HistoryEntry, HistoryFilter, HistoryPosition,
+    Repository, StorageError, User,
+};
+use crate::RankingCategory;
+
+/// Lagring i SQLite. Spørringene sjekkes mot `migrations` ved kompilering.
+#[derive(Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn migrate(&self) -> Result<(), StorageError> {
+        sqlx::migrate!("./migrations").run(&self.pool).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Repository for SqliteRepository {
+    async fn create_deck(&self, deck: &DeckInfo) -> Result<(), StorageError> {
+        let time = deck.time as i64;
+        let cards = deck
+            .cards
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(StorageError::new)?;
+
+        sqlx::query!(
+            r#"INSERT INTO decks(id, time, seed, label, notes, cards, owner, sealed)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            deck.id,
+            time,
+            deck.seed,
+            deck.label,
+            deck.notes,
+            cards,
+            deck.owner,
+            deck.sealed,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn deck(&self, id: Uuid) -> Result<Option<DeckInfo>, StorageError> {
+        let row = sqlx::query!(
+            r#"SELECT time as "time!: u64", seed, label, notes, cards, owner as "owner: Uuid",
+                      sealed
+               FROM decks
+               WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(DeckInfo {
+            id,
+            time: row.time,
+            seed: row.seed,
+            label: row.label,
+            notes: row.notes,
+            cards: row
+                .cards
+                .map(|cards| serde_json::from_str(&cards))
+                .transpose()
+                .map_err(StorageError::new)?,
+            owner: row.owner,
+            sealed: row.sealed,
+        }))
+    }
+
+    async fn unseal_deck(&self, id: Uuid) -> Result<(), StorageError> {
+        sqlx::query!("UPDATE decks SET sealed = FALSE WHERE id = ?", id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn record_event(&self, event: Event) -> Result<(), StorageError> {
+        insert_event(&self.pool, &EventRow::from(event)).await
+    }
+
+    async fn record_view(
+        &self,
+        event: Event,
+        ranking_category: RankingCategory,
+        owner: Option<Uuid>,
+    ) -> Result<HistoryEntry, StorageError> {
+        let row = EventRow::from(event);
+        let (Some(deck), Some(offset)) = (row.deck, row.offset) else {
+            return Err(StorageError::new("a view needs both deck and offset"));
+        };
+        let ranking_category_name = ranking_category.as_str();
+
+        let mut transaction = self.pool.begin().await?;
+
+        insert_event(&mut *transaction, &row).await?;
+
+        let views = sqlx::query_scalar!(
+            r#"INSERT INTO history(deck, offset, time, ranking_category, owner)
+               VALUES (?, ?, ?, ?, ?)
+               ON CONFLICT (deck, offset, ifnull(owner, 0)) DO UPDATE
+               SET time = excluded.time,
+                   ranking_category = excluded.ranking_category,
+                   views = history.views + 1
+               RETURNING views
+            "#,
+            deck,
+            offset,
+            row.time,
+            ranking_category_name,
+            owner,
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(HistoryEntry {
+            deck,
+            offset,
+            time: row.time as u64,
+            views,
+            ranking_category: Some(ranking_category),
+            owner,
+        })
+    }
+
+    async fn history(
+        &self,
+        filter: &HistoryFilter,
+        position: HistoryPosition,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, StorageError> {
+        let limit = limit as i64;
+        let from = filter.from.map(|time| time as i64);
+        let to = filter.to.map(|time| time as i64);
+        let hand_offset = filter.hand_offset.map(|offset| offset as i64);
+        let ranking_category = filter.ranking_category.map(|category| category.as_str());
+        let (key, skip) = match position {
+            HistoryPosition::Skip(skip) => (None, skip as i64),
+            HistoryPosition::After(key) | HistoryPosition::Before(key) => (Some(key), 0),
+        };
+        let time = key.map(|key| key.time as i64);
+        let deck = key.map(|key| key.deck);
+        let offset = key.map(|key| key.offset);
+
+        let rows =
+            match position {
+                HistoryPosition::Before(_) => sqlx::query_as!(
+                    HistoryRow,
+                    r#"SELECT offset, time as "time!: u64", deck as "deck!: Uuid", ranking_category,
+                              views, owner as "owner: Uuid"
+                       FROM history
+                       WHERE owner IS ?10
+                         AND (?1 IS NULL OR deck = ?1)
+                         AND (?2 IS NULL OR time >= ?2)
+                         AND (?3 IS NULL OR time < ?3)
+                         AND (?4 IS NULL OR offset = ?4)
+                         AND (?5 IS NULL OR ranking_category = ?5)
+                         AND (time, deck, offset) > (?7, ?8, ?9)
+                       ORDER BY time ASC, deck ASC, offset ASC
+                       LIMIT ?6
+                    "#,
+                    filter.deck,
+                    from,
+                    to,
+                    hand_offset,
+                    ranking_category,
+                    limit,
+                    time,
+                    deck,
+                    offset,
+                    filter.owner,
+                )
+                .fetch_all(&self.pool)
+                .await?,
+                HistoryPosition::After(_) | HistoryPosition::Skip(_) => sqlx::query_as!(
+                    HistoryRow,
+                    r#"SELECT offset, time as "time!: u64", deck as "deck!: Uuid", ranking_category,
+                              views, owner as "owner: Uuid"
+                       FROM history
+                       WHERE owner IS ?11
+                         AND (?1 IS NULL OR deck = ?1)
+                         AND (?2 IS NULL OR time >= ?2)
+                         AND (?3 IS NULL OR time < ?3)
+                         AND (?4 IS NULL OR offset = ?4)
+                         AND (?5 IS NULL OR ranking_category = ?5)
+                         AND (?7 IS NULL OR (time, deck, offset) < (?7, ?8, ?9))
+                       ORDER BY time DESC, deck DESC, offset DESC
+                       LIMIT ?6 OFFSET ?10
+                    "#,
+                    filter.deck,
+                    from,
+                    to,
+                    hand_offset,
+                    ranking_category,
+                    limit,
+                    time,
+                    deck,
+                    offset,
+                    skip,
+                    filter.owner,
+                )
+                .fetch_all(&self.pool)
+                .await?,
+            };
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn prune_history(
+        &self,
+        before: Option<u64>,
+        keep: Option<usize>,
+    ) -> Result<u64, StorageError> {
+        let before = before.map(|time| time as i64);
+        let keep = keep.map(|keep| keep as i64);
+
+        let result = sqlx::query!(
+            r#"DELETE FROM history
+               WHERE time < ?1
+                  OR (?2 IS NOT NULL AND rowid NOT IN (
+                        SELECT rowid
+                        FROM history
+                        ORDER BY time DESC, deck DESC, offset DESC
+                        LIMIT ?2
+                      ))
+            "#,
+            before,
+            keep,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn purge_deck_history(&self, deck: Uuid) -> Result<u64, StorageError> {
+        let result = sqlx::query!("DELETE FROM history WHERE deck = ?", deck)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn store_comparison(
+        &self,
+        comparison: &Comparison,
+        event: Event,
+    ) -> Result<(), StorageError> {
+        let time = comparison.time as i64;
+        let hands = comparison.hands.to_string();
+        let winners = comparison.winners.to_string();
+        let decided_by = comparison.decided_by.as_ref().map(|d| d.to_string());
+
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"INSERT INTO comparisons(id, time, evaluator_version, hands, winners, decided_by,
+                                       owner)
+               VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            comparison.id,
+            time,
+            comparison.evaluator_version,
+            hands,
+            winners,
+            decided_by,
+            comparison.owner,
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        insert_event(&mut *transaction, &EventRow::from(event)).await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn comparison(&self, id: Uuid) -> Result<Option<Comparison>, StorageError> {
+        let row = sqlx::query!(
+            r#"SELECT time as "time!: u64", evaluator_version as "evaluator_version!: u32",
+                      hands, winners, decided_by, owner as "owner: Uuid"
+               FROM comparisons
+               WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(Comparison {
+            id,
+            time: row.time,
+            evaluator_version: row.evaluator_version,
+            hands: serde_json::from_str(&row.hands).map_err(StorageError::new)?,
+            winners: serde_json::from_str(&row.winners).map_err(StorageError::new)?,
+            decided_by: row
+                .decided_by
+                .map(|decided_by| serde_json::from_str(&decided_by))
+                .transpose()
+                .map_err(StorageError::new)?,
+            owner: row.owner,
+        }))
+    }
+
+    async fn create_draw_game(&self, game: &DrawGame) -> Result<bool, StorageError> {
+        let time = game.time as i64;
+        let players = game.players as i64;
+
+        let result = sqlx::query!(
+            r#"INSERT INTO draw_games(deck, time, players)
+               VALUES (?, ?, ?)
+               ON CONFLICT (deck) DO NOTHING
+            "#,
+            game.deck,
+            time,
+            players,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn draw_game(&self, deck: Uuid) -> Result<Option<DrawGame>, StorageError> {
+        let row = sqlx::query!(
+            r#"SELECT time as "time!: u64", players as "players!: u32"
+               FROM draw_games
+               WHERE deck = ?
+            "#,
+            deck
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let discards = sqlx::query_as!(
+            DiscardRow,
+            r#"SELECT positions
+               FROM draw_discards
+               WHERE deck = ?
+               ORDER BY seat
+            "#,
+            deck
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(Some(DrawGame {
+            deck,
+            time: row.time,
+            players: row.players as usize,
+            discards: discards
+                .into_iter()
+                .map(|row| serde_json::from_str(&row.positions))
+                .collect::<Result<_, _>>()
+                .map_err(StorageError::new)?,
+        }))
+    }
+
+    async fn record_discard(
+        &self,
+        deck: Uuid,
+        seat: usize,
+        time: u64,
+        positions: &[usize],
+    ) -> Result<bool, StorageError> {
+        let seat = seat as i64;
+        let time = time as i64;
+        let positions = serde_json::to_string(positions).map_err(StorageError::new)?;
+
+        let result = sqlx::query!(
+            r#"INSERT INTO draw_discards(deck, seat, time, positions)
+               VALUES (?, ?, ?, ?)
+               ON CONFLICT (deck, seat) DO NOTHING
+            "#,
+            deck,
+            seat,
+            time,
+            positions,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), StorageError> {
+        let time = user.time as i64;
+
+        sqlx::query!(
+            "INSERT INTO users(id, time, name) VALUES (?, ?, ?)",
+            user.id,
+            time,
+            user.name,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn user(&self, id: Uuid) -> Result<Option<User>, StorageError> {
+        let row = sqlx::query!(
+            r#"SELECT time as "time!: u64", name FROM users WHERE id = ?"#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| User {
+            id,
+            time: row.time,
+            name: row.name,
+        }))
+    }
+
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), StorageError> {
+        let time = key.time as i64;
+        let revoked = key.revoked.map(|time| time as i64);
+
+        sqlx::query!(
+            r#"INSERT INTO api_keys(id, user_id, time, label, key_hash, revoked)
+               VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            key.id,
+            key.user,
+            time,
+            key.label,
+            key.key_hash,
+            revoked,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn api_keys(&self, user: Uuid) -> Result<Vec<ApiKey>, StorageError> {
+        let keys = sqlx::query_as!(
+            ApiKey,
+            r#"SELECT id as "id!: Uuid", user_id as "user!: Uuid", time as "time!: u64",
+                      label, key_hash, revoked as "revoked: u64"
+               FROM api_keys
+               WHERE user_id = ?
+               ORDER BY time, id
+            "#,
+            user
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(keys)
+    }
+
+    async fn revoke_api_key(&self, id: Uuid, time: u64) -> Result<bool, StorageError> {
+        let time = time as i64;
+
+        let result = sqlx::query!(
+            "UPDATE api_keys SET revoked = ? WHERE id = ? AND revoked IS NULL",
+            time,
+            id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn user_by_api_key(&self, key_hash: &str) -> Result<Option<User>, StorageError> {
+        let user = sqlx::query_as!(
+            User,
+            r#"SELECT users.id as "id!: Uuid", users.time as "time!: u64", users.name
+               FROM api_keys
+               JOIN users ON users.id = api_keys.user_id
+               WHERE api_keys.key_hash = ? AND api_keys.revoked IS NULL
+            "#,
+            key_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user)
+    }
+}
+
+/// Kastene til en plass slik de lagres
+struct DiscardRow {
+    positions: String,
+}
+
+async fn insert_event(
+    executor: impl SqliteExecutor<'_>,
+    row: &EventRow,
+) -> Result<(), StorageError> {
+    sqlx::query!(
+        r#"INSERT INTO events(kind, time, deck, offset, user_agent, client_ip, payload)
+           VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        row.kind,
+        row.time,
+        row.deck,
+        row.offset,
+        row.user_agent,
+        row.client_ip,
+        row.payload,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// En hendelse med verdiene slik de lagres
+struct EventRow {
+    kind: &'static str,
+    time: i64,
+    deck: Option<Uuid>,
+    offset: Option<i64>,
+    user_agent: Option<String>,
+    client_ip: Option<String>,
+    payload: Option<String>,
+}
+
+impl From<Event> for EventRow {
+    fn from(event: Event) -> Self {
+        Self {
+            kind: event.kind.as_str(),
+            time: event.time as i64,
+            deck: event.deck,
+            offset: event.offset.map(|offset| offset as i64),
+            user_agent: event.user_agent,
+            client_ip: event.client_ip,
+            payload: event.payload.map(|payload| payload.to_string()),
+        }
+    }
+}
+
+struct HistoryRow {
+    offset: i64,
+    time: u64,
+    deck: Uuid,
+    ranking_category: Option<String>,
+    views: i64,
+    owner: Option<Uuid>,
+}
+
+impl From<HistoryRow> for HistoryEntry {
+    fn from(row: HistoryRow) -> Self {
+        Self {
+            deck: row.deck,
+            offset: row.offset,
+            time: row.time,
+            views: row.views,
+            ranking_category: row.ranking_category.and_then(|c| c.parse().ok()),
+            owner: row.owner,
+        }
+    }
+}
@@ -0,0 +1,506 @@
+//! Bord som spilles i sanntid. Hvert bord eies av én oppgave som tar imot
+//! kommandoer over en kanal og sender hendelser til plassene, slik at
+//! tilstanden aldri deles mellom tråder. Hver hånd spilles med en ny lagret
+//! kortstokk, så den kan spilles av igjen med `/api/v1/decks/{id}`.
+//! Kortstokken er skjult og id-en holdes tilbake til hånden er ferdig, ellers
+//! kunne spillerne sett kortene til hverandre.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+use crate::{
+    Card, DeckInfo, DeckProvider, Event, EventKind, Repository,
+    holdem::game::{Action, Game, GameError, HandResult, MAX_SEATS, TableView},
+};
+
+/// Hendelser som sendes til en plass
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TableEvent {
+    /// Fra 1
+    Joined {
+        table: Uuid,
+        seat: usize,
+    },
+    /// `deck` er kortstokken til forrige ferdige hånd
+    State {
+        deck: Option<Uuid>,
+        #[serde(flatten)]
+        table: TableView,
+    },
+    /// Sendes bare til plassen kortene tilhører
+    HoleCards {
+        cards: [Card; 2],
+    },
+    /// `deck` er kortstokken hånden ble spilt med
+    HandFinished {
+        deck: Uuid,
+        #[serde(flatten)]
+        result: HandResult,
+    },
+    Error {
+        code: &'static str,
+        detail: String,
+    },
+}
+
+impl TableEvent {
+    fn error(error: TableError) -> Self {
+        TableEvent::Error {
+            code: error.code(),
+            detail: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TableError {
+    Game(GameError),
+    /// Kortstokken til hånden kunne ikke lagres
+    Storage,
+}
+
+impl TableError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TableError::Game(error) => error.code(),
+            TableError::Storage => "database_error",
+        }
+    }
+}
+
+impl std::fmt::Display for TableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableError::Game(error) => error.fmt(f),
+            TableError::Storage => f.write_str("the deck for the hand could not be stored"),
+        }
+    }
+}
+
+impl From<GameError> for TableError {
+    fn from(error: GameError) -> Self {
+        TableError::Game(error)
+    }
+}
+
+/// Innstillinger for et nytt bord
+#[derive(Clone, Copy, Debug)]
+pub struct TableOptions {
+    pub small_blind: u64,
+    pub big_blind: u64,
+    /// Sjetongene hver spiller får når den setter seg
+    pub stack: u64,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            small_blind: 1,
+            big_blind: 2,
+            stack: 200,
+        }
+    }
+}
+
+/// Grenser for bordene, siden hvem som helst kan lage et
+#[derive(Clone, Copy, Debug)]
+pub struct TableLimits {
+    pub max_tables: usize,
+    /// Et bord uten spillere legges ned etter så lang tid
+    pub idle_timeout: Duration,
+}
+
+impl Default for TableLimits {
+    fn default() -> Self {
+        Self {
+            max_tables: 1000,
+            idle_timeout: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Plassen en spiller fikk og hendelsene til den
+type Seated = (usize, mpsc::UnboundedReceiver<TableEvent>);
+
+enum Command {
+    Join {
+        name: String,
+        reply: oneshot::Sender<Result<Seated, GameError>>,
+    },
+    Start {
+        seat: usize,
+    },
+    Act {
+        seat: usize,
+        action: Action,
+    },
+    Leave {
+        seat: usize,
+    },
+}
+
+/// Alle bordene som er i gang
+pub struct Tables {
+    tables: Mutex<HashMap<Uuid, TableHandle>>,
+    deck_provider: Arc<DeckProvider>,
+    repository: Arc<dyn Repository>,
+    limits: TableLimits,
+}
+
+impl Tables {
+    pub fn new(
+        deck_provider: Arc<DeckProvider>,
+        repository: Arc<dyn Repository>,
+        limits: TableLimits,
+    ) -> Self {
+        Self {
+            tables: Mutex::default(),
+            deck_provider,
+            repository,
+            limits,
+        }
+    }
+
+    /// Lager et bord og starter oppgaven som eier det. Bordet fjernes når
+    /// siste spiller går, eller når ingen har sittet ved det på en stund.
+    /// `None` når det allerede er `max_tables` bord.
+    pub fn create(self: &Arc<Self>, options: TableOptions) -> Option<Uuid> {
+        let id = Uuid::new_v4();
+        let (commands, receiver) = mpsc::unbounded_channel();
+        {
+            let mut tables = self.lock();
+            if tables.len() >= self.limits.max_tables {
+                return None;
+            }
+            tables.insert(id, TableHandle { commands });
+        }
+
+        let task = TableTask {
+            id,
+            game: Game::new(options.small_blind, options.big_blind),
+            stack: options.stack,
+            seats: vec![None; MAX_SEATS],
+            deck: None,
+            hand_deck: None,
+            tables: self.clone(),
+        };
+        tokio::spawn(task.run(receiver));
+
+        Some(id)
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<TableHandle> {
+        self.lock().get(&id).cloned()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, TableHandle>> {
+        self.tables
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+#[derive(Clone)]
+pub struct TableHandle {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl TableHandle {
+    /// Setter en spiller ved bordet. `None` når bordet er lagt ned.
+    pub async fn join(&self, name: String) -> Option<Result<SeatHandle, GameError>> {
+        let (reply, response) = oneshot::channel();
+        self.commands.send(Command::Join { name, reply }).ok()?;
+        let seated = response.await.ok()?;
+
+        Some(seated.map(|(seat, events)| SeatHandle {
+            seat,
+            events,
+            commands: self.commands.clone(),
+        }))
+    }
+}
+
+/// En plass ved et bord. Hendelsene til plassen kommer i `events`.
+pub struct SeatHandle {
+    pub seat: usize,
+    pub events: mpsc::UnboundedReceiver<TableEvent>,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl SeatHandle {
+    pub fn start(&self) {
+        let _ = self.commands.send(Command::Start { seat: self.seat });
+    }
+
+    pub fn act(&self, action: Action) {
+        let _ = self.commands.send(Command::Act {
+            seat: self.seat,
+            action,
+        });
+    }
+}
+
+impl Drop for SeatHandle {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Leave { seat: self.seat });
+    }
+}
+
+struct TableTask {
+    id: Uuid,
+    game: Game,
+    stack: u64,
+    seats: Vec<Option<mpsc::UnboundedSender<TableEvent>>>,
+    /// Kortstokken til forrige ferdige hånd
+    deck: Option<Uuid>,
+    /// Den skjulte kortstokken til hånden som spilles
+    hand_deck: Option<Uuid>,
+    tables: Arc<Tables>,
+}
+
+impl TableTask {
+    async fn run(mut self, mut receiver: mpsc::UnboundedReceiver<Command>) {
+        loop {
+            let command = if self.seats.iter().all(Option::is_none) {
+                let idle_timeout = self.tables.limits.idle_timeout;
+                match tokio::time::timeout(idle_timeout, receiver.recv()).await {
+                    Ok(command) => command,
+                    // Ingen har satt seg ved bordet
+                    Err(_) => break,
+                }
+            } else {
+                receiver.recv().await
+            };
+            let Some(command) = command else { break };
+
+            match command {
+                Command::Join { name, reply } => self.join(name, reply),
+                Command::Start { seat } => {
+                    if let Err(error) = self.start().await {
+                        self.send(seat, TableEvent::error(error));
+                    }
+                }
+                Command::Act { seat, action } => match self.game.act(seat, action) {
+                    Ok(result) => self.update(result).await,
+                    Err(error) => self.send(seat, TableEvent::error(error.into())),
+                },
+                Command::Leave { seat } => {
+                    self.seats[seat] = None;
+                    let result = self.game.leave(seat);
+                    if self.seats.iter().all(Option::is_none) {
+                        break;
+                    }
+                    self.update(result).await;
+                }
+            }
+        }
+
+        // Ingen spiller lenger hånden, så kortstokken kan vises
+        if let Some(deck) = self.hand_deck.take() {
+            self.unseal(deck).await;
+        }
+        self.tables.lock().remove(&self.id);
+        tracing::debug!(table = %self.id, "closed table");
+    }
+
+    fn join(&mut self, name: String, reply: oneshot::Sender<Result<Seated, GameError>>) {
+        let seat = match self.game.sit(name, self.stack) {
+            Ok(seat) => seat,
+            Err(error) => {
+                let _ = reply.send(Err(error));
+                return;
+            }
+        };
+
+        let (events, receiver) = mpsc::unbounded_channel();
+        // Klienten kan ha gått før den fikk plassen
+        if reply.send(Ok((seat, receiver))).is_err() {
+            self.game.leave(seat);
+            return;
+        }
+        self.seats[seat] = Some(events);
+
+        self.send(
+            seat,
+            TableEvent::Joined {
+                table: self.id,
+                seat: seat + 1,
+            },
+        );
+        self.broadcast_state();
+    }
+
+    async fn start(&mut self) -> Result<(), TableError> {
+        self.game.can_start()?;
+
+        let tables = &self.tables;
+        let deck = DeckInfo {
+            id: Uuid::new_v4(),
+            time: now(),
+            seed: None,
+            label: Some(format!("table {}", self.id)),
+            notes: None,
+            cards: None,
+            owner: None,
+            sealed: true,
+        };
+        tables
+            .repository
+            .create_deck(&deck)
+            .await
+            .map_err(|error| {
+                tracing::error!(%error, table = %self.id, "failed to store deck");
+                TableError::Storage
+            })?;
+        let event = Event {
+            kind: EventKind::Deal,
+            time: deck.time,
+            deck: Some(deck.id),
+            offset: None,
+            user_agent: None,
+            client_ip: None,
+            payload: Some(serde_json::json!({ "variant": "table", "table": self.id })),
+        };
+        tables
+            .repository
+            .record_event(event)
+            .await
+            .map_err(|error| {
+                tracing::error!(%error, table = %self.id, "failed to record deal");
+                TableError::Storage
+            })?;
+
+        // Som kortstokker uten seed i API-et
+        let cards = tables.deck_provider.get_with_seed(deck.id);
+        let result = self.game.start_hand(cards)?;
+        self.hand_deck = Some(deck.id);
+
+        for seat in self.game.seats_in_hand() {
+            if let Some(cards) = self.game.hole_cards(seat) {
+                let cards = cards.clone();
+                self.send(seat, TableEvent::HoleCards { cards });
+            }
+        }
+        self.update(result).await;
+
+        Ok(())
+    }
+
+    /// Sender tilstanden til alle, og resultatet når en hånd er ferdig.
+    /// Kortstokken til hånden vises først når den er ferdig.
+    async fn update(&mut self, result: Option<HandResult>) {
+        if let Some(result) = result
+            && let Some(deck) = self.hand_deck.take()
+        {
+            self.unseal(deck).await;
+            self.deck = Some(deck);
+            self.broadcast(TableEvent::HandFinished { deck, result });
+        }
+        self.broadcast_state();
+    }
+
+    fn broadcast_state(&self) {
+        self.broadcast(TableEvent::State {
+            deck: self.deck,
+            table: self.game.view(),
+        });
+    }
+
+    async fn unseal(&self, deck: Uuid) {
+        if let Err(error) = self.tables.repository.unseal_deck(deck).await {
+            tracing::error!(%error, table = %self.id, %deck, "failed to unseal deck");
+        }
+    }
+
+    fn broadcast(&self, event: TableEvent) {
+        for seat in self.seats.iter().flatten() {
+            let _ = seat.send(event.clone());
+        }
+    }
+
+    fn send(&self, seat: usize, event: TableEvent) {
+        if let Some(Some(events)) = self.seats.get(seat) {
+            let _ = events.send(event);
+        }
+    }
+}
+
+/// Millisekunder siden epoch
+fn now() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryRepository;
+
+    fn tables(limits: TableLimits) -> Arc<Tables> {
+        Arc::new(Tables::new(
+            Default::default(),
+            Arc::new(MemoryRepository::new()),
+            limits,
+        ))
+    }
+
+    /// Venter til oppgaven har lagt ned bordet
+    async fn closed(tables: &Tables, id: Uuid) -> bool {
+        for _ in 0..100 {
+            if tables.get(id).is_none() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_table_nobody_joins_is_closed() {
+        let tables = tables(TableLimits {
+            idle_timeout: Duration::from_millis(20),
+            ..Default::default()
+        });
+
+        let id = tables.create(TableOptions::default()).unwrap();
+
+        assert!(tables.get(id).is_some());
+        assert!(closed(&tables, id).await);
+    }
+
+    #[tokio::test]
+    async fn test_table_is_closed_when_last_seat_leaves() {
+        let tables = tables(TableLimits::default());
+        let id = tables.create(TableOptions::default()).unwrap();
+
+        let seat = tables
+            .get(id)
+            .unwrap()
+            .join("Alice".into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(tables.get(id).is_some());
+        drop(seat);
+
+        assert!(closed(&tables, id).await);
+    }
+
+    #[tokio::test]
+    async fn test_number_of_tables_is_capped() {
+        let tables = tables(TableLimits {
+            max_tables: 2,
+            ..Default::default()
+        });
+
+        assert!(tables.create(TableOptions::default()).is_some());
+        assert!(tables.create(TableOptions::default()).is_some());
+        assert!(tables.create(TableOptions::default()).is_none());
+    }
+}